@@ -1,4 +1,4 @@
-//! Render Markdown files to HTML.
+//! Render Markdown files to HTML, or extract their plain text.
 
 use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
 use comrak::nodes::{AstNode, NodeValue};
@@ -55,28 +55,9 @@ impl<'a> MarkdownRenderer<'a> {
 
     /// Renders the given markdown to HTML using the current settings.
     fn to_html(&self, text: &str) -> String {
-        use comrak::{
-            format_html, parse_document, Arena, ComrakExtensionOptions, ComrakOptions,
-            ComrakRenderOptions,
-        };
-
-        let options = ComrakOptions {
-            render: ComrakRenderOptions {
-                unsafe_: true, // The output will be sanitized with `ammonia`
-                ..ComrakRenderOptions::default()
-            },
-            extension: ComrakExtensionOptions {
-                autolink: true,
-                strikethrough: true,
-                table: true,
-                tagfilter: true,
-                tasklist: true,
-                header_ids: Some("user-content-".to_string()),
-                ..ComrakExtensionOptions::default()
-            },
-            ..ComrakOptions::default()
-        };
+        use comrak::{format_html, parse_document, Arena};
 
+        let options = comrak_options();
         let arena = Arena::new();
         let root = parse_document(&arena, text, &options);
 
@@ -101,6 +82,28 @@ impl<'a> MarkdownRenderer<'a> {
     }
 }
 
+/// Options used to parse and render Markdown with comrak.
+fn comrak_options() -> comrak::ComrakOptions {
+    use comrak::{ComrakExtensionOptions, ComrakOptions, ComrakRenderOptions};
+
+    ComrakOptions {
+        render: ComrakRenderOptions {
+            unsafe_: true, // The output will be sanitized with `ammonia`
+            ..ComrakRenderOptions::default()
+        },
+        extension: ComrakExtensionOptions {
+            autolink: true,
+            strikethrough: true,
+            table: true,
+            tagfilter: true,
+            tasklist: true,
+            header_ids: Some("user-content-".to_string()),
+            ..ComrakExtensionOptions::default()
+        },
+        ..ComrakOptions::default()
+    }
+}
+
 /// Iterate the nodes in the CommonMark AST, used in comrak.
 fn iter_nodes<'a, F>(node: &'a AstNode<'a>, f: &F)
 where
@@ -223,6 +226,42 @@ fn markdown_to_html(text: &str, base_url: Option<&str>, base_dir: &str) -> Strin
     renderer.to_html(text)
 }
 
+/// Extracts the text of Markdown, without markup, code blocks and raw HTML. Blocks are separated
+/// by new lines.
+fn markdown_to_plaintext(text: &str) -> String {
+    use comrak::{parse_document, Arena};
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, text, &comrak_options());
+    let mut output = String::new();
+    collect_text(root, &mut output);
+    output.trim().to_string()
+}
+
+fn collect_text<'a>(node: &'a AstNode<'a>, output: &mut String) {
+    let is_block = {
+        let ast = node.data.borrow();
+        match &ast.value {
+            NodeValue::Text(literal) => output.push_str(&String::from_utf8_lossy(literal)),
+            NodeValue::Code(code) => output.push_str(&String::from_utf8_lossy(&code.literal)),
+            NodeValue::SoftBreak | NodeValue::LineBreak | NodeValue::TableCell => output.push(' '),
+            NodeValue::CodeBlock(_) | NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) => {
+                return;
+            }
+            _ => {}
+        }
+        ast.value.block()
+    };
+
+    for child in node.children() {
+        collect_text(child, output);
+    }
+
+    if is_block && !output.ends_with('\n') {
+        output.push('\n');
+    }
+}
+
 /// Any file with a filename ending in one of these extensions will be rendered as Markdown.
 /// Note we also render a file as Markdown if _no_ extension is on the filename.
 static MARKDOWN_EXTENSIONS: [&str; 7] =
@@ -270,6 +309,34 @@ pub fn text_to_html(
     encode_minimal(text).replace('\n', "<br>\n")
 }
 
+/// Extracts the plain text of a text file, e.g. to index it for full text search. Markdown files
+/// are recognized the same way as in `text_to_html`, other files are returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use cargo_registry_markdown::text_to_plaintext;
+///
+/// let text = "# Rust\n\n[Rust](https://rust-lang.org/) is an awesome *systems programming* language!";
+/// let extracted = text_to_plaintext(text, "README.md");
+/// assert_eq!(extracted, "Rust\nRust is an awesome systems programming language!");
+/// ```
+pub fn text_to_plaintext(text: &str, readme_path_in_pkg: &str) -> String {
+    let is_markdown = match Path::new(readme_path_in_pkg).extension() {
+        None => true,
+        Some(ext) => ext
+            .to_str()
+            .map(|ext| MARKDOWN_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            .unwrap_or(false),
+    };
+
+    if is_markdown {
+        markdown_to_plaintext(text)
+    } else {
+        text.to_string()
+    }
+}
+
 /// Helper function to build a new `HashSet` from the items slice.
 fn hashset<T>(items: &[T]) -> std::collections::HashSet<T>
 where
@@ -464,6 +531,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn text_to_plaintext_strips_markup() {
+        let text = "# foo_readme\n\n\
+                    Some *emphasis* and `code`,\nover [two](https://example.com) lines.\n\n\
+                    ```rust\nfn main() {}\n```\n\n\
+                    <div>raw html</div>\n\n\
+                    - first\n- second\n";
+        assert_eq!(
+            text_to_plaintext(text, "README.md"),
+            "foo_readme\nSome emphasis and code, over two lines.\nfirst\nsecond"
+        );
+
+        assert_eq!(
+            text_to_plaintext("*not markdown*", "README.txt"),
+            "*not markdown*"
+        );
+    }
+
     #[test]
     fn text_to_html_renders_markdown() {
         for f in &[
@@ -65,6 +65,23 @@ impl Bucket {
             .map_err(Into::into)
     }
 
+    pub fn get(&self, client: &Client, path: &str) -> Result<Response, Error> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let date = Utc::now().to_rfc2822();
+        let auth = self.auth("GET", &date, path, "", "");
+        let url = self.url(path);
+
+        client
+            .get(&url)
+            .header(header::DATE, date)
+            .header(header::AUTHORIZATION, auth)
+            .header(header::USER_AGENT, "crates.io (https://crates.io)")
+            .timeout(Duration::from_secs(60))
+            .send()?
+            .error_for_status()
+            .map_err(Into::into)
+    }
+
     pub fn delete(&self, client: &Client, path: &str) -> Result<Response, Error> {
         let path = path.strip_prefix('/').unwrap_or(path);
         let date = Utc::now().to_rfc2822();
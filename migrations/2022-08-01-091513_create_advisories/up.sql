@@ -0,0 +1,21 @@
+CREATE TABLE advisories (
+    id VARCHAR PRIMARY KEY,
+    crate_name VARCHAR NOT NULL,
+    title VARCHAR NOT NULL,
+    description TEXT NOT NULL,
+    url VARCHAR,
+    date DATE NOT NULL,
+    patched TEXT[] NOT NULL DEFAULT '{}',
+    unaffected TEXT[] NOT NULL DEFAULT '{}',
+    imported_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX index_advisories_crate_name ON advisories (canon_crate_name(crate_name));
+
+CREATE TABLE version_advisories (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    advisory_id VARCHAR NOT NULL REFERENCES advisories (id) ON DELETE CASCADE,
+    PRIMARY KEY (version_id, advisory_id)
+);
+
+CREATE INDEX index_version_advisories_advisory_id ON version_advisories (advisory_id);
@@ -0,0 +1,5 @@
+ALTER TABLE versions
+    DROP COLUMN unpacked_size,
+    DROP COLUMN file_count,
+    DROP COLUMN largest_file_path,
+    DROP COLUMN largest_file_size;
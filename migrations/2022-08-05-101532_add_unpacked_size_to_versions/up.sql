@@ -0,0 +1,5 @@
+ALTER TABLE versions
+    ADD COLUMN unpacked_size BIGINT,
+    ADD COLUMN file_count INTEGER,
+    ADD COLUMN largest_file_path VARCHAR,
+    ADD COLUMN largest_file_size BIGINT;
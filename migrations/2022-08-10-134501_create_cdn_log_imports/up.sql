@@ -0,0 +1,6 @@
+CREATE TABLE cdn_log_imports (
+    path VARCHAR PRIMARY KEY,
+    format VARCHAR NOT NULL,
+    downloads INTEGER NOT NULL,
+    imported_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
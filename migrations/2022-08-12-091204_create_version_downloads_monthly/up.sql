@@ -0,0 +1,6 @@
+CREATE TABLE version_downloads_monthly (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    downloads BIGINT NOT NULL,
+    PRIMARY KEY (version_id, month)
+);
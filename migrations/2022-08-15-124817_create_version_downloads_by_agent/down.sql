@@ -0,0 +1 @@
+DROP TABLE version_downloads_by_agent;
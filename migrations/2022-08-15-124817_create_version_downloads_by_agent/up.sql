@@ -0,0 +1,7 @@
+CREATE TABLE version_downloads_by_agent (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    date DATE NOT NULL DEFAULT CURRENT_DATE,
+    agent_class VARCHAR NOT NULL,
+    downloads INTEGER NOT NULL,
+    PRIMARY KEY (version_id, date, agent_class)
+);
@@ -0,0 +1,3 @@
+DROP INDEX index_keywords_keyword_tgrm;
+DROP INDEX index_crates_name_squashed_tgrm;
+DROP FUNCTION squash_crate_name(text);
@@ -0,0 +1,10 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Separators split words for pg_trgm, which gives unrelated names sharing a word like `_sort` a
+-- high similarity, so they are removed before comparing names.
+CREATE FUNCTION squash_crate_name(text) RETURNS text AS $$
+    SELECT replace(canon_crate_name($1), '_', '')
+$$ LANGUAGE SQL IMMUTABLE;
+
+CREATE INDEX index_crates_name_squashed_tgrm ON crates USING gin (squash_crate_name(name) gin_trgm_ops);
+CREATE INDEX index_keywords_keyword_tgrm ON keywords USING gin (lower(keyword) gin_trgm_ops);
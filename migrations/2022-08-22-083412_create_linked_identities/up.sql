@@ -0,0 +1,20 @@
+CREATE TABLE linked_identities (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- Name of the identity provider, e.g. `github` or the name of an OIDC provider
+    provider VARCHAR NOT NULL,
+    -- Stable identifier of the user at the provider (the `sub` claim for OIDC providers)
+    subject VARCHAR NOT NULL,
+    login VARCHAR NOT NULL,
+    email VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_login_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (provider, subject)
+);
+
+CREATE INDEX index_linked_identities_user_id ON linked_identities (user_id);
+
+INSERT INTO linked_identities (user_id, provider, subject, login)
+SELECT id, 'github', gh_id::text, gh_login
+FROM users
+WHERE gh_id > 0;
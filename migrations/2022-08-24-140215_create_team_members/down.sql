@@ -0,0 +1,9 @@
+DROP TABLE team_members;
+
+DELETE FROM crate_owners
+WHERE owner_kind = 1
+AND owner_id IN (SELECT id FROM teams WHERE github_id IS NULL);
+
+DELETE FROM teams WHERE github_id IS NULL;
+
+ALTER TABLE teams ALTER COLUMN github_id SET NOT NULL;
@@ -0,0 +1,13 @@
+-- Registry-native teams are managed locally rather than on GitHub
+ALTER TABLE teams ALTER COLUMN github_id DROP NOT NULL;
+
+CREATE TABLE team_members (
+    team_id INTEGER NOT NULL REFERENCES teams (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- 0 = member, 1 = maintainer
+    role INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE INDEX index_team_members_user_id ON team_members (user_id);
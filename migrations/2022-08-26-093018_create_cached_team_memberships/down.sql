@@ -0,0 +1 @@
+DROP TABLE cached_team_memberships;
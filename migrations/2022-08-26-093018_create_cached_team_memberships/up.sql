@@ -0,0 +1,10 @@
+-- Results of the GitHub team membership checks, so that publishing doesn't depend on GitHub
+CREATE TABLE cached_team_memberships (
+    team_id INTEGER NOT NULL REFERENCES teams (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    is_member BOOLEAN NOT NULL,
+    checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (team_id, user_id)
+);
+
+CREATE INDEX index_cached_team_memberships_checked_at ON cached_team_memberships (checked_at);
@@ -0,0 +1,2 @@
+ALTER TABLE crate_owners DROP COLUMN role;
+ALTER TABLE crate_owner_invitations DROP COLUMN role;
@@ -0,0 +1,6 @@
+-- 0 = yank-only, 1 = publisher, 2 = admin
+ALTER TABLE crate_owners ADD COLUMN role INTEGER NOT NULL DEFAULT 2;
+ALTER TABLE crate_owner_invitations ADD COLUMN role INTEGER NOT NULL DEFAULT 2;
+
+-- Teams only ever had publish rights
+UPDATE crate_owners SET role = 1 WHERE owner_kind = 1;
@@ -0,0 +1,2 @@
+DROP TABLE crate_owner_actions;
+ALTER TABLE crate_owner_invitations DROP COLUMN transfer_from_user_id;
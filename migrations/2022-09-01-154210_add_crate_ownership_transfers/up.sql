@@ -0,0 +1,16 @@
+-- Invitations with a `transfer_from_user_id` remove that user as an owner once accepted
+ALTER TABLE crate_owner_invitations
+    ADD COLUMN transfer_from_user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE TABLE crate_owner_actions (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id),
+    -- 0 = transfer requested, 1 = owner added, 2 = owner removed
+    action INTEGER NOT NULL,
+    owner_id INTEGER NOT NULL,
+    owner_kind INTEGER NOT NULL,
+    time TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX index_crate_owner_actions_crate_id ON crate_owner_actions (crate_id);
@@ -0,0 +1 @@
+DROP TABLE crate_owner_email_invitations;
@@ -0,0 +1,16 @@
+-- Invitations sent to an email address that doesn't belong to a user yet. They're turned into
+-- regular `crate_owner_invitations` once a user verifies the email address.
+CREATE TABLE crate_owner_email_invitations (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    email VARCHAR NOT NULL,
+    invited_by_user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- 0 = yank-only, 1 = publisher, 2 = admin
+    role INTEGER NOT NULL DEFAULT 2,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX index_crate_owner_email_invitations_crate_id_email
+    ON crate_owner_email_invitations (crate_id, lower(email));
+CREATE INDEX index_crate_owner_email_invitations_email
+    ON crate_owner_email_invitations (lower(email));
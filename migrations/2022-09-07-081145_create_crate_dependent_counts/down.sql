@@ -0,0 +1,2 @@
+DROP FUNCTION refresh_crate_dependent_counts();
+DROP MATERIALIZED VIEW crate_dependent_counts;
@@ -0,0 +1,12 @@
+-- Number of crates with a non-yanked version depending on each crate, used to rank search results.
+CREATE MATERIALIZED VIEW crate_dependent_counts (crate_id, dependents) AS
+  SELECT dependencies.crate_id, COUNT(DISTINCT versions.crate_id) FROM dependencies
+    INNER JOIN versions
+      ON versions.id = dependencies.version_id
+    WHERE NOT versions.yanked
+    GROUP BY dependencies.crate_id;
+CREATE UNIQUE INDEX crate_dependent_counts_crate_id ON crate_dependent_counts (crate_id);
+
+CREATE FUNCTION refresh_crate_dependent_counts() RETURNS VOID AS $$
+  REFRESH MATERIALIZED VIEW CONCURRENTLY crate_dependent_counts;
+$$ LANGUAGE SQL;
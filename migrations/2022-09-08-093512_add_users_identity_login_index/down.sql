@@ -0,0 +1 @@
+DROP INDEX users_identity_login;
@@ -0,0 +1,3 @@
+-- Users created by logging in with an identity provider other than GitHub have a `gh_id` of 0 and
+-- must not share their login, since `User::find_by_login` can't tell them apart.
+CREATE UNIQUE INDEX users_identity_login ON users (lower(gh_login)) WHERE gh_id = 0;
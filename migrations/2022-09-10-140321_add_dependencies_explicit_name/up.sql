@@ -0,0 +1,2 @@
+-- The name of the dependency in the `Cargo.toml` of the depending crate, if it was renamed there.
+ALTER TABLE dependencies ADD COLUMN explicit_name VARCHAR;
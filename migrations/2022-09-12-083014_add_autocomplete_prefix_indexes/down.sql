@@ -0,0 +1,2 @@
+DROP INDEX index_keywords_lower_keyword_pattern;
+DROP INDEX index_crates_name_pattern;
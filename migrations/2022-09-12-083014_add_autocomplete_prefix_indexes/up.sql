@@ -0,0 +1,4 @@
+-- Indexes that can serve the `LIKE 'prefix%'` queries of the autocomplete endpoint, which the
+-- existing indexes on these expressions can't with a non-C collation.
+CREATE INDEX index_crates_name_pattern ON crates (canon_crate_name(name) text_pattern_ops);
+CREATE INDEX index_keywords_lower_keyword_pattern ON keywords (lower(keyword) text_pattern_ops);
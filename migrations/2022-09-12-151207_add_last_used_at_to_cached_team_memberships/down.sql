@@ -0,0 +1,2 @@
+DROP INDEX index_cached_team_memberships_last_used_at;
+ALTER TABLE cached_team_memberships DROP COLUMN last_used_at;
@@ -0,0 +1,4 @@
+-- Memberships that are refreshed in the background keep being checked, so whether they are still
+-- needed is tracked separately.
+ALTER TABLE cached_team_memberships ADD COLUMN last_used_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+CREATE INDEX index_cached_team_memberships_last_used_at ON cached_team_memberships (last_used_at);
@@ -0,0 +1,74 @@
+use crate::{
+    config, db,
+    schema::{crates, versions},
+};
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+use sha2::{Digest, Sha256};
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "backfill-checksums",
+    about = "Records the checksum of the versions that were published before checksums \
+        were stored in the database.",
+    after_help = "Warning: this downloads the crate file of every affected version and can \
+        take a lot of time."
+)]
+pub struct Opts {
+    /// How many versions should be queried and processed at a time.
+    #[clap(long, default_value = "100")]
+    batch_size: i64,
+
+    /// Only backfill the checksums of the specified crate.
+    #[clap(long = "crate")]
+    crate_name: Option<String>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let base_config = config::Base::from_environment();
+    let uploader = base_config.uploader();
+    let conn = db::oneoff_connection()?;
+    let client = Client::new();
+
+    let mut last_id = 0;
+    let mut backfilled = 0;
+    loop {
+        let mut query = versions::table
+            .inner_join(crates::table)
+            .filter(versions::checksum.is_null())
+            .filter(versions::id.gt(last_id))
+            .select((versions::id, crates::name, versions::num))
+            .order(versions::id)
+            .limit(opts.batch_size)
+            .into_boxed();
+        if let Some(crate_name) = &opts.crate_name {
+            query = query.filter(crates::name.eq(crate_name));
+        }
+
+        let batch: Vec<(i32, String, String)> = query.load(&conn)?;
+        match batch.last() {
+            Some((id, _, _)) => last_id = *id,
+            None => break,
+        }
+
+        for (id, crate_name, num) in batch {
+            let tarball = match uploader.download_crate(&client, &crate_name, &num)? {
+                Some(tarball) => tarball,
+                None => {
+                    println!("[{crate_name}-{num}] Crate file is missing, skipping");
+                    continue;
+                }
+            };
+
+            let checksum = hex::encode(Sha256::digest(&tarball));
+            diesel::update(versions::table.find(id))
+                .set(versions::checksum.eq(checksum))
+                .execute(&conn)?;
+            backfilled += 1;
+        }
+
+        println!("Backfilled {backfilled} checksums");
+    }
+
+    Ok(())
+}
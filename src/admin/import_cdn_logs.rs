@@ -0,0 +1,43 @@
+use crate::cdn_logs::LogFormat;
+use crate::schema::cdn_log_imports;
+use crate::{db, worker};
+
+use diesel::prelude::*;
+use swirl::Job;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "import-cdn-logs",
+    about = "Import download counts from CDN access logs.",
+    long_about = "Enqueue background jobs that count the crate downloads in CDN or mirror \
+        access logs and add them to the download counts. The log files are read from the \
+        configured storage, and files that were imported before are skipped."
+)]
+pub struct Opts {
+    /// Format of the log files
+    #[clap(long, value_enum)]
+    format: LogFormat,
+    /// Paths of the log files in storage
+    #[clap(required = true)]
+    paths: Vec<String>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = db::oneoff_connection()?;
+
+    let imported: Vec<String> = cdn_log_imports::table
+        .filter(cdn_log_imports::path.eq_any(&opts.paths))
+        .select(cdn_log_imports::path)
+        .load(&conn)?;
+
+    for path in opts.paths {
+        if imported.contains(&path) {
+            println!("Skipping {path}, it was already imported");
+            continue;
+        }
+        println!("Enqueueing import of {path}");
+        worker::import_cdn_log(path, opts.format).enqueue(&conn)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::db;
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    name = "largest-crates",
+    about = "List the crates with the largest unpacked size.",
+    long_about = "List the crates whose most recent non-yanked version has the largest \
+        unpacked size, together with their file count and largest file. Versions that were \
+        published before the unpacked size was recorded are ignored."
+)]
+pub struct Opts {
+    /// Number of crates to list
+    #[clap(long, default_value = "25")]
+    limit: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct LargeCrate {
+    #[sql_type = "Text"]
+    name: String,
+    #[sql_type = "Text"]
+    num: String,
+    #[sql_type = "BigInt"]
+    unpacked_size: i64,
+    #[sql_type = "Nullable<Integer>"]
+    file_count: Option<i32>,
+    #[sql_type = "Nullable<Text>"]
+    largest_file_path: Option<String>,
+    #[sql_type = "Nullable<BigInt>"]
+    largest_file_size: Option<i64>,
+}
+
+pub fn run(opts: Opts) -> anyhow::Result<()> {
+    let conn = db::oneoff_connection()?;
+
+    let crates: Vec<LargeCrate> = diesel::sql_query(include_str!("largest_crates.sql"))
+        .bind::<BigInt, _>(opts.limit)
+        .load(&conn)?;
+
+    println!(
+        "{:<40} {:<16} {:>14} {:>8}  largest file",
+        "crate", "version", "unpacked size", "files"
+    );
+    for krate in crates {
+        let largest_file = match (krate.largest_file_path, krate.largest_file_size) {
+            (Some(path), Some(size)) => format!("{path} ({size} bytes)"),
+            _ => String::new(),
+        };
+        println!(
+            "{:<40} {:<16} {:>14} {:>8}  {}",
+            krate.name,
+            krate.num,
+            krate.unpacked_size,
+            krate.file_count.unwrap_or_default(),
+            largest_file
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+SELECT crates.name, latest.num, latest.unpacked_size, latest.file_count,
+       latest.largest_file_path, latest.largest_file_size
+FROM (
+    SELECT DISTINCT ON (crate_id) *
+    FROM versions
+    WHERE NOT yanked
+      AND unpacked_size IS NOT NULL
+    ORDER BY crate_id, created_at DESC
+) latest
+INNER JOIN crates ON crates.id = latest.crate_id
+ORDER BY latest.unpacked_size DESC
+LIMIT $1
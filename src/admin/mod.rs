@@ -1,6 +1,9 @@
+pub mod backfill_checksums;
 pub mod delete_crate;
 pub mod delete_version;
 pub mod dialoguer;
+pub mod import_cdn_logs;
+pub mod largest_crates;
 pub mod migrate;
 pub mod on_call;
 pub mod populate;
@@ -7,7 +7,9 @@ use std::{sync::Arc, time::Duration};
 use crate::downloads_counter::DownloadsCounter;
 use crate::email::Emails;
 use crate::github::{GitHubClient, RealGitHubClient};
+use crate::identity::{IdentityProvider, OidcProvider};
 use crate::metrics::{InstanceMetrics, ServiceMetrics};
+use crate::views::EncodableAutocomplete;
 use diesel::r2d2;
 use moka::sync::{Cache, CacheBuilder};
 use oauth2::basic::BasicClient;
@@ -29,6 +31,9 @@ pub struct App {
     /// The GitHub OAuth2 configuration
     pub github_oauth: BasicClient,
 
+    /// Additional identity providers users can log in with, besides GitHub
+    pub identity_providers: Vec<Box<dyn IdentityProvider>>,
+
     /// The server configuration
     pub config: config::Server,
 
@@ -38,6 +43,12 @@ pub struct App {
     /// `version_id` is only cached under the canonical spelling of the crate name.
     pub(crate) version_id_cacher: Cache<(String, String), i32>,
 
+    /// Cache the completions of a `prefix` returned by the autocomplete endpoint, along with the
+    /// maximum number of results of each kind
+    ///
+    /// The cache is cleared when a crate is published.
+    pub(crate) autocomplete_cacher: Cache<(String, i64), EncodableAutocomplete>,
+
     /// Count downloads and periodically persist them in the database
     pub downloads_counter: DownloadsCounter,
 
@@ -86,6 +97,15 @@ impl App {
             ),
         );
 
+        let identity_providers = config
+            .oidc_providers
+            .iter()
+            .map(|provider| {
+                let client = http_client.clone().unwrap_or_else(Client::new);
+                Box::new(OidcProvider::new(provider.clone(), client)) as Box<dyn IdentityProvider>
+            })
+            .collect();
+
         let db_helper_threads = match (dotenv::var("DB_HELPER_THREADS"), config.env()) {
             (Ok(num), _) => num.parse().expect("couldn't parse DB_HELPER_THREADS"),
             (_, Env::Production) => 3,
@@ -160,17 +180,30 @@ impl App {
             None
         };
 
+        let downloads_counter = match &config.downloads_journal_path {
+            Some(path) => {
+                DownloadsCounter::with_journal(path).expect("could not open the downloads journal")
+            }
+            None => DownloadsCounter::new(),
+        };
+
         let version_id_cacher = CacheBuilder::new(config.version_id_cache_size)
             .time_to_live(config.version_id_cache_ttl)
             .build();
 
+        let autocomplete_cacher = CacheBuilder::new(config.autocomplete_cache_size)
+            .time_to_live(config.autocomplete_cache_ttl)
+            .build();
+
         App {
             primary_database,
             read_only_replica_database: replica_database,
             github,
             github_oauth,
+            identity_providers,
             version_id_cacher,
-            downloads_counter: DownloadsCounter::new(),
+            autocomplete_cacher,
+            downloads_counter,
             emails: Emails::from_environment(&config),
             service_metrics: ServiceMetrics::new().expect("could not initialize service metrics"),
             instance_metrics,
@@ -194,6 +227,14 @@ impl App {
             .expect("No HTTP client is configured.  In tests, use `TestApp::with_proxy()`.")
     }
 
+    /// Returns the identity provider named `name`, if it is configured.
+    pub fn identity_provider(&self, name: &str) -> Option<&dyn IdentityProvider> {
+        self.identity_providers
+            .iter()
+            .find(|provider| provider.name() == name)
+            .map(AsRef::as_ref)
+    }
+
     /// A unique key used with conduit_cookie to generate signed/encrypted cookies
     pub fn session_key(&self) -> &str {
         &self.config.session_key
@@ -7,19 +7,23 @@
 //! the worker thread), we will rebuild the runner and try again up to 5 times.
 //! After the 5th occurrence, we will panic.
 //!
+//! It also enqueues the `refresh_team_memberships` job every quarter of the
+//! team membership cache TTL, so that cached memberships are refreshed before
+//! they expire.
+//!
 //! Usage:
 //!      cargo run --bin background-worker
 
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::config;
-use cargo_registry::{background_jobs::*, db};
+use cargo_registry::{background_jobs::*, db, worker};
 use cargo_registry_index::{Repository, RepositoryConfig};
 use diesel::r2d2;
 use reqwest::blocking::Client;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 fn main() {
     println!("Booting runner");
@@ -69,8 +73,20 @@ fn main() {
     println!("Runner booted, running jobs");
 
     let mut failure_count = 0;
+    let refresh_interval = config.team_membership_cache.ttl / 4;
+    let mut last_refresh: Option<Instant> = None;
 
     loop {
+        if last_refresh.map_or(true, |last| last.elapsed() >= refresh_interval) {
+            last_refresh = Some(Instant::now());
+            let enqueued = db::oneoff_connection_with_config(&config.db)
+                .map_err(anyhow::Error::from)
+                .and_then(|conn| worker::enqueue_refresh_team_memberships(&conn, &config));
+            if let Err(e) = enqueued {
+                eprintln!("Error enqueueing refresh_team_memberships: {e:?}");
+            }
+        }
+
         if let Err(e) = runner.run_all_pending_jobs() {
             failure_count += 1;
             if failure_count < 5 {
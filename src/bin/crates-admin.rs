@@ -1,8 +1,9 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::admin::{
-    delete_crate, delete_version, migrate, populate, render_readmes, test_pagerduty,
-    transfer_crates, upload_index, verify_token, yank_version,
+    backfill_checksums, delete_crate, delete_version, import_cdn_logs, largest_crates, migrate,
+    populate, render_readmes, test_pagerduty, transfer_crates, upload_index, verify_token,
+    yank_version,
 };
 
 #[derive(clap::Parser, Debug)]
@@ -14,8 +15,11 @@ struct Opts {
 
 #[derive(clap::Parser, Debug)]
 enum SubCommand {
+    BackfillChecksums(backfill_checksums::Opts),
     DeleteCrate(delete_crate::Opts),
     DeleteVersion(delete_version::Opts),
+    ImportCdnLogs(import_cdn_logs::Opts),
+    LargestCrates(largest_crates::Opts),
     Populate(populate::Opts),
     RenderReadmes(render_readmes::Opts),
     TestPagerduty(test_pagerduty::Opts),
@@ -32,8 +36,11 @@ fn main() -> anyhow::Result<()> {
     let opts: Opts = Opts::parse();
 
     match opts.command {
+        SubCommand::BackfillChecksums(opts) => backfill_checksums::run(opts)?,
         SubCommand::DeleteCrate(opts) => delete_crate::run(opts),
         SubCommand::DeleteVersion(opts) => delete_version::run(opts),
+        SubCommand::ImportCdnLogs(opts) => import_cdn_logs::run(opts)?,
+        SubCommand::LargestCrates(opts) => largest_crates::run(opts)?,
         SubCommand::Populate(opts) => populate::run(opts),
         SubCommand::RenderReadmes(opts) => render_readmes::run(opts)?,
         SubCommand::TestPagerduty(opts) => test_pagerduty::run(opts)?,
@@ -1,7 +1,7 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 use anyhow::{anyhow, Result};
-use cargo_registry::{db, env, worker};
+use cargo_registry::{config, db, env, worker};
 use diesel::prelude::*;
 use swirl::schema::background_jobs::dsl::*;
 use swirl::Job;
@@ -36,6 +36,17 @@ fn main() -> Result<()> {
             Ok(worker::dump_db(database_url, target_name).enqueue(&conn)?)
         }
         "daily_db_maintenance" => Ok(worker::daily_db_maintenance().enqueue(&conn)?),
+        "import_advisories" => {
+            let path = args.next().unwrap_or_else(|| env("ADVISORY_DB_PATH"));
+            Ok(worker::import_advisories(path).enqueue(&conn)?)
+        }
+        "refresh_team_memberships" => {
+            let config = config::Server::default();
+            if !worker::enqueue_refresh_team_memberships(&conn, &config)? {
+                println!("Did not enqueue refresh_team_memberships, existing job already queued");
+            }
+            Ok(())
+        }
         "squash_index" => Ok(worker::squash_index().enqueue(&conn)?),
         other => Err(anyhow!("Unrecognized job type `{}`", other)),
     }
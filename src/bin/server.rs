@@ -137,6 +137,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Persisting remaining downloads counters");
     match app.downloads_counter.persist_all_shards(&app) {
         Ok(stats) => stats.log(),
+        Err(err) if app.config.downloads_journal_path.is_some() => println!(
+            "downloads_counter error: {err}, the remaining downloads are kept in the journal"
+        ),
         Err(err) => println!("downloads_counter error: {err}"),
     }
 
@@ -0,0 +1,333 @@
+//! Parsing of CDN and mirror access logs.
+//!
+//! Crate files served directly by a CDN or a mirror never reach the
+//! `download` endpoint, so their downloads are counted by importing the
+//! access logs instead. Only successful `GET` requests for paths of the form
+//! `crates/{name}/{name}-{version}.crate` are counted.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use flate2::read::GzDecoder;
+
+/// The supported access log formats.
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// CloudFront standard logs, tab separated with a `#Fields:` header.
+    Cloudfront,
+    /// Fastly logs with one JSON object per line, containing at least the
+    /// `timestamp`, `url` and `status` fields. A syslog prefix is ignored.
+    Fastly,
+    /// The NCSA combined log format used by Apache and nginx.
+    Combined,
+}
+
+impl LogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogFormat::Cloudfront => "cloudfront",
+            LogFormat::Fastly => "fastly",
+            LogFormat::Combined => "combined",
+        }
+    }
+}
+
+/// The number of downloads per crate name, version and day.
+pub type DownloadCounts = HashMap<(String, String, NaiveDate), i32>;
+
+#[derive(Debug, Default)]
+pub struct ParsedLog {
+    pub downloads: DownloadCounts,
+    /// Lines that could not be parsed. Comments and empty lines are not
+    /// counted.
+    pub invalid_lines: usize,
+}
+
+/// A single request in an access log.
+#[derive(Debug, PartialEq, Eq)]
+struct Request<'a> {
+    date: NaiveDate,
+    method: Option<Cow<'a, str>>,
+    path: Cow<'a, str>,
+    status: u16,
+}
+
+/// Positions of the relevant fields in CloudFront logs, which can be changed
+/// by a `#Fields:` header.
+struct CloudfrontFields {
+    date: usize,
+    method: usize,
+    path: usize,
+    status: usize,
+}
+
+impl Default for CloudfrontFields {
+    fn default() -> Self {
+        Self {
+            date: 0,
+            method: 5,
+            path: 7,
+            status: 8,
+        }
+    }
+}
+
+impl CloudfrontFields {
+    fn from_header(header: &str) -> Self {
+        let mut fields = Self::default();
+        for (i, name) in header.split_whitespace().enumerate() {
+            match name {
+                "date" => fields.date = i,
+                "cs-method" => fields.method = i,
+                "cs-uri-stem" => fields.path = i,
+                "sc-status" => fields.status = i,
+                _ => {}
+            }
+        }
+        fields
+    }
+}
+
+/// Decompresses gzip compressed logs. Uncompressed logs are returned as is.
+pub fn decompress(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    if !content.starts_with(&[0x1f, 0x8b]) {
+        return Ok(content.to_vec());
+    }
+    let mut decompressed = Vec::new();
+    GzDecoder::new(content).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+/// Counts the crate downloads in an access log.
+pub fn parse(format: LogFormat, content: &str) -> ParsedLog {
+    let mut log = ParsedLog::default();
+    let mut cloudfront_fields = CloudfrontFields::default();
+
+    for line in content.lines() {
+        if format == LogFormat::Cloudfront {
+            if let Some(header) = line.strip_prefix("#Fields:") {
+                cloudfront_fields = CloudfrontFields::from_header(header);
+                continue;
+            }
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let request = match format {
+            LogFormat::Cloudfront => parse_cloudfront_line(line, &cloudfront_fields),
+            LogFormat::Fastly => parse_fastly_line(line),
+            LogFormat::Combined => parse_combined_line(line),
+        };
+        let request = match request {
+            Some(request) => request,
+            None => {
+                log.invalid_lines += 1;
+                continue;
+            }
+        };
+
+        let is_get = request.method.map_or(true, |method| method == "GET");
+        if !is_get || request.status != 200 {
+            continue;
+        }
+        if let Some((name, version)) = crate_download(&request.path) {
+            *log.downloads
+                .entry((name, version, request.date))
+                .or_default() += 1;
+        }
+    }
+
+    log
+}
+
+fn parse_cloudfront_line<'a>(line: &'a str, fields: &CloudfrontFields) -> Option<Request<'a>> {
+    let values = line.split('\t').collect::<Vec<_>>();
+    Some(Request {
+        date: NaiveDate::parse_from_str(values.get(fields.date)?, "%Y-%m-%d").ok()?,
+        method: Some(Cow::Borrowed(values.get(fields.method)?)),
+        path: Cow::Borrowed(values.get(fields.path)?),
+        status: values.get(fields.status)?.parse().ok()?,
+    })
+}
+
+fn parse_fastly_line(line: &str) -> Option<Request<'_>> {
+    #[derive(Deserialize)]
+    struct FastlyLine<'a> {
+        timestamp: DateTime<FixedOffset>,
+        #[serde(borrow)]
+        method: Option<Cow<'a, str>>,
+        #[serde(borrow)]
+        url: Cow<'a, str>,
+        status: u16,
+    }
+
+    let json = &line[line.find('{')?..];
+    let line: FastlyLine<'_> = serde_json::from_str(json).ok()?;
+    Some(Request {
+        date: line.timestamp.naive_utc().date(),
+        method: line.method,
+        path: line.url,
+        status: line.status,
+    })
+}
+
+/// Parses lines like
+/// `127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET /path HTTP/1.1" 200 2326 "referer" "user agent"`.
+fn parse_combined_line(line: &str) -> Option<Request<'_>> {
+    let (_, rest) = line.split_once('[')?;
+    let (timestamp, rest) = rest.split_once(']')?;
+    let timestamp = DateTime::parse_from_str(timestamp, "%d/%b/%Y:%H:%M:%S %z").ok()?;
+
+    let (_, rest) = rest.split_once('"')?;
+    let (request_line, rest) = rest.split_once('"')?;
+    let mut request_line = request_line.split_whitespace();
+    let method = request_line.next()?;
+    let path = request_line.next()?;
+
+    let status = rest.split_whitespace().next()?.parse().ok()?;
+
+    Some(Request {
+        date: timestamp.naive_utc().date(),
+        method: Some(Cow::Borrowed(method)),
+        path: Cow::Borrowed(path),
+        status,
+    })
+}
+
+/// Returns the crate name and version if `path` is the path of a crate file.
+fn crate_download(path: &str) -> Option<(String, String)> {
+    let path = path.split('?').next()?;
+    let path = percent_decode(path)?;
+
+    let mut segments = path.trim_start_matches('/').split('/');
+    if segments.next()? != "crates" {
+        return None;
+    }
+    let name = segments.next()?;
+    let file = segments.next()?;
+    if segments.next().is_some() {
+        return None;
+    }
+
+    let version = file
+        .strip_prefix(name)?
+        .strip_prefix('-')?
+        .strip_suffix(".crate")?;
+    semver::Version::parse(version).ok()?;
+
+    Some((name.to_string(), version.to_string()))
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2022, 8, day)
+    }
+
+    fn downloads(log: &ParsedLog) -> Vec<(&str, &str, NaiveDate, i32)> {
+        let mut downloads = log
+            .downloads
+            .iter()
+            .map(|((name, version, date), count)| (name.as_str(), version.as_str(), *date, *count))
+            .collect::<Vec<_>>();
+        downloads.sort();
+        downloads
+    }
+
+    #[test]
+    fn crate_paths() {
+        assert_eq!(
+            crate_download("/crates/foo/foo-1.0.0.crate"),
+            Some(("foo".to_string(), "1.0.0".to_string()))
+        );
+        assert_eq!(
+            crate_download("crates/foo-bar/foo-bar-1.0.0%2Bbuild.crate?x=1"),
+            Some(("foo-bar".to_string(), "1.0.0+build".to_string()))
+        );
+        assert_none!(crate_download("/crates/foo/bar-1.0.0.crate"));
+        assert_none!(crate_download("/crates/foo/foo-latest.crate"));
+        assert_none!(crate_download("/readmes/foo/foo-1.0.0.html"));
+        assert_none!(crate_download("/crates/foo/foo/foo-1.0.0.crate"));
+    }
+
+    #[test]
+    fn cloudfront() {
+        let content = "#Version: 1.0\n\
+            #Fields: date time x-edge-location sc-bytes c-ip cs-method cs(Host) cs-uri-stem sc-status\n\
+            2022-08-08\t08:30:12\tFRA2\t1000\t1.2.3.4\tGET\tstatic.crates.io\t/crates/foo/foo-1.0.0.crate\t200\n\
+            2022-08-08\t08:30:13\tFRA2\t1000\t1.2.3.4\tGET\tstatic.crates.io\t/crates/foo/foo-1.0.0.crate\t200\n\
+            2022-08-09\t00:00:01\tFRA2\t1000\t1.2.3.4\tGET\tstatic.crates.io\t/crates/foo/foo-1.0.0.crate\t200\n\
+            2022-08-09\t00:00:02\tFRA2\t1000\t1.2.3.4\tHEAD\tstatic.crates.io\t/crates/foo/foo-1.0.0.crate\t200\n\
+            2022-08-09\t00:00:03\tFRA2\t1000\t1.2.3.4\tGET\tstatic.crates.io\t/crates/foo/foo-2.0.0.crate\t404\n\
+            garbage\n";
+
+        let log = parse(LogFormat::Cloudfront, content);
+        assert_eq!(
+            downloads(&log),
+            vec![("foo", "1.0.0", date(8), 2), ("foo", "1.0.0", date(9), 1)]
+        );
+        assert_eq!(log.invalid_lines, 1);
+    }
+
+    #[test]
+    fn fastly() {
+        let content = r#"<134>2022-08-08T08:30:12Z cache-fra1 crates[1]: {"timestamp":"2022-08-08T08:30:12+00:00","method":"GET","url":"/crates/bar/bar-0.1.0.crate","status":200}
+{"timestamp":"2022-08-08T23:30:12-02:00","url":"/crates/bar/bar-0.1.0.crate","status":200}
+{"timestamp":"2022-08-08T08:30:12+00:00","url":"/crates/bar/bar-0.1.0.crate","status":304}
+"#;
+
+        let log = parse(LogFormat::Fastly, content);
+        assert_eq!(
+            downloads(&log),
+            vec![("bar", "0.1.0", date(8), 1), ("bar", "0.1.0", date(9), 1)]
+        );
+        assert_eq!(log.invalid_lines, 0);
+    }
+
+    #[test]
+    fn combined() {
+        let content = r#"1.2.3.4 - - [08/Aug/2022:08:30:12 +0000] "GET /crates/baz/baz-3.0.0-beta.1.crate HTTP/1.1" 200 2326 "-" "cargo 1.62.0"
+1.2.3.4 - - [08/Aug/2022:08:30:12 +0000] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/7.79.1"
+1.2.3.4 - - [08/Aug/2022:08:30:12 +0000] "GET /crates/baz/baz-3.0.0-beta.1.crate HTTP/1.1" 206 2326 "-" "cargo 1.62.0"
+"#;
+
+        let log = parse(LogFormat::Combined, content);
+        assert_eq!(downloads(&log), vec![("baz", "3.0.0-beta.1", date(8), 1)]);
+    }
+
+    #[test]
+    fn decompress_gzip() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"hello").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decompress(&compressed).unwrap(), b"hello");
+        assert_eq!(decompress(b"hello").unwrap(), b"hello");
+    }
+}
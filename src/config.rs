@@ -1,19 +1,27 @@
 use anyhow::{anyhow, Context};
 use ipnetwork::IpNetwork;
 
+use crate::identity::OidcProviderConfig;
 use crate::publish_rate_limit::PublishRateLimit;
 use crate::{env, env_optional, uploaders::Uploader, Env};
 
 mod base;
 mod database_pools;
+mod search_ranking;
+mod team_membership_cache;
 
 pub use self::base::Base;
 pub use self::database_pools::{DatabasePools, DbPoolConfig};
+pub use self::search_ranking::SearchRankingWeights;
+pub use self::team_membership_cache::TeamMembershipCacheConfig;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::time::Duration;
 
 const DEFAULT_VERSION_ID_CACHE_SIZE: u64 = 10_000;
 const DEFAULT_VERSION_ID_CACHE_TTL: u64 = 5 * 60; // 5 minutes
+const DEFAULT_AUTOCOMPLETE_CACHE_SIZE: u64 = 10_000;
+const DEFAULT_AUTOCOMPLETE_CACHE_TTL: u64 = 10 * 60; // 10 minutes
 
 pub struct Server {
     pub base: Base,
@@ -22,9 +30,13 @@ pub struct Server {
     pub gh_client_id: String,
     pub gh_client_secret: String,
     pub gh_base_url: String,
+    pub oidc_providers: Vec<OidcProviderConfig>,
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
     pub publish_rate_limit: PublishRateLimit,
+    pub search_ranking_weights: SearchRankingWeights,
+    pub gh_admin_user_ids: HashSet<i32>,
+    pub team_membership_cache: TeamMembershipCacheConfig,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub max_allowed_page_offset: u32,
     pub page_offset_ua_blocklist: Vec<String>,
@@ -33,6 +45,7 @@ pub struct Server {
     pub domain_name: String,
     pub allowed_origins: Vec<String>,
     pub downloads_persist_interval_ms: usize,
+    pub downloads_journal_path: Option<PathBuf>,
     pub ownership_invitations_expiration_days: u64,
     pub metrics_authorization_token: Option<String>,
     pub use_test_database_pool: bool,
@@ -41,6 +54,8 @@ pub struct Server {
     pub blocked_routes: HashSet<String>,
     pub version_id_cache_size: u64,
     pub version_id_cache_ttl: Duration,
+    pub autocomplete_cache_size: u64,
+    pub autocomplete_cache_ttl: Duration,
 }
 
 impl Default for Server {
@@ -56,9 +71,14 @@ impl Default for Server {
     /// - `SESSION_KEY`: The key used to sign and encrypt session cookies.
     /// - `GH_CLIENT_ID`: The client ID of the associated GitHub application.
     /// - `GH_CLIENT_SECRET`: The client secret of the associated GitHub application.
+    /// - `OIDC_PROVIDERS`: A comma separated list of additional OpenID Connect identity providers
+    ///   users can log in with. See `OidcProviderConfig::all_from_environment` for the variables
+    ///   configuring each provider.
     /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
     ///   traffic. See the `block_traffic` module for more documentation.
     /// - `DOWNLOADS_PERSIST_INTERVAL_MS`: how frequent to persist download counts (in ms).
+    /// - `DOWNLOADS_JOURNAL_PATH`: path of a local journal recording the download counts that are
+    ///   not yet persisted, so that they survive restarts. If not set, no journal is kept.
     /// - `METRICS_AUTHORIZATION_TOKEN`: authorization token needed to query metrics. If missing,
     ///   querying metrics will be completely disabled.
     /// - `WEB_MAX_ALLOWED_PAGE_OFFSET`: Page offsets larger than this value are rejected. Defaults
@@ -76,6 +96,12 @@ impl Default for Server {
     ///   endpoint even with a healthy database pool.
     /// - `BLOCKED_ROUTES`: A comma separated list of HTTP route patterns that are manually blocked
     ///   by an operator (e.g. `/crates/:crate_id/:version/download`).
+    /// - `SEARCH_RANKING_WEIGHT_*`: weights of the relevance score of search results. See
+    ///   `SearchRankingWeights` for the list of variables.
+    /// - `GH_ADMIN_USER_IDS`: A comma separated list of the GitHub user IDs of the crates.io
+    ///   administrators.
+    /// - `TEAM_MEMBERSHIP_CACHE_*`: how long GitHub team memberships are cached. See
+    ///   `TeamMembershipCacheConfig` for the list of variables.
     ///
     /// # Panics
     ///
@@ -115,9 +141,19 @@ impl Default for Server {
             gh_client_id: env("GH_CLIENT_ID"),
             gh_client_secret: env("GH_CLIENT_SECRET"),
             gh_base_url: "https://api.github.com".to_string(),
+            oidc_providers: OidcProviderConfig::all_from_environment(),
             max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
             max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
             publish_rate_limit: Default::default(),
+            search_ranking_weights: Default::default(),
+            gh_admin_user_ids: env_optional("GH_ADMIN_USER_IDS")
+                .map(|ids: String| {
+                    ids.split(',')
+                        .map(|id| id.trim().parse().expect("invalid GH_ADMIN_USER_IDS"))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            team_membership_cache: Default::default(),
             blocked_traffic: blocked_traffic(),
             max_allowed_page_offset: env_optional("WEB_MAX_ALLOWED_PAGE_OFFSET").unwrap_or(200),
             page_offset_ua_blocklist,
@@ -132,6 +168,7 @@ impl Default for Server {
                         .expect("invalid DOWNLOADS_PERSIST_INTERVAL_MS")
                 })
                 .unwrap_or(60_000), // 1 minute
+            downloads_journal_path: env_optional("DOWNLOADS_JOURNAL_PATH"),
             ownership_invitations_expiration_days: 30,
             metrics_authorization_token: dotenv::var("METRICS_AUTHORIZATION_TOKEN").ok(),
             use_test_database_pool: false,
@@ -145,6 +182,11 @@ impl Default for Server {
             version_id_cache_ttl: Duration::from_secs(
                 env_optional("VERSION_ID_CACHE_TTL").unwrap_or(DEFAULT_VERSION_ID_CACHE_TTL),
             ),
+            autocomplete_cache_size: env_optional("AUTOCOMPLETE_CACHE_SIZE")
+                .unwrap_or(DEFAULT_AUTOCOMPLETE_CACHE_SIZE),
+            autocomplete_cache_ttl: Duration::from_secs(
+                env_optional("AUTOCOMPLETE_CACHE_TTL").unwrap_or(DEFAULT_AUTOCOMPLETE_CACHE_TTL),
+            ),
         }
     }
 }
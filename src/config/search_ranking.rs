@@ -0,0 +1,35 @@
+use crate::env_optional;
+
+/// Weights of the components of the relevance score used to order search results.
+///
+/// Each weight can be overridden with an environment variable:
+///
+/// - `SEARCH_RANKING_WEIGHT_TEXT`: full text rank of the query (`ts_rank_cd`).
+/// - `SEARCH_RANKING_WEIGHT_NAME_SIMILARITY`: trigram similarity of the crate name and the query.
+/// - `SEARCH_RANKING_WEIGHT_RECENT_DOWNLOADS`: logarithm of the downloads of the last 90 days.
+/// - `SEARCH_RANKING_WEIGHT_REVERSE_DEPENDENCIES`: logarithm of the number of crates depending on
+///   the crate.
+/// - `SEARCH_RANKING_WEIGHT_RECENCY`: decays from 1 with the number of months since the last
+///   update of the crate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchRankingWeights {
+    pub text: f64,
+    pub name_similarity: f64,
+    pub recent_downloads: f64,
+    pub reverse_dependencies: f64,
+    pub recency: f64,
+}
+
+impl Default for SearchRankingWeights {
+    fn default() -> Self {
+        Self {
+            text: env_optional("SEARCH_RANKING_WEIGHT_TEXT").unwrap_or(1.0),
+            name_similarity: env_optional("SEARCH_RANKING_WEIGHT_NAME_SIMILARITY").unwrap_or(0.1),
+            recent_downloads: env_optional("SEARCH_RANKING_WEIGHT_RECENT_DOWNLOADS")
+                .unwrap_or(0.02),
+            reverse_dependencies: env_optional("SEARCH_RANKING_WEIGHT_REVERSE_DEPENDENCIES")
+                .unwrap_or(0.02),
+            recency: env_optional("SEARCH_RANKING_WEIGHT_RECENCY").unwrap_or(0.05),
+        }
+    }
+}
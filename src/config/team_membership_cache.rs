@@ -0,0 +1,28 @@
+use crate::env_optional;
+use std::time::Duration;
+
+/// How long the results of GitHub team membership checks are cached in the
+/// `cached_team_memberships` table.
+///
+/// Both durations can be overridden with an environment variable, in seconds:
+///
+/// - `TEAM_MEMBERSHIP_CACHE_TTL`: how long a result is used before GitHub is asked again.
+///   Defaults to 1 hour.
+/// - `TEAM_MEMBERSHIP_CACHE_MAX_STALENESS`: how long an expired result is still used when GitHub
+///   is unreachable. Defaults to 1 day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TeamMembershipCacheConfig {
+    pub ttl: Duration,
+    pub max_staleness: Duration,
+}
+
+impl Default for TeamMembershipCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(env_optional("TEAM_MEMBERSHIP_CACHE_TTL").unwrap_or(60 * 60)),
+            max_staleness: Duration::from_secs(
+                env_optional("TEAM_MEMBERSHIP_CACHE_MAX_STALENESS").unwrap_or(24 * 60 * 60),
+            ),
+        }
+    }
+}
@@ -76,6 +76,7 @@ mod prelude {
 pub mod helpers;
 mod util;
 
+pub mod autocomplete;
 pub mod category;
 pub mod crate_owner_invitation;
 pub mod keyword;
@@ -0,0 +1,116 @@
+//! Endpoint for the type-ahead completion of crate names, keywords and categories
+
+use super::frontend_prelude::*;
+
+use crate::schema::{categories, crates, keywords};
+use crate::sql::{canon_crate_name, lower};
+use crate::views::EncodableAutocomplete;
+
+/// Number of results of each kind returned when no `limit` is given.
+const DEFAULT_LIMIT: i64 = 10;
+
+/// Maximum `limit` accepted by the endpoint.
+const MAX_LIMIT: i64 = 50;
+
+/// Handles the `GET /autocomplete` route.
+///
+/// Returns the crate names, keywords and category slugs starting with the `q` prefix, with the
+/// most downloaded crates and the keywords and categories with the most crates first. At most
+/// `limit` results of each kind are returned.
+///
+/// The results are cached in memory, and the cache is cleared whenever a crate is published.
+pub fn autocomplete(req: &mut dyn RequestExt) -> EndpointResult {
+    let params = req.query();
+    let prefix = params
+        .get("q")
+        .map(|q| q.trim().to_lowercase())
+        .unwrap_or_default();
+    if prefix.is_empty() {
+        return Err(bad_request("missing or empty `q` parameter"));
+    }
+
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse::<i64>()
+            .ok()
+            .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+            .ok_or_else(|| bad_request(&format!("`limit` must be between 1 and {MAX_LIMIT}")))?,
+        None => DEFAULT_LIMIT,
+    };
+
+    let cache_key = (prefix, limit);
+    let app = req.app();
+    let completions = match app.autocomplete_cacher.get(&cache_key) {
+        Some(completions) => completions,
+        None => {
+            let conn = req.db_read()?;
+            let completions = load_completions(&conn, &cache_key.0, limit)?;
+            app.autocomplete_cacher
+                .insert(cache_key, completions.clone());
+            completions
+        }
+    };
+
+    Ok(req.json(&completions))
+}
+
+fn load_completions(
+    conn: &PgConnection,
+    prefix: &str,
+    limit: i64,
+) -> QueryResult<EncodableAutocomplete> {
+    // The prefixes of crate names and keywords are matched with the `text_pattern_ops` indexes
+    // on `canon_crate_name(name)` and `lower(keyword)`. There are only a few categories, so they
+    // are not indexed.
+    let crates = crates::table
+        .filter(canon_crate_name(crates::name).like(prefix_pattern(&prefix.replace('-', "_"))))
+        .order((crates::downloads.desc(), crates::name.asc()))
+        .select(crates::name)
+        .limit(limit)
+        .load(conn)?;
+
+    let keywords = keywords::table
+        .filter(lower(keywords::keyword).like(prefix_pattern(prefix)))
+        .order((keywords::crates_cnt.desc(), keywords::keyword.asc()))
+        .select(keywords::keyword)
+        .limit(limit)
+        .load(conn)?;
+
+    let categories = categories::table
+        .filter(lower(categories::slug).like(prefix_pattern(prefix)))
+        .order((categories::crates_cnt.desc(), categories::slug.asc()))
+        .select(categories::slug)
+        .limit(limit)
+        .load(conn)?;
+
+    Ok(EncodableAutocomplete {
+        crates,
+        keywords,
+        categories,
+    })
+}
+
+/// Returns a `LIKE` pattern matching the strings starting with `prefix`.
+fn prefix_pattern(prefix: &str) -> String {
+    let mut pattern = String::with_capacity(prefix.len() + 1);
+    for c in prefix.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(prefix_pattern("serde"), "serde%");
+        assert_eq!(prefix_pattern("serde_j"), "serde\\_j%");
+        assert_eq!(prefix_pattern("100%\\"), "100\\%\\\\%");
+    }
+}
@@ -1,14 +1,16 @@
 use super::frontend_prelude::*;
 
+use crate::config;
 use crate::controllers::helpers::pagination::{Page, PaginationOptions};
 use crate::controllers::util::AuthenticatedUser;
-use crate::models::{Crate, CrateOwnerInvitation, Rights, User};
+use crate::models::{Crate, CrateOwnerInvitation, Rights, User, WebhookEvent, WebhookPayload};
 use crate::schema::{crate_owner_invitations, crates, users};
 use crate::util::errors::{forbidden, internal};
 use crate::views::{
     EncodableCrateOwnerInvitation, EncodableCrateOwnerInvitationV1, EncodablePublicUser,
     InvitationResponse,
 };
+use crate::worker;
 use chrono::{Duration, Utc};
 use diesel::{pg::Pg, sql_types::Bool};
 use indexmap::IndexMap;
@@ -94,8 +96,8 @@ fn prepare_list(
             ListFilter::CrateName(crate_name) => {
                 // Only allow crate owners to query pending invitations for their crate.
                 let krate: Crate = Crate::by_name(&crate_name).first(&*conn)?;
-                let owners = krate.owners(&*conn)?;
-                if user.rights(req.app(), &owners)? != Rights::Full {
+                let owners = krate.owners_with_roles(&*conn)?;
+                if user.rights(req.app(), &*conn, &owners)? != Rights::Full {
                     return Err(forbidden());
                 }
 
@@ -261,7 +263,7 @@ pub fn handle_invite(req: &mut dyn RequestExt) -> EndpointResult {
 
     let invitation = CrateOwnerInvitation::find_by_id(user_id, crate_invite.crate_id, conn)?;
     if crate_invite.accepted {
-        invitation.accept(conn, config)?;
+        accept_and_notify(conn, config, invitation)?;
     } else {
         invitation.decline(conn)?;
     }
@@ -277,7 +279,7 @@ pub fn handle_invite_with_token(req: &mut dyn RequestExt) -> EndpointResult {
 
     let invitation = CrateOwnerInvitation::find_by_token(req_token, &conn)?;
     let crate_id = invitation.crate_id;
-    invitation.accept(&conn, config)?;
+    accept_and_notify(&conn, config, invitation)?;
 
     Ok(req.json(&json!({
         "crate_owner_invitation": {
@@ -286,3 +288,54 @@ pub fn handle_invite_with_token(req: &mut dyn RequestExt) -> EndpointResult {
         },
     })))
 }
+
+/// Accepts the invitation and enqueues the webhook deliveries for the new owner, and for the
+/// previous owner if the invitation was an ownership transfer. Both happen in one transaction,
+/// so the deliveries are only sent once the acceptance is committed, and can't get lost.
+fn accept_and_notify(
+    conn: &PgConnection,
+    config: &config::Server,
+    invitation: CrateOwnerInvitation,
+) -> AppResult<()> {
+    conn.transaction(|| {
+        let crate_id = invitation.crate_id;
+        let user_id = invitation.invited_user_id;
+        let transfer_from_user_id = invitation.transfer_from_user_id;
+        invitation.accept(conn, config)?;
+        notify_owner_added(conn, crate_id, user_id, transfer_from_user_id)
+    })
+}
+
+/// Notifies the webhooks of the crate that the invited user accepted the
+/// invitation and is now an owner, replacing the previous owner if the
+/// invitation was an ownership transfer.
+fn notify_owner_added(
+    conn: &PgConnection,
+    crate_id: i32,
+    user_id: i32,
+    transfer_from_user_id: Option<i32>,
+) -> AppResult<()> {
+    let crate_name: String = crates::table
+        .find(crate_id)
+        .select(crates::name)
+        .first(conn)?;
+    let login: String = users::table
+        .find(user_id)
+        .select(users::gh_login)
+        .first(conn)?;
+
+    let payload =
+        WebhookPayload::new(WebhookEvent::OwnerAdd, &crate_name, &login).with_owner(&login);
+    worker::notify_webhooks(conn, crate_id, &payload)?;
+
+    if let Some(previous_owner_id) = transfer_from_user_id {
+        let previous_login: String = users::table
+            .find(previous_owner_id)
+            .select(users::gh_login)
+            .first(conn)?;
+        let payload = WebhookPayload::new(WebhookEvent::OwnerRemove, &crate_name, &login)
+            .with_owner(&previous_login);
+        worker::notify_webhooks(conn, crate_id, &payload)?;
+    }
+    Ok(())
+}
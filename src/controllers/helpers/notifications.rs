@@ -0,0 +1,66 @@
+use diesel::prelude::*;
+
+use crate::email::{Emails, VersionNotification};
+use crate::models::OwnerKind;
+use crate::schema::{api_tokens, crate_owners, emails, users};
+use crate::util::errors::AppResult;
+
+/// A notification about a change to a version, along with the owners it is
+/// sent to. It is collected in the transaction making the change, and only
+/// sent once that transaction is committed, so that owners are never notified
+/// about a change that was rolled back.
+#[derive(Debug)]
+pub(crate) struct OwnerNotification {
+    recipients: Vec<(String, String)>,
+    notification: VersionNotification,
+}
+
+impl OwnerNotification {
+    /// Collects all user owners of the crate that have a verified email address
+    /// and email notifications enabled for it.
+    pub(crate) fn new(
+        conn: &PgConnection,
+        crate_id: i32,
+        notification: VersionNotification,
+    ) -> AppResult<Self> {
+        let recipients = crate_owners::table
+            .filter(crate_owners::crate_id.eq(crate_id))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .filter(crate_owners::email_notifications.eq(true))
+            .inner_join(users::table.inner_join(emails::table))
+            .filter(emails::verified.eq(true))
+            .select((users::gh_login, emails::email))
+            .load(conn)?;
+
+        Ok(Self {
+            recipients,
+            notification,
+        })
+    }
+
+    /// Sends the notification to the owners.
+    ///
+    /// Failing to send an individual email is not an error, so that a broken
+    /// mail server can't block publishing or yanking.
+    pub(crate) fn send(&self, emails: &Emails) {
+        for (login, email) in &self.recipients {
+            let _ = emails.send_version_notification(email, login, &self.notification);
+        }
+    }
+}
+
+/// Returns the name of the API token with the given id, if any.
+pub(crate) fn token_name(
+    conn: &PgConnection,
+    api_token_id: Option<i32>,
+) -> AppResult<Option<String>> {
+    Ok(api_token_id
+        .map(|id| {
+            api_tokens::table
+                .find(id)
+                .select(api_tokens::name)
+                .first(conn)
+        })
+        .transpose()?)
+}
@@ -1,6 +1,8 @@
+pub mod advisories;
 pub mod downloads;
 pub mod follow;
 pub mod metadata;
 pub mod owners;
 pub mod publish;
 pub mod search;
+pub mod webhooks;
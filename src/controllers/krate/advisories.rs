@@ -0,0 +1,40 @@
+//! Endpoint for exposing the security advisories of a crate
+//!
+//! Advisories are imported from a RustSec-format advisory database by the
+//! `import_advisories` background job.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Advisory, Crate};
+use crate::schema::{version_advisories, versions};
+use crate::views::EncodableAdvisory;
+
+/// Handles the `GET /crates/:crate_id/advisories` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    let advisories = Advisory::for_crate(&conn, &krate)?;
+
+    let affected: Vec<(String, String)> = version_advisories::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq(krate.id))
+        .select((version_advisories::advisory_id, versions::num))
+        .load(&*conn)?;
+
+    let advisories = advisories
+        .into_iter()
+        .map(|advisory| {
+            let mut versions = affected
+                .iter()
+                .filter(|(advisory_id, _)| *advisory_id == advisory.id)
+                .map(|(_, num)| num.clone())
+                .collect::<Vec<_>>();
+            versions.sort_by_cached_key(|num| semver::Version::parse(num).ok());
+            EncodableAdvisory::from(advisory, versions)
+        })
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "advisories": advisories })))
+}
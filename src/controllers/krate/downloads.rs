@@ -7,10 +7,13 @@ use std::cmp;
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, CrateVersions, Version, VersionDownload};
-use crate::schema::version_downloads;
+use crate::models::{
+    AgentDownloads, Crate, CrateVersions, MonthlyDownloads, Version, VersionDownload,
+};
+use crate::schema::{version_downloads, versions};
 use crate::sql::to_char;
-use crate::views::EncodableVersionDownload;
+use crate::views::{EncodableAgentDownloads, EncodableMonthlyDownload, EncodableVersionDownload};
+use chrono::{Duration, Utc};
 
 /// Handles the `GET /crates/:crate_id/downloads` route.
 pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
@@ -44,6 +47,17 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
         .order(version_downloads::date.asc())
         .load(&*conn)?;
 
+    let end_date = Utc::today().naive_utc();
+    let start_date = end_date - Duration::days(89);
+    let version_ids = versions
+        .iter()
+        .map(|version| version.id)
+        .collect::<Vec<_>>();
+    let user_agents = AgentDownloads::for_versions(&conn, &version_ids, start_date, end_date)?
+        .into_iter()
+        .map(AgentDownloads::into)
+        .collect::<Vec<EncodableAgentDownloads>>();
+
     #[derive(Serialize, Queryable)]
     struct ExtraDownload {
         date: String,
@@ -54,6 +68,25 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
         "version_downloads": downloads,
         "meta": {
             "extra_downloads": extra,
+            "user_agents": user_agents,
         },
     })))
 }
+
+/// Handles the `GET /crates/:crate_id/monthly_downloads` route.
+///
+/// Returns the downloads of all versions of the crate for every month, including the months
+/// older than the 90 days covered by the `downloads` route.
+pub fn monthly_downloads(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    let version_ids: Vec<i32> = krate.all_versions().select(versions::id).load(&*conn)?;
+    let downloads = MonthlyDownloads::for_versions(&conn, &version_ids)?
+        .into_iter()
+        .map(MonthlyDownloads::into)
+        .collect::<Vec<EncodableMonthlyDownload>>();
+
+    Ok(req.json(&json!({ "monthly_downloads": downloads })))
+}
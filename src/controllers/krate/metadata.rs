@@ -7,19 +7,27 @@
 use std::cmp::Reverse;
 use std::str::FromStr;
 
+use conduit::{Body, Response};
+
 use crate::controllers::frontend_prelude::*;
 use crate::controllers::helpers::pagination::PaginationOptions;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    TopVersions, User, Version, VersionOwnerAction,
+    Advisory, Category, Crate, CrateCategory, CrateKeyword, CrateVersions, DependencyKind, Keyword,
+    RecentCrateDownloads, ReverseDependency, ReverseDependencyFilter, TopVersions, User, Version,
+    VersionOwnerAction,
 };
 use crate::schema::*;
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    EncodableCategory, EncodableCrate, EncodableDependency, EncodableDependent, EncodableKeyword,
+    EncodableVersion,
 };
 
 use crate::models::krate::ALL_COLUMNS;
+use crate::util::errors::internal;
+
+/// The maximum number of dependents in the CSV export of reverse dependencies.
+const MAX_CSV_REVERSE_DEPENDENCIES: i64 = 10_000;
 
 /// Handles the `GET /summary` route.
 pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
@@ -154,7 +162,8 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
             versions_and_publishers
                 .into_iter()
                 .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-                .map(|((v, pb), aas)| (v, pb, aas))
+                .zip(Advisory::ids_for_versions(&conn, &versions)?.into_iter())
+                .map(|(((v, pb), aas), advisories)| (v, pb, aas, advisories))
                 .collect::<Vec<_>>(),
         )
     } else {
@@ -220,7 +229,9 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     );
     let encodable_versions = versions_publishers_and_audit_actions.map(|vpa| {
         vpa.into_iter()
-            .map(|(v, pb, aas)| EncodableVersion::from(v, &krate.name, pb, aas))
+            .map(|(v, pb, aas, advisories)| {
+                EncodableVersion::from(v, &krate.name, pb, aas, advisories)
+            })
             .collect::<Vec<_>>()
     });
     let encodable_keywords = kws.map(|kws| {
@@ -345,21 +356,54 @@ pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| EncodableVersion::from(v, crate_name, pb, aas))
+        .zip(Advisory::ids_for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, pb), aas), advisories)| {
+            EncodableVersion::from(v, crate_name, pb, aas, advisories)
+        })
         .collect::<Vec<_>>();
 
     Ok(req.json(&json!({ "versions": versions })))
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
+///
+/// The dependents can be filtered with the `kind` (`normal`, `build` or
+/// `dev`), `optional` (`true` or `false`), `matches_latest` (`true` or
+/// `false`) and `range` (a semver requirement for the versions of this crate)
+/// query parameters. With `format=csv` the matching dependents are returned
+/// as CSV without pagination, limited to the `MAX_CSV_REVERSE_DEPENDENCIES`
+/// most downloaded ones.
 pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
 
+    let filter = reverse_dependency_filter(req)?;
+    let csv = match req.query().get("format").map(String::as_str) {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(format) => return Err(bad_request(&format!("unsupported format `{format}`"))),
+    };
     let pagination_options = PaginationOptions::builder().gather(req)?;
     let name = &req.params()["crate_id"];
     let conn = req.db_read()?;
     let krate: Crate = Crate::by_name(name).first(&*conn)?;
-    let (rev_deps, total) = krate.reverse_dependencies(&*conn, pagination_options)?;
+
+    if csv {
+        let (rev_deps, _) =
+            krate.filtered_reverse_dependencies(&conn, &filter, 0, MAX_CSV_REVERSE_DEPENDENCIES)?;
+        return reverse_dependencies_csv(rev_deps);
+    }
+
+    let (rev_deps, total) = if filter.is_empty() {
+        krate.reverse_dependencies(&*conn, pagination_options)?
+    } else {
+        let offset = pagination_options.offset().unwrap_or_default();
+        krate.filtered_reverse_dependencies(
+            &conn,
+            &filter,
+            i64::from(offset),
+            i64::from(pagination_options.per_page),
+        )?
+    };
     let rev_deps: Vec<_> = rev_deps
         .into_iter()
         .map(|dep| EncodableDependency::from_reverse_dep(dep, &krate.name))
@@ -385,9 +429,12 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, krate_name, published_by), actions)| {
-            EncodableVersion::from(version, &krate_name, published_by, actions)
-        })
+        .zip(Advisory::ids_for_versions(&conn, &versions)?.into_iter())
+        .map(
+            |(((version, krate_name, published_by), actions), advisories)| {
+                EncodableVersion::from(version, &krate_name, published_by, actions, advisories)
+            },
+        )
         .collect::<Vec<_>>();
 
     Ok(req.json(&json!({
@@ -396,3 +443,56 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
         "meta": { "total": total },
     })))
 }
+
+fn reverse_dependency_filter(req: &dyn RequestExt) -> AppResult<ReverseDependencyFilter> {
+    let query = req.query();
+    let bool_param = |name: &str| match query.get(name).map(String::as_str) {
+        None => Ok(None),
+        Some("true") => Ok(Some(true)),
+        Some("false") => Ok(Some(false)),
+        Some(value) => Err(bad_request(&format!(
+            "invalid value `{value}` for `{name}`, expected `true` or `false`"
+        ))),
+    };
+
+    let kind = match query.get("kind").map(String::as_str) {
+        None => None,
+        Some("normal") => Some(DependencyKind::Normal),
+        Some("build") => Some(DependencyKind::Build),
+        Some("dev") => Some(DependencyKind::Dev),
+        Some(kind) => return Err(bad_request(&format!("invalid dependency kind `{kind}`"))),
+    };
+
+    let range = query
+        .get("range")
+        .map(|range| semver::VersionReq::parse(range))
+        .transpose()
+        .map_err(|e| bad_request(&format!("invalid version range: {e}")))?;
+
+    Ok(ReverseDependencyFilter {
+        kind,
+        optional: bool_param("optional")?,
+        matches_latest: bool_param("matches_latest")?,
+        range,
+    })
+}
+
+fn reverse_dependencies_csv(rev_deps: Vec<ReverseDependency>) -> EndpointResult {
+    // The header is written explicitly, so that it is also present if there
+    // are no dependents
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+    writer.write_record(EncodableDependent::CSV_HEADER)?;
+    for rev_dep in rev_deps {
+        writer.serialize(EncodableDependent::from(rev_dep))?;
+    }
+    let body = writer
+        .into_inner()
+        .map_err(|e| internal(&format_args!("failed to write CSV: {e}")))?;
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(Body::from_vec(body))?)
+}
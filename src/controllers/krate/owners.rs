@@ -1,8 +1,9 @@
 //! All routes related to managing owners of a crate
 
 use crate::controllers::prelude::*;
-use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::models::{Crate, Owner, OwnerRole, Rights, Team, User, WebhookEvent, WebhookPayload};
 use crate::views::EncodableOwner;
+use crate::worker;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
 pub fn owners(req: &mut dyn RequestExt) -> EndpointResult {
@@ -10,9 +11,12 @@ pub fn owners(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_read()?;
     let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
     let owners = krate
-        .owners(&conn)?
+        .owners_with_roles(&conn)?
         .into_iter()
-        .map(Owner::into)
+        .map(|(owner, role)| EncodableOwner {
+            role: Some(role),
+            ..EncodableOwner::from(owner)
+        })
         .collect::<Vec<EncodableOwner>>();
 
     Ok(req.json(&json!({ "users": owners })))
@@ -45,6 +49,8 @@ pub fn owner_user(req: &mut dyn RequestExt) -> EndpointResult {
 }
 
 /// Handles the `PUT /crates/:crate_id/owners` route.
+///
+/// Existing owners are given the requested role instead of being added again.
 pub fn add_owners(req: &mut dyn RequestExt) -> EndpointResult {
     modify_owners(req, true)
 }
@@ -54,14 +60,118 @@ pub fn remove_owners(req: &mut dyn RequestExt) -> EndpointResult {
     modify_owners(req, false)
 }
 
+/// Handles the `PUT /me/crate_transfers` route.
+///
+/// Transfers the ownership of one or more crates from one of their owners to another user or
+/// team. Users are sent an invitation, and only replace the previous owner once they accept it.
+/// Teams replace the previous owner right away.
+///
+/// ## Request Body Example
+///
+/// ```json
+/// {"crates": ["foo", "bar"], "to": "new-owner", "from": "old-owner"}
+/// ```
+///
+/// `from` defaults to the authenticated user, and may also be a team, e.g. when the crates of a
+/// team are moved to another team.
+pub fn transfer_owners(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct TransferRequest {
+        crates: Vec<String>,
+        to: String,
+        from: Option<String>,
+    }
+
+    let authenticated_user = req.authenticate()?;
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: TransferRequest =
+        serde_json::from_str(&body).map_err(|_| cargo_err("invalid json request"))?;
+    if request.crates.is_empty() {
+        return Err(cargo_err("no crates to transfer"));
+    }
+
+    let app = req.app();
+    let conn = req.db_write()?;
+    let user = authenticated_user.user();
+    let from = request.from.unwrap_or_else(|| user.gh_login.clone());
+
+    // The emails are only sent once every transfer is committed, so that users aren't invited
+    // to transfers that end up rolled back.
+    let (msgs, emails) = conn.transaction(|| {
+        let mut msgs = Vec::with_capacity(request.crates.len());
+        let mut emails = Vec::new();
+        for crate_name in &request.crates {
+            let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+            let owners = krate.owners_with_roles(&conn)?;
+            if user.rights(app, &conn, &owners)? != Rights::Full {
+                return Err(cargo_err(&format_args!(
+                    "only owners with the admin role have permission to transfer crate {}",
+                    krate.name
+                )));
+            }
+
+            let (previous_owner, role) = owners
+                .iter()
+                .find(|(owner, _)| owner.login().to_lowercase() == from.to_lowercase())
+                .ok_or_else(|| {
+                    cargo_err(&format_args!(
+                        "`{}` is not an owner of crate {}",
+                        from, krate.name
+                    ))
+                })?;
+
+            let (msg, email) =
+                krate.transfer_ownership(app, &conn, &user, previous_owner, *role, &request.to)?;
+            msgs.push(msg);
+            emails.extend(email);
+
+            // Users only replace the previous owner once they accepted their
+            // invitation, but teams do right away
+            if request.to.contains(':') {
+                if !has_admin_user(&krate, &conn)? {
+                    return Err(cargo_err(&format_args!(
+                        "cannot transfer crate {} to a team, as it would be left without an \
+                         individual owner with the admin role",
+                        krate.name
+                    )));
+                }
+                for (event, owner) in [
+                    (WebhookEvent::OwnerAdd, request.to.as_str()),
+                    (WebhookEvent::OwnerRemove, previous_owner.login()),
+                ] {
+                    let payload =
+                        WebhookPayload::new(event, &krate.name, &user.gh_login).with_owner(owner);
+                    worker::notify_webhooks(&conn, krate.id, &payload)?;
+                }
+            }
+        }
+
+        Ok((msgs, emails))
+    })?;
+
+    for email in emails {
+        email.send(&app.emails);
+    }
+
+    Ok(req.json(&json!({ "ok": true, "msg": msgs.join(",") })))
+}
+
 /// Parse the JSON request body of requests to modify the owners of a crate.
 ///
 /// The format is:
 ///
 /// ```json
-/// {"owners": ["username", "github:org:team", ...]}
+/// {
+///     "owners": ["username", "github:org:team", "registry:team", "someone@example.com", ...],
+///     "role": "publisher"
+/// }
 /// ```
-fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<Vec<String>> {
+///
+/// The `role` is optional, and only used when adding owners. It is one of `admin`, `publisher`
+/// or `yank-only`, and defaults to `admin` for users and `publisher` for teams. Email addresses
+/// that don't belong to a user yet are invited once someone verifies them.
+fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<(Vec<String>, Option<OwnerRole>)> {
     let mut body = String::new();
     req.body().read_to_string(&mut body)?;
     #[derive(Deserialize)]
@@ -69,32 +179,42 @@ fn parse_owners_request(req: &mut dyn RequestExt) -> AppResult<Vec<String>> {
         // identical, for back-compat (owners preferred)
         users: Option<Vec<String>>,
         owners: Option<Vec<String>>,
+        role: Option<OwnerRole>,
     }
     let request: Request =
         serde_json::from_str(&body).map_err(|_| cargo_err("invalid json request"))?;
-    request
+    let logins = request
         .owners
         .or(request.users)
-        .ok_or_else(|| cargo_err("invalid json request"))
+        .ok_or_else(|| cargo_err("invalid json request"))?;
+    Ok((logins, request.role))
 }
 
 fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
     let authenticated_user = req.authenticate()?;
-    let logins = parse_owners_request(req)?;
+    let (logins, role) = parse_owners_request(req)?;
     let app = req.app();
     let crate_name = &req.params()["crate_id"];
 
     let conn = req.db_write()?;
     let user = authenticated_user.user();
 
-    conn.transaction(|| {
+    // The invitation emails are only sent once the changes are committed, so that nobody is
+    // invited by a request that ends up failing.
+    let (response, emails) = conn.transaction(|| {
         let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
-        let owners = krate.owners(&conn)?;
+        let owners = krate.owners_with_roles(&conn)?;
+        let mut emails = Vec::new();
 
-        match user.rights(app, &owners)? {
+        match user.rights(app, &conn, &owners)? {
             Rights::Full => {}
             // Yes!
-            Rights::Publish => {
+            Rights::Publish | Rights::Yank if is_user_owner(&owners, &user) => {
+                return Err(cargo_err(
+                    "only owners with the admin role have permission to modify owners",
+                ));
+            }
+            Rights::Publish | Rights::Yank => {
                 return Err(cargo_err(
                     "team members don't have permission to modify owners",
                 ));
@@ -107,20 +227,45 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
         let comma_sep_msg = if add {
             let mut msgs = Vec::with_capacity(logins.len());
             for login in &logins {
-                let login_test =
-                    |owner: &Owner| owner.login().to_lowercase() == *login.to_lowercase();
-                if owners.iter().any(login_test) {
-                    return Err(cargo_err(&format_args!("`{}` is already an owner", login)));
+                let existing = owners
+                    .iter()
+                    .find(|(owner, _)| owner.login().to_lowercase() == *login.to_lowercase());
+                if let Some((owner, _)) = existing {
+                    match role {
+                        Some(role) => msgs.push(krate.owner_set_role(&conn, owner, role)?),
+                        None => {
+                            return Err(cargo_err(&format_args!("`{}` is already an owner", login)))
+                        }
+                    }
+                    continue;
+                }
+                let (msg, email) = krate.owner_add(app, &conn, &user, login, role)?;
+                emails.extend(email);
+                // Users are only added once they accepted their invitation, but
+                // teams become owners right away
+                if login.contains(':') {
+                    let payload =
+                        WebhookPayload::new(WebhookEvent::OwnerAdd, &krate.name, &user.gh_login)
+                            .with_owner(login);
+                    worker::notify_webhooks(&conn, krate.id, &payload)?;
                 }
-                let msg = krate.owner_add(app, &conn, &user, login)?;
                 msgs.push(msg);
             }
+            if !has_admin_user(&krate, &conn)? {
+                return Err(cargo_err(
+                    "cannot take the admin role away from all individual owners of a crate",
+                ));
+            }
             msgs.join(",")
         } else {
             for login in &logins {
                 krate.owner_remove(app, &conn, &user, login)?;
+                let payload =
+                    WebhookPayload::new(WebhookEvent::OwnerRemove, &krate.name, &user.gh_login)
+                        .with_owner(login);
+                worker::notify_webhooks(&conn, krate.id, &payload)?;
             }
-            if User::owning(&krate, &conn)?.is_empty() {
+            if !has_admin_user(&krate, &conn)? {
                 return Err(cargo_err(
                     "cannot remove all individual owners of a crate. \
                      Team member don't have permission to modify owners, so \
@@ -130,6 +275,28 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
             "owners successfully removed".to_owned()
         };
 
-        Ok(req.json(&json!({ "ok": true, "msg": comma_sep_msg })))
-    })
+        Ok((
+            req.json(&json!({ "ok": true, "msg": comma_sep_msg })),
+            emails,
+        ))
+    })?;
+
+    for email in &emails {
+        email.send(&app.emails);
+    }
+    Ok(response)
+}
+
+fn is_user_owner(owners: &[(Owner, OwnerRole)], user: &User) -> bool {
+    owners
+        .iter()
+        .any(|(owner, _)| matches!(owner, Owner::User(owner) if owner.id == user.id))
+}
+
+/// Whether the crate still has an individual owner that can manage its owners.
+fn has_admin_user(krate: &Crate, conn: &PgConnection) -> QueryResult<bool> {
+    Ok(krate
+        .owners_with_roles(conn)?
+        .iter()
+        .any(|(owner, role)| matches!(owner, Owner::User(_)) && *role == OwnerRole::Admin))
 }
@@ -1,5 +1,6 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
+use cargo_registry_markdown::text_to_plaintext;
 use flate2::read::GzDecoder;
 use hex::ToHex;
 use sha2::{Digest, Sha256};
@@ -10,16 +11,20 @@ use std::sync::Arc;
 use swirl::Job;
 
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::helpers::notifications::{token_name, OwnerNotification};
+use crate::email::VersionNotification;
 use crate::models::{
     insert_version_owner_action, Badge, Category, Crate, DependencyKind, Keyword, NewCrate,
-    NewVersion, Rights, VersionAction,
+    NewVersion, Rights, VersionAction, VersionFileStats, WebhookEvent, WebhookPayload,
 };
 use crate::worker;
 
 use crate::middleware::log_request::add_custom_metadata;
 use crate::schema::*;
 use crate::util::errors::{cargo_err, AppResult};
-use crate::util::{read_fill, read_le_u32, CargoVcsInfo, LimitErrorReader, Maximums};
+use crate::util::{
+    read_fill, read_le_u32, request_header, CargoVcsInfo, LimitErrorReader, Maximums,
+};
 use crate::views::{
     EncodableCrate, EncodableCrateDependency, EncodableCrateUpload, GoodCrate, PublishWarnings,
 };
@@ -35,6 +40,9 @@ pub const WILDCARD_ERROR_MESSAGE: &str = "wildcard (`*`) dependency constraints
      libraries-use--as-a-version-for-their-dependencies for more \
      information";
 
+/// Maximum length in bytes of the README extract stored for full text search.
+const MAX_README_EXTRACT_LENGTH: usize = 64 * 1024;
+
 /// Handles the `PUT /crates/new` route.
 /// Used by `cargo publish` to publish a new crate or to publish a new version of an
 /// existing crate.
@@ -67,6 +75,7 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
     let ids = req.authenticate()?;
     let api_token_id = ids.api_token_id();
     let user = ids.user();
+    let user_agent = request_header(req, header::USER_AGENT).to_string();
 
     let verified_email_address = user.verified_email(&conn)?;
     let verified_email_address = verified_email_address.ok_or_else(|| {
@@ -79,7 +88,7 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
 
     // Create a transaction on the database, if there are no errors,
     // commit the transactions to record a new or updated crate.
-    conn.transaction(|| {
+    let (response, notification) = conn.transaction(|| {
         let _ = &new_crate;
         let name = new_crate.name;
         let vers = &*new_crate.vers;
@@ -101,13 +110,19 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             .map(|s| s.as_str())
             .collect::<Vec<_>>();
 
+        // Only the plain text of the README is stored, to be indexed for full text search
+        let readme_extract = new_crate.readme.as_deref().map(|readme| {
+            let readme_file = new_crate.readme_file.as_deref().unwrap_or("README.md");
+            readme_extract(readme, readme_file)
+        });
+
         // Persist the new crate, if it doesn't already exist
         let persist = NewCrate {
             name: &name,
             description: new_crate.description.as_deref(),
             homepage: new_crate.homepage.as_deref(),
             documentation: new_crate.documentation.as_deref(),
-            readme: new_crate.readme.as_deref(),
+            readme: readme_extract.as_deref(),
             repository: repo.as_deref(),
             max_upload_size: None,
         };
@@ -116,9 +131,15 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         let krate =
             persist.create_or_update(&conn, user.id, Some(&app.config.publish_rate_limit))?;
 
-        let owners = krate.owners(&conn)?;
-        if user.rights(req.app(), &owners)? < Rights::Publish {
-            return Err(cargo_err(MISSING_RIGHTS_ERROR_MESSAGE));
+        let owners = krate.owners_with_roles(&conn)?;
+        match user.rights(req.app(), &conn, &owners)? {
+            Rights::Full | Rights::Publish => {}
+            Rights::Yank => {
+                return Err(cargo_err(
+                    "yank-only owners don't have permission to publish new versions",
+                ));
+            }
+            Rights::None => return Err(cargo_err(MISSING_RIGHTS_ERROR_MESSAGE)),
         }
 
         if krate.name != *name {
@@ -196,8 +217,12 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         LimitErrorReader::new(req.body(), maximums.max_upload_size).read_to_end(&mut tarball)?;
         let hex_cksum: String = Sha256::digest(&tarball).encode_hex();
         let pkg_name = format!("{}-{}", krate.name, vers);
-        let cargo_vcs_info = verify_tarball(&pkg_name, &tarball, maximums.max_unpack_size)?;
-        let pkg_path_in_vcs = cargo_vcs_info.map(|info| info.path_in_vcs);
+        let tarball_info = verify_tarball(&pkg_name, &tarball, maximums.max_unpack_size)?;
+        version.record_file_stats(&conn, &tarball_info.file_stats)?;
+        diesel::update(&version)
+            .set(versions::checksum.eq(&hex_cksum))
+            .execute(&*conn)?;
+        let pkg_path_in_vcs = tarball_info.vcs_info.map(|info| info.path_in_vcs);
 
         if let Some(readme) = new_crate.readme {
             worker::render_and_upload_readme(
@@ -243,6 +268,20 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         };
         worker::add_crate(git_crate).enqueue(&conn)?;
 
+        let payload = WebhookPayload::new(WebhookEvent::Publish, &krate.name, &user.gh_login)
+            .with_version(&version.num);
+        worker::notify_webhooks(&conn, krate.id, &payload)?;
+
+        let notification = VersionNotification {
+            crate_name: krate.name.clone(),
+            version: version.num.clone(),
+            action: VersionAction::Publish,
+            user: user.gh_login.clone(),
+            token_name: token_name(&conn, api_token_id)?,
+            user_agent: user_agent.clone(),
+        };
+        let notification = OwnerNotification::new(&conn, krate.id, notification)?;
+
         // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
         // that is no longer needed. As such, crates.io currently does not return any `other`
         // warnings at this time, but if we need to, the field is available.
@@ -252,11 +291,33 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             other: vec![],
         };
 
-        Ok(req.json(&GoodCrate {
+        let response = req.json(&GoodCrate {
             krate: EncodableCrate::from_minimal(krate, Some(&top_versions), None, false, None),
             warnings,
-        }))
-    })
+        });
+        Ok((response, notification))
+    })?;
+
+    notification.send(&app.emails);
+
+    // New crates, keywords and categories may now be completed. This happens once the
+    // transaction is committed, so that the cache can't be filled again with the old results.
+    app.autocomplete_cacher.invalidate_all();
+
+    Ok(response)
+}
+
+/// Returns the plain text of the README, truncated to `MAX_README_EXTRACT_LENGTH`.
+fn readme_extract(readme: &str, readme_file: &str) -> String {
+    let mut extract = text_to_plaintext(readme, readme_file);
+    if extract.len() > MAX_README_EXTRACT_LENGTH {
+        let mut end = MAX_README_EXTRACT_LENGTH;
+        while !extract.is_char_boundary(end) {
+            end -= 1;
+        }
+        extract.truncate(end);
+    }
+    extract
 }
 
 /// Used by the `krate::new` function.
@@ -367,6 +428,7 @@ pub fn add_dependencies(
                     default_features.eq(dep.default_features),
                     features.eq(&dep.features),
                     target.eq(dep.target.as_deref()),
+                    explicit_name.eq(dep.explicit_name_in_toml.as_deref().map(String::as_str)),
                 ),
             ))
         })
@@ -382,11 +444,14 @@ pub fn add_dependencies(
     Ok(git_deps)
 }
 
-fn verify_tarball(
-    pkg_name: &str,
-    tarball: &[u8],
-    max_unpack: u64,
-) -> AppResult<Option<CargoVcsInfo>> {
+/// Information about an uploaded tarball, collected while verifying it.
+#[derive(Debug)]
+struct TarballInfo {
+    vcs_info: Option<CargoVcsInfo>,
+    file_stats: VersionFileStats,
+}
+
+fn verify_tarball(pkg_name: &str, tarball: &[u8], max_unpack: u64) -> AppResult<TarballInfo> {
     // All our data is currently encoded with gzip
     let decoder = GzDecoder::new(tarball);
 
@@ -399,6 +464,7 @@ fn verify_tarball(
 
     let vcs_info_path = Path::new(&pkg_name).join(".cargo_vcs_info.json");
     let mut vcs_info = None;
+    let mut file_stats = VersionFileStats::default();
 
     for entry in archive.entries()? {
         let mut entry = entry.map_err(|err| {
@@ -431,14 +497,33 @@ fn verify_tarball(
         if entry_type.is_hard_link() || entry_type.is_symlink() {
             return Err(cargo_err("invalid tarball uploaded"));
         }
+
+        if entry_type.is_file() {
+            let size = entry.size() as i64;
+            file_stats.unpacked_size += size;
+            file_stats.file_count += 1;
+            if file_stats
+                .largest_file_size
+                .map_or(true, |largest| size > largest)
+            {
+                let path = entry.path()?;
+                let path = path.strip_prefix(&pkg_name).unwrap_or(&path);
+                file_stats.largest_file_path = Some(path.to_string_lossy().into_owned());
+                file_stats.largest_file_size = Some(size);
+            }
+        }
     }
-    Ok(vcs_info)
+    Ok(TarballInfo {
+        vcs_info,
+        file_stats,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::{missing_metadata_error_message, verify_tarball};
     use crate::admin::render_readmes::tests::add_file;
+    use crate::models::VersionFileStats;
     use flate2::read::GzEncoder;
     use std::io::Read;
 
@@ -459,9 +544,10 @@ mod tests {
             .unwrap();
 
         let limit = 512 * 1024 * 1024;
-        assert_eq!(
-            verify_tarball("foo-0.0.1", &serialized_archive, limit).unwrap(),
-            None
+        assert_none!(
+            verify_tarball("foo-0.0.1", &serialized_archive, limit)
+                .unwrap()
+                .vcs_info
         );
         assert_err!(verify_tarball("bar-0.0.1", &serialized_archive, limit));
     }
@@ -482,6 +568,7 @@ mod tests {
         let limit = 512 * 1024 * 1024;
         let vcs_info = verify_tarball("foo-0.0.1", &serialized_archive, limit)
             .unwrap()
+            .vcs_info
             .unwrap();
         assert_eq!(vcs_info.path_in_vcs, "");
     }
@@ -502,7 +589,33 @@ mod tests {
         let limit = 512 * 1024 * 1024;
         let vcs_info = verify_tarball("foo-0.0.1", &serialized_archive, limit)
             .unwrap()
+            .vcs_info
             .unwrap();
         assert_eq!(vcs_info.path_in_vcs, "path/in/vcs");
     }
+
+    #[test]
+    fn verify_tarball_test_file_stats() {
+        let mut pkg = tar::Builder::new(vec![]);
+        add_file(&mut pkg, "foo-0.0.1/Cargo.toml", b"[package]");
+        add_file(&mut pkg, "foo-0.0.1/src/lib.rs", b"pub fn foo() {}");
+        add_file(&mut pkg, "foo-0.0.1/README.md", b"");
+        let mut serialized_archive = vec![];
+        GzEncoder::new(pkg.into_inner().unwrap().as_slice(), Default::default())
+            .read_to_end(&mut serialized_archive)
+            .unwrap();
+        let limit = 512 * 1024 * 1024;
+        let file_stats = verify_tarball("foo-0.0.1", &serialized_archive, limit)
+            .unwrap()
+            .file_stats;
+        assert_eq!(
+            file_stats,
+            VersionFileStats {
+                unpacked_size: 24,
+                file_count: 3,
+                largest_file_path: Some("src/lib.rs".to_string()),
+                largest_file_size: Some(15),
+            }
+        );
+    }
 }
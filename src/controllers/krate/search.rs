@@ -1,10 +1,16 @@
 //! Endpoint for searching and discovery functionality
 
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::dsl::*;
-use diesel::sql_types::Array;
+use diesel::expression::{SqlLiteral, UncheckedBind};
+use diesel::pg::Pg;
+use diesel::sql_types::{Array, Double, Integer, Text};
 use diesel_full_text_search::*;
 use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashMap;
 
+use crate::config::SearchRankingWeights;
 use crate::controllers::cargo_prelude::*;
 use crate::controllers::helpers::Paginate;
 use crate::models::{
@@ -16,7 +22,7 @@ use crate::views::EncodableCrate;
 
 use crate::controllers::helpers::pagination::{Page, Paginated, PaginationOptions};
 use crate::models::krate::ALL_COLUMNS;
-use crate::sql::{array_agg, canon_crate_name, lower};
+use crate::sql::{array_agg, canon_crate_name, lower, squash_crate_name};
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -40,19 +46,16 @@ use crate::sql::{array_agg, canon_crate_name, lower};
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
-    use diesel::sql_types::{Bool, Text};
+    use diesel::sql_types::Bool;
 
     let params = req.query();
     let sort = params.get("sort").map(|s| &**s);
-    let include_yanked = params
-        .get("include_yanked")
-        .map(|s| s == "yes")
-        .unwrap_or(true);
-
-    // Remove 0x00 characters from the query string because Postgres can not
-    // handle them and will return an error, which would cause us to throw
-    // an Internal Server Error ourselves.
-    let q_string = params.get("q").map(|q| q.replace('\u{0}', ""));
+    let include_facets = params.get("include_facets").map(|s| s == "yes") == Some(true);
+    let include_scores = params.get("include_scores").map(|s| s == "yes") == Some(true)
+        && req
+            .authenticate()
+            .map_or(false, |auth| auth.user().is_admin(req.app()));
+    let filter_params = FilterParams::from_request(req, &params)?;
 
     let selection = (
         ALL_COLUMNS,
@@ -64,9 +67,17 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .select(selection)
         .into_boxed();
 
+    // The filters are applied through a subquery, which is also used to count the total number of
+    // results and the facets.
+    if !filter_params.is_empty() {
+        query = query.filter(crates::id.eq_any(filter_params.make_query()));
+    }
+
     let mut supports_seek = true;
+    // The query string the results are ranked by, if sorted by relevance.
+    let mut ranked_by = None;
 
-    if let Some(q_string) = &q_string {
+    if let Some(q_string) = &filter_params.q_string {
         // Searching with a query string always puts the exact match at the start of the results,
         // so we can't support seek-based pagination with it.
         supports_seek = false;
@@ -74,15 +85,6 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         if !q_string.is_empty() {
             let sort = params.get("sort").map(|s| &**s).unwrap_or("relevance");
 
-            let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                .bind::<Text, _>(q_string)
-                .sql(")");
-            query = query.filter(
-                q.clone()
-                    .matches(crates::textsearchable_index_col)
-                    .or(Crate::loosly_matches_name(q_string)),
-            );
-
             query = query.select((
                 ALL_COLUMNS,
                 Crate::with_name(q_string),
@@ -91,135 +93,19 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
             query = query.order(Crate::with_name(q_string).desc());
 
             if sort == "relevance" {
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                query = query.then_order_by(rank.desc())
+                let weights = &req.app().config.search_ranking_weights;
+                let c = ScoreComponents::new(q_string);
+                let score = c.text * weights.text
+                    + c.name_similarity * weights.name_similarity
+                    + c.recent_downloads * weights.recent_downloads
+                    + c.reverse_dependencies * weights.reverse_dependencies
+                    + c.recency * weights.recency;
+                query = query.then_order_by(score.desc());
+                ranked_by = Some(q_string.as_str());
             }
         }
     }
 
-    if let Some(cat) = params.get("category") {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        query = query.filter(
-            crates::id.eq_any(
-                crates_categories::table
-                    .select(crates_categories::crate_id)
-                    .inner_join(categories::table)
-                    .filter(
-                        categories::slug
-                            .eq(cat)
-                            .or(categories::slug.like(format!("{cat}::%"))),
-                    ),
-            ),
-        );
-    }
-
-    if let Some(kws) = params.get("all_keywords") {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        let names: Vec<_> = kws
-            .split_whitespace()
-            .map(|name| name.to_lowercase())
-            .collect();
-
-        query = query.filter(
-            // FIXME: Just use `.contains` in Diesel 2.0
-            // https://github.com/diesel-rs/diesel/issues/2066
-            Contains::new(
-                crates_keywords::table
-                    .inner_join(keywords::table)
-                    .filter(crates_keywords::crate_id.eq(crates::id))
-                    .select(array_agg(keywords::keyword))
-                    .single_value(),
-                names.into_sql::<Array<Text>>(),
-            ),
-        );
-    } else if let Some(kw) = params.get("keyword") {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        query = query.filter(
-            crates::id.eq_any(
-                crates_keywords::table
-                    .select(crates_keywords::crate_id)
-                    .inner_join(keywords::table)
-                    .filter(lower(keywords::keyword).eq(lower(kw))),
-            ),
-        );
-    } else if let Some(letter) = params.get("letter") {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        let pattern = format!(
-            "{}%",
-            letter
-                .chars()
-                .next()
-                .ok_or_else(|| bad_request("letter value must contain 1 character"))?
-                .to_lowercase()
-                .collect::<String>()
-        );
-        query = query.filter(canon_crate_name(crates::name).like(pattern));
-    } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::User)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(user_id)),
-            ),
-        );
-    } else if let Some(team_id) = params.get("team_id").and_then(|s| s.parse::<i32>().ok()) {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        query = query.filter(
-            crates::id.eq_any(
-                CrateOwner::by_owner_kind(OwnerKind::Team)
-                    .select(crate_owners::crate_id)
-                    .filter(crate_owners::owner_id.eq(team_id)),
-            ),
-        );
-    } else if params.get("following").is_some() {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        let user_id = req.authenticate()?.user_id();
-        query = query.filter(
-            crates::id.eq_any(
-                follows::table
-                    .select(follows::crate_id)
-                    .filter(follows::user_id.eq(user_id)),
-            ),
-        );
-    } else if params.get("ids[]").is_some() {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        let query_bytes = req.query_string().unwrap_or("").as_bytes();
-        let ids: Vec<_> = url::form_urlencoded::parse(query_bytes)
-            .filter(|(key, _)| key == "ids[]")
-            .map(|(_, value)| value.to_string())
-            .collect();
-
-        query = query.filter(crates::name.eq(any(ids)));
-    }
-
-    if !include_yanked {
-        // Calculating the total number of results with filters is not supported yet.
-        supports_seek = false;
-
-        query = query.filter(exists(
-            versions::table
-                .filter(versions::crate_id.eq(crates::id))
-                .filter(versions::yanked.eq(false)),
-        ));
-    }
-
     if sort == Some("downloads") {
         // Custom sorting is not supported yet with seek.
         supports_seek = false;
@@ -271,13 +157,13 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
             query = query.filter(crates::name.gt(crate_name));
         }
 
-        // This does a full index-only scan over the crates table to gather how many crates were
-        // published. Unfortunately on PostgreSQL counting the rows in a table requires scanning
-        // the table, and the `total` field is part of the stable registries API.
+        // Without filters this does a full index-only scan over the crates table to gather how
+        // many crates were published. Unfortunately on PostgreSQL counting the rows in a table
+        // requires scanning the table, and the `total` field is part of the stable registries API.
         //
         // If this becomes a problem in the future the crates count could be denormalized, at least
         // for the filterless happy path.
-        let total: i64 = crates::table.count().get_result(&*conn)?;
+        let total: i64 = filter_params.make_query().count().get_result(&*conn)?;
 
         let results: Vec<(Crate, bool, Option<i64>)> = query.load(&*conn)?;
 
@@ -312,6 +198,14 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .collect::<Vec<_>>();
     let crates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
 
+    let scores = match ranked_by {
+        Some(q_string) if include_scores => {
+            let weights = &req.app().config.search_ranking_weights;
+            Some(score_breakdowns(&conn, q_string, &crates, weights)?)
+        }
+        _ => None,
+    };
+
     let versions: Vec<Version> = crates.versions().load(&*conn)?;
     let versions = versions
         .grouped_by(&crates)
@@ -326,6 +220,11 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .into_iter()
         .map(|badges| badges.into_iter().map(|cb| cb.badge).collect());
 
+    let mut snippets = match filter_params.q_string.as_deref() {
+        Some(q_string) if !q_string.is_empty() => readme_snippets(&conn, q_string, &crates)?,
+        _ => HashMap::new(),
+    };
+
     let crates = versions
         .zip(crates)
         .zip(perfect_matches)
@@ -333,25 +232,647 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .zip(badges)
         .map(
             |((((max_version, krate), perfect_match), recent_downloads), badges)| {
-                EncodableCrate::from_minimal(
-                    krate,
-                    Some(&max_version),
-                    Some(badges),
-                    perfect_match,
-                    Some(recent_downloads),
-                )
+                let readme_snippet = snippets.remove(&krate.id);
+                EncodableCrate {
+                    readme_snippet,
+                    ..EncodableCrate::from_minimal(
+                        krate,
+                        Some(&max_version),
+                        Some(badges),
+                        perfect_match,
+                        Some(recent_downloads),
+                    )
+                }
             },
         )
         .collect::<Vec<_>>();
 
+    let facets = if include_facets {
+        Some(filter_params.facets(&conn)?)
+    } else {
+        None
+    };
+
+    let did_you_mean = match &filter_params.q_string {
+        Some(q_string) if uses_fuzzy_matching(q_string) => {
+            let exact_hits: i64 = filter_params
+                .make_exact_query()
+                .count()
+                .get_result(&*conn)?;
+            if exact_hits < FEW_EXACT_HITS {
+                did_you_mean(&conn, q_string)?
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
     Ok(req.json(&json!({
         "crates": crates,
         "meta": {
             "total": total,
             "next_page": next_page,
             "prev_page": prev_page,
+            "facets": facets,
+            "scores": scores,
+            "did_you_mean": did_you_mean,
         },
     })))
 }
 
+/// The filters of a search, parsed from the query parameters:
+///
+/// - `q`: full text search of the name, description, keywords and README. Queries of at least
+///   `MIN_FUZZY_QUERY_LENGTH` characters also match crate names and keywords that are similar
+///   enough to the query (see `pg_trgm`), to tolerate typos.
+/// - `include_yanked`: set to `no` to exclude crates where every version is yanked.
+/// - `category`: slug of a category, also matching its subcategories.
+/// - `all_keywords`, `keyword`, `letter`, `user_id`, `team_id`, `following` and `ids[]`: only one
+///   of these is applied, in this order of precedence.
+/// - `license`: an SPDX license expression. Crates match if the license of their most recently
+///   published non-yanked version mentions any of the licenses in the expression.
+/// - `updated_after` and `updated_before`: inclusive range of `YYYY-MM-DD` dates the crate was
+///   last updated in.
+/// - `min_downloads`: minimum number of all-time downloads.
+/// - `has_repository`: set to `yes` to only return crates with a repository URL.
+/// - `latest_not_yanked`: set to `yes` to exclude crates whose most recently published version is
+///   yanked.
+#[derive(Debug, PartialEq, Eq)]
+struct FilterParams {
+    q_string: Option<String>,
+    include_yanked: bool,
+    category: Option<String>,
+    all_keywords: Option<Vec<String>>,
+    keyword: Option<String>,
+    letter: Option<String>,
+    user_id: Option<i32>,
+    team_id: Option<i32>,
+    following: Option<i32>,
+    ids: Option<Vec<String>>,
+    licenses: Option<Vec<String>>,
+    updated_after: Option<NaiveDateTime>,
+    updated_before: Option<NaiveDateTime>,
+    min_downloads: Option<i32>,
+    has_repository: bool,
+    latest_not_yanked: bool,
+}
+
+impl Default for FilterParams {
+    fn default() -> Self {
+        Self {
+            q_string: None,
+            include_yanked: true,
+            category: None,
+            all_keywords: None,
+            keyword: None,
+            letter: None,
+            user_id: None,
+            team_id: None,
+            following: None,
+            ids: None,
+            licenses: None,
+            updated_after: None,
+            updated_before: None,
+            min_downloads: None,
+            has_repository: false,
+            latest_not_yanked: false,
+        }
+    }
+}
+
+impl FilterParams {
+    fn from_request(
+        req: &mut dyn RequestExt,
+        params: &IndexMap<String, String>,
+    ) -> AppResult<Self> {
+        let mut filters = Self {
+            include_yanked: params
+                .get("include_yanked")
+                .map(|s| s == "yes")
+                .unwrap_or(true),
+            // Remove 0x00 characters from the query string because Postgres can not
+            // handle them and will return an error, which would cause us to throw
+            // an Internal Server Error ourselves.
+            q_string: params.get("q").map(|q| q.replace('\u{0}', "")),
+            category: params.get("category").cloned(),
+            licenses: params
+                .get("license")
+                .map(|expression| license_ids(expression))
+                .transpose()?,
+            updated_after: params
+                .get("updated_after")
+                .map(|date| parse_date(date, "updated_after"))
+                .transpose()?,
+            updated_before: params
+                .get("updated_before")
+                .map(|date| parse_date(date, "updated_before"))
+                .transpose()?
+                .map(|date| date + chrono::Duration::days(1)),
+            min_downloads: params
+                .get("min_downloads")
+                .map(|downloads| {
+                    downloads
+                        .parse()
+                        .map_err(|_| bad_request("min_downloads must be an integer"))
+                })
+                .transpose()?,
+            has_repository: params.get("has_repository").map(|s| &**s) == Some("yes"),
+            latest_not_yanked: params.get("latest_not_yanked").map(|s| &**s) == Some("yes"),
+            ..Self::default()
+        };
+
+        if let Some(kws) = params.get("all_keywords") {
+            filters.all_keywords = Some(
+                kws.split_whitespace()
+                    .map(|name| name.to_lowercase())
+                    .collect(),
+            );
+        } else if let Some(kw) = params.get("keyword") {
+            filters.keyword = Some(kw.clone());
+        } else if let Some(letter) = params.get("letter") {
+            filters.letter = Some(format!(
+                "{}%",
+                letter
+                    .chars()
+                    .next()
+                    .ok_or_else(|| bad_request("letter value must contain 1 character"))?
+                    .to_lowercase()
+                    .collect::<String>()
+            ));
+        } else if let Some(user_id) = params.get("user_id").and_then(|s| s.parse::<i32>().ok()) {
+            filters.user_id = Some(user_id);
+        } else if let Some(team_id) = params.get("team_id").and_then(|s| s.parse::<i32>().ok()) {
+            filters.team_id = Some(team_id);
+        } else if params.get("following").is_some() {
+            filters.following = Some(req.authenticate()?.user_id());
+        } else if params.get("ids[]").is_some() {
+            let query_bytes = req.query_string().unwrap_or("").as_bytes();
+            filters.ids = Some(
+                url::form_urlencoded::parse(query_bytes)
+                    .filter(|(key, _)| key == "ids[]")
+                    .map(|(_, value)| value.to_string())
+                    .collect(),
+            );
+        }
+
+        Ok(filters)
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns a query selecting the IDs of the crates matching the filters.
+    fn make_query(&self) -> crates::BoxedQuery<'_, Pg, Integer> {
+        self.build_query(true)
+    }
+
+    /// Returns a query selecting the IDs of the crates matching the filters, without the crates
+    /// that only match the query string because of their similarity.
+    fn make_exact_query(&self) -> crates::BoxedQuery<'_, Pg, Integer> {
+        self.build_query(false)
+    }
+
+    fn build_query(&self, fuzzy: bool) -> crates::BoxedQuery<'_, Pg, Integer> {
+        use diesel::sql_types::Bool;
+
+        let mut query = crates::table.select(crates::id).into_boxed();
+
+        if let Some(q_string) = self.q_string.as_ref().filter(|q| !q.is_empty()) {
+            let q = sql::<TsQuery>("plainto_tsquery('english', ")
+                .bind::<Text, _>(q_string)
+                .sql(")");
+            let exact = q
+                .matches(crates::textsearchable_index_col)
+                .or(Crate::loosly_matches_name(q_string));
+
+            if fuzzy && uses_fuzzy_matching(q_string) {
+                query = query.filter(
+                    exact
+                        .or(TrigramMatches::new(
+                            squash_crate_name(crates::name),
+                            squash_crate_name(q_string),
+                        ))
+                        .or(crates::id.eq_any(
+                            crates_keywords::table
+                                .select(crates_keywords::crate_id)
+                                .inner_join(keywords::table)
+                                .filter(TrigramMatches::new(
+                                    lower(keywords::keyword),
+                                    lower(q_string),
+                                )),
+                        )),
+                );
+            } else {
+                query = query.filter(exact);
+            }
+        }
+
+        if let Some(cat) = &self.category {
+            query = query.filter(
+                crates::id.eq_any(
+                    crates_categories::table
+                        .select(crates_categories::crate_id)
+                        .inner_join(categories::table)
+                        .filter(
+                            categories::slug
+                                .eq(cat)
+                                .or(categories::slug.like(format!("{cat}::%"))),
+                        ),
+                ),
+            );
+        }
+
+        if let Some(names) = &self.all_keywords {
+            query = query.filter(
+                // FIXME: Just use `.contains` in Diesel 2.0
+                // https://github.com/diesel-rs/diesel/issues/2066
+                Contains::new(
+                    crates_keywords::table
+                        .inner_join(keywords::table)
+                        .filter(crates_keywords::crate_id.eq(crates::id))
+                        .select(array_agg(keywords::keyword))
+                        .single_value(),
+                    names.into_sql::<Array<Text>>(),
+                ),
+            );
+        }
+
+        if let Some(kw) = &self.keyword {
+            query = query.filter(
+                crates::id.eq_any(
+                    crates_keywords::table
+                        .select(crates_keywords::crate_id)
+                        .inner_join(keywords::table)
+                        .filter(lower(keywords::keyword).eq(lower(kw))),
+                ),
+            );
+        }
+
+        if let Some(pattern) = &self.letter {
+            query = query.filter(canon_crate_name(crates::name).like(pattern));
+        }
+
+        if let Some(user_id) = self.user_id {
+            query = query.filter(
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::User)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(user_id)),
+                ),
+            );
+        }
+
+        if let Some(team_id) = self.team_id {
+            query = query.filter(
+                crates::id.eq_any(
+                    CrateOwner::by_owner_kind(OwnerKind::Team)
+                        .select(crate_owners::crate_id)
+                        .filter(crate_owners::owner_id.eq(team_id)),
+                ),
+            );
+        }
+
+        if let Some(user_id) = self.following {
+            query = query.filter(
+                crates::id.eq_any(
+                    follows::table
+                        .select(follows::crate_id)
+                        .filter(follows::user_id.eq(user_id)),
+                ),
+            );
+        }
+
+        if let Some(ids) = &self.ids {
+            query = query.filter(crates::name.eq(any(ids)));
+        }
+
+        if !self.include_yanked {
+            query = query.filter(exists(
+                versions::table
+                    .filter(versions::crate_id.eq(crates::id))
+                    .filter(versions::yanked.eq(false)),
+            ));
+        }
+
+        if let Some(licenses) = &self.licenses {
+            // Compound expressions like `MIT OR Apache-2.0` or `(MIT/Apache-2.0)` are split into
+            // their lowercase words, which are then compared with the requested licenses.
+            query = query.filter(
+                sql::<Bool>(
+                    "(SELECT string_to_array(lower(regexp_replace(versions.license, '[()/]', ' ', 'g')), ' ') \
+                    FROM versions \
+                    WHERE versions.crate_id = crates.id AND NOT versions.yanked \
+                    ORDER BY versions.created_at DESC \
+                    LIMIT 1) && ",
+                )
+                .bind::<Array<Text>, _>(licenses),
+            );
+        }
+
+        if let Some(updated_after) = self.updated_after {
+            query = query.filter(crates::updated_at.ge(updated_after));
+        }
+
+        if let Some(updated_before) = self.updated_before {
+            query = query.filter(crates::updated_at.lt(updated_before));
+        }
+
+        if let Some(min_downloads) = self.min_downloads {
+            query = query.filter(crates::downloads.ge(min_downloads));
+        }
+
+        if self.has_repository {
+            query = query.filter(
+                crates::repository
+                    .is_not_null()
+                    .and(crates::repository.ne("")),
+            );
+        }
+
+        if self.latest_not_yanked {
+            query = query.filter(sql::<Bool>(
+                "NOT (SELECT versions.yanked \
+                FROM versions \
+                WHERE versions.crate_id = crates.id \
+                ORDER BY versions.created_at DESC \
+                LIMIT 1)",
+            ));
+        }
+
+        query
+    }
+
+    /// Counts the crates matching the filters per category and keyword, returning the ten most
+    /// common of each.
+    fn facets(&self, conn: &PgConnection) -> AppResult<Value> {
+        use diesel::sql_types::BigInt;
+
+        const FACET_LIMIT: i64 = 10;
+
+        let count = || sql::<BigInt>("COUNT(*)");
+
+        let categories: Vec<(String, i64)> = crates_categories::table
+            .inner_join(categories::table)
+            .select((categories::slug, count()))
+            .filter(crates_categories::crate_id.eq_any(self.make_query()))
+            .group_by(categories::slug)
+            .order((count().desc(), categories::slug))
+            .limit(FACET_LIMIT)
+            .load(conn)?;
+
+        let keywords: Vec<(String, i64)> = crates_keywords::table
+            .inner_join(keywords::table)
+            .select((keywords::keyword, count()))
+            .filter(crates_keywords::crate_id.eq_any(self.make_query()))
+            .group_by(keywords::keyword)
+            .order((count().desc(), keywords::keyword))
+            .limit(FACET_LIMIT)
+            .load(conn)?;
+
+        let encode = |facet: Vec<(String, i64)>| {
+            facet
+                .into_iter()
+                .map(|(value, count)| json!({ "value": value, "count": count }))
+                .collect::<Vec<_>>()
+        };
+
+        Ok(json!({
+            "categories": encode(categories),
+            "keywords": encode(keywords),
+        }))
+    }
+}
+
+/// Queries shorter than this have too few trigrams to be meaningfully compared with names.
+const MIN_FUZZY_QUERY_LENGTH: usize = 4;
+
+/// A "did you mean" suggestion is included in the response when fewer crates than this match the
+/// query without taking similar names into account.
+const FEW_EXACT_HITS: i64 = 5;
+
+type BoundSql<'a> = SqlLiteral<Double, UncheckedBind<SqlLiteral<Double>, &'a str, Text>>;
+
+/// Components of the relevance score of a crate for a query string, which are weighted by the
+/// `SearchRankingWeights` of the config.
+struct ScoreComponents<'a> {
+    text: BoundSql<'a>,
+    name_similarity: BoundSql<'a>,
+    recent_downloads: SqlLiteral<Double>,
+    reverse_dependencies: SqlLiteral<Double>,
+    recency: SqlLiteral<Double>,
+}
+
+impl<'a> ScoreComponents<'a> {
+    /// Builds the components for a query on `crates` left joined with `recent_crate_downloads`.
+    fn new(q_string: &'a str) -> Self {
+        Self {
+            text: sql("ts_rank_cd(crates.textsearchable_index_col, plainto_tsquery('english', ")
+                .bind::<Text, _>(q_string)
+                .sql("))::float8"),
+            name_similarity: sql("similarity(squash_crate_name(crates.name), squash_crate_name(")
+                .bind::<Text, _>(q_string)
+                .sql("))::float8"),
+            recent_downloads: sql("ln(1 + COALESCE(recent_crate_downloads.downloads, 0))::float8"),
+            // Crates with a non-yanked version depending on the crate, as of the last refresh of
+            // the `crate_dependent_counts` view by the `daily_db_maintenance` job.
+            reverse_dependencies: sql("ln(1 + COALESCE(( \
+                SELECT crate_dependent_counts.dependents \
+                FROM crate_dependent_counts \
+                WHERE crate_dependent_counts.crate_id = crates.id \
+            ), 0))::float8"),
+            // Halves after a month without updates, and keeps decreasing from there.
+            recency: sql(
+                "(1 / (1 + EXTRACT(EPOCH FROM now() - crates.updated_at) / 2592000))::float8",
+            ),
+        }
+    }
+}
+
+/// The components of the relevance score of a search result, as included in the response when
+/// the `include_scores=yes` parameter is passed to tune the `SearchRankingWeights`. This is only
+/// honored for administrators.
+#[derive(Serialize)]
+struct ScoreBreakdown {
+    name: String,
+    text: f64,
+    name_similarity: f64,
+    recent_downloads: f64,
+    reverse_dependencies: f64,
+    recency: f64,
+    score: f64,
+}
+
+/// Returns the breakdown of the relevance scores of `crates`, in the same order.
+fn score_breakdowns(
+    conn: &PgConnection,
+    q_string: &str,
+    crates: &[Crate],
+    weights: &SearchRankingWeights,
+) -> QueryResult<Vec<ScoreBreakdown>> {
+    let c = ScoreComponents::new(q_string);
+    let ids = crates.iter().map(|krate| krate.id).collect::<Vec<_>>();
+    let mut components: HashMap<i32, (f64, f64, f64, f64, f64)> = crates::table
+        .left_join(recent_crate_downloads::table)
+        .filter(crates::id.eq_any(ids))
+        .select((
+            crates::id,
+            (
+                c.text,
+                c.name_similarity,
+                c.recent_downloads,
+                c.reverse_dependencies,
+                c.recency,
+            ),
+        ))
+        .load(conn)?
+        .into_iter()
+        .collect();
+
+    Ok(crates
+        .iter()
+        .filter_map(|krate| {
+            let (text, name_similarity, recent_downloads, reverse_dependencies, recency) =
+                components.remove(&krate.id)?;
+            Some(ScoreBreakdown {
+                name: krate.name.clone(),
+                text,
+                name_similarity,
+                recent_downloads,
+                reverse_dependencies,
+                recency,
+                score: text * weights.text
+                    + name_similarity * weights.name_similarity
+                    + recent_downloads * weights.recent_downloads
+                    + reverse_dependencies * weights.reverse_dependencies
+                    + recency * weights.recency,
+            })
+        })
+        .collect())
+}
+
+/// Returns highlighted excerpts of the READMEs of `crates` that match the query string, by crate
+/// ID. The READMEs are HTML escaped, only the `<mark>` tags wrapping the matches are kept.
+fn readme_snippets(
+    conn: &PgConnection,
+    q_string: &str,
+    crates: &[Crate],
+) -> QueryResult<HashMap<i32, String>> {
+    use diesel::sql_types::Bool;
+
+    let ids = crates.iter().map(|krate| krate.id).collect::<Vec<_>>();
+    let snippets: Vec<(i32, String)> = crates::table
+        .filter(crates::id.eq_any(ids))
+        .filter(
+            sql::<Bool>("to_tsvector('english', crates.readme) @@ plainto_tsquery('english', ")
+                .bind::<Text, _>(q_string)
+                .sql(")"),
+        )
+        .select((
+            crates::id,
+            sql::<Text>("ts_headline('english', crates.readme, plainto_tsquery('english', ")
+                .bind::<Text, _>(q_string)
+                .sql(
+                    "), 'StartSel=<mark>, StopSel=</mark>, \
+                    MaxWords=30, MinWords=10, MaxFragments=2, FragmentDelimiter=\" … \"')",
+                ),
+        ))
+        .load(conn)?;
+
+    Ok(snippets
+        .into_iter()
+        .map(|(id, snippet)| (id, escape_snippet(&snippet)))
+        .collect())
+}
+
+/// Escapes the HTML in a `ts_headline` snippet, except for the `<mark>` tags.
+fn escape_snippet(snippet: &str) -> String {
+    snippet
+        .split("<mark>")
+        .map(|part| {
+            part.split("</mark>")
+                .map(escape_html)
+                .collect::<Vec<_>>()
+                .join("</mark>")
+        })
+        .collect::<Vec<_>>()
+        .join("<mark>")
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn uses_fuzzy_matching(q_string: &str) -> bool {
+    q_string.chars().count() >= MIN_FUZZY_QUERY_LENGTH
+}
+
+/// Returns the crate name or keyword most similar to the query, if any is similar enough.
+fn did_you_mean(conn: &PgConnection, q_string: &str) -> QueryResult<Option<String>> {
+    use diesel::sql_query;
+
+    #[derive(QueryableByName)]
+    struct Suggestion {
+        #[sql_type = "Text"]
+        suggestion: String,
+    }
+
+    let suggestion: Option<Suggestion> = sql_query(
+        "SELECT suggestion FROM ( \
+            SELECT name AS suggestion, \
+                similarity(squash_crate_name(name), squash_crate_name($1)) AS score, \
+                downloads AS popularity \
+            FROM crates \
+            WHERE squash_crate_name(name) % squash_crate_name($1) \
+            UNION ALL \
+            SELECT keyword, similarity(lower(keyword), lower($1)), crates_cnt \
+            FROM keywords \
+            WHERE lower(keyword) % lower($1) \
+        ) candidates \
+        WHERE squash_crate_name(suggestion) <> squash_crate_name($1) \
+        ORDER BY score DESC, popularity DESC \
+        LIMIT 1",
+    )
+    .bind::<Text, _>(q_string)
+    .get_result(conn)
+    .optional()?;
+
+    Ok(suggestion.map(|s| s.suggestion))
+}
+
+/// Returns the lowercase IDs of the licenses and exceptions in an SPDX license expression.
+fn license_ids(expression: &str) -> AppResult<Vec<String>> {
+    let expression = spdx::Expression::parse_mode(expression, spdx::ParseMode::LAX)
+        .map_err(|_| bad_request("license must be a valid SPDX license expression"))?;
+
+    Ok(expression
+        .requirements()
+        .flat_map(|requirement| {
+            let license = requirement.req.license.to_string();
+            let exception = requirement.req.exception.map(|exception| exception.name);
+            std::iter::once(license).chain(exception.map(str::to_string))
+        })
+        .map(|id| id.to_lowercase())
+        .collect())
+}
+
+fn parse_date(date: &str, param: &str) -> AppResult<NaiveDateTime> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|date| date.and_hms(0, 0, 0))
+        .map_err(|_| bad_request(&format!("{param} must be a date formatted as YYYY-MM-DD")))
+}
+
 diesel_infix_operator!(Contains, "@>");
+diesel_infix_operator!(TrigramMatches, " % ");
@@ -0,0 +1,149 @@
+//! Endpoints for managing the webhooks of a crate
+//!
+//! Webhooks are notified about new versions, yanks and owner changes. Only
+//! owners with full rights can see and modify them.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{
+    check_webhook_url, Crate, CrateWebhook, NewCrateWebhook, Rights, User, WebhookDelivery,
+};
+use crate::schema::{crate_webhooks, webhook_deliveries};
+use crate::views::{EncodableWebhook, EncodableWebhookDelivery};
+
+/// The maximum number of webhooks a single crate can have.
+const MAX_WEBHOOKS_PER_CRATE: i64 = 10;
+
+/// The number of deliveries returned by the deliveries endpoint.
+const DELIVERIES_LIMIT: i64 = 50;
+
+/// Handles the `GET /crates/:crate_id/webhooks` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let (krate, _) = authorize(req)?;
+    let conn = req.db_read_prefer_primary()?;
+
+    let webhooks = CrateWebhook::belonging_to(&krate)
+        .order(crate_webhooks::id)
+        .load::<CrateWebhook>(&*conn)?
+        .into_iter()
+        .map(EncodableWebhook::from)
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "webhooks": webhooks })))
+}
+
+/// Handles the `PUT /crates/:crate_id/webhooks` route.
+///
+/// The request body has the format:
+///
+/// ```json
+/// {"webhook": {"url": "https://example.com/hook", "secret": "..."}}
+/// ```
+///
+/// The URL must use `https`, and its host must not be a loopback, private or
+/// link-local address. Hosts resolving to such addresses are refused when
+/// delivering.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct NewWebhook {
+        url: String,
+        secret: String,
+    }
+
+    #[derive(Deserialize)]
+    struct NewWebhookRequest {
+        webhook: NewWebhook,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new: NewWebhookRequest = serde_json::from_str(&body)
+        .map_err(|e| bad_request(&format!("invalid new webhook request: {e}")))?;
+
+    if let Err(message) = check_webhook_url(&new.webhook.url) {
+        return Err(bad_request(&message));
+    }
+    if new.webhook.secret.is_empty() {
+        return Err(bad_request("secret must have a value"));
+    }
+
+    let (krate, user) = authorize(req)?;
+    let conn = req.db_write()?;
+
+    let count: i64 = CrateWebhook::belonging_to(&krate)
+        .count()
+        .get_result(&*conn)?;
+    if count >= MAX_WEBHOOKS_PER_CRATE {
+        return Err(bad_request(&format!(
+            "maximum webhooks per crate is: {MAX_WEBHOOKS_PER_CRATE}"
+        )));
+    }
+
+    let webhook = NewCrateWebhook {
+        crate_id: krate.id,
+        url: &new.webhook.url,
+        secret: &new.webhook.secret,
+        created_by: user.id,
+    }
+    .create(&conn)?;
+
+    Ok(req.json(&json!({ "webhook": EncodableWebhook::from(webhook) })))
+}
+
+/// Handles the `DELETE /crates/:crate_id/webhooks/:webhook_id` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    let webhook_id = parse_webhook_id(req)?;
+    let (krate, _) = authorize(req)?;
+    let conn = req.db_write()?;
+
+    diesel::delete(CrateWebhook::belonging_to(&krate).find(webhook_id)).execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Handles the `GET /crates/:crate_id/webhooks/:webhook_id/deliveries` route.
+///
+/// Returns the most recent deliveries of the webhook, newest first.
+pub fn deliveries(req: &mut dyn RequestExt) -> EndpointResult {
+    let webhook_id = parse_webhook_id(req)?;
+    let (krate, _) = authorize(req)?;
+    let conn = req.db_read_prefer_primary()?;
+
+    let webhook: CrateWebhook = CrateWebhook::belonging_to(&krate)
+        .find(webhook_id)
+        .first(&*conn)?;
+
+    let deliveries = WebhookDelivery::belonging_to(&webhook)
+        .order(webhook_deliveries::id.desc())
+        .limit(DELIVERIES_LIMIT)
+        .load::<WebhookDelivery>(&*conn)?
+        .into_iter()
+        .map(EncodableWebhookDelivery::from)
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "deliveries": deliveries })))
+}
+
+fn parse_webhook_id(req: &dyn RequestExt) -> AppResult<i32> {
+    req.params()["webhook_id"]
+        .parse::<i32>()
+        .map_err(|e| bad_request(&format!("invalid webhook id: {e:?}")))
+}
+
+/// Loads the crate from the request and checks that the authenticated user has
+/// full rights to it.
+fn authorize(req: &mut dyn RequestExt) -> AppResult<(Crate, User)> {
+    let user = req.authenticate()?.user();
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_prefer_primary()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    let owners = krate.owners_with_roles(&conn)?;
+
+    match user.rights(req.app(), &conn, &owners)? {
+        Rights::Full => Ok((krate, user)),
+        Rights::Publish | Rights::Yank => Err(cargo_err(
+            "only owners with the admin role have permission to manage webhooks",
+        )),
+        Rights::None => Err(cargo_err("only owners have permission to manage webhooks")),
+    }
+}
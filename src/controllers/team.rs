@@ -1,8 +1,9 @@
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::Team;
+use crate::models::{Team, TeamMember, TeamRole, User};
 use crate::schema::teams;
-use crate::views::EncodableTeam;
+use crate::util::errors::{forbidden, not_found};
+use crate::views::{EncodableTeam, EncodableTeamMember};
 
 /// Handles the `GET /teams/:team_id` route.
 pub fn show_team(req: &mut dyn RequestExt) -> EndpointResult {
@@ -14,3 +15,160 @@ pub fn show_team(req: &mut dyn RequestExt) -> EndpointResult {
 
     Ok(req.json(&json!({ "team": EncodableTeam::from(team) })))
 }
+
+/// Handles the `PUT /teams` route.
+///
+/// Creates a registry-native team, which can be added as a crate owner as `registry:{name}`.
+/// The user creating the team becomes its first maintainer.
+///
+/// ## Request Body Example
+///
+/// ```json
+/// {"team": {"name": "serde-maintainers", "display_name": "Serde maintainers"}}
+/// ```
+pub fn new(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct NewTeam {
+        name: String,
+        display_name: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct NewTeamRequest {
+        team: NewTeam,
+    }
+
+    let user = req.authenticate()?.user();
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new: NewTeamRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let conn = req.db_write()?;
+    let team = Team::create_native(
+        &conn,
+        &new.team.name,
+        new.team.display_name.as_deref(),
+        &user,
+    )?;
+
+    Ok(req.json(&json!({ "team": EncodableTeam::from(team) })))
+}
+
+/// Handles the `GET /teams/:team_id/members` route.
+pub fn members(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read()?;
+    let team = find_native_team(req, &conn)?;
+
+    let members = TeamMember::for_team(&conn, team.id)?
+        .into_iter()
+        .map(|(member, user)| EncodableTeamMember {
+            login: user.gh_login,
+            role: member.role,
+        })
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "members": members })))
+}
+
+/// Handles the `PUT /teams/:team_id/members/:user_id` route.
+///
+/// Adds a user to a registry-native team, or changes their role if they're already a member.
+/// Only maintainers of the team can manage its members.
+///
+/// ## Request Body Example
+///
+/// ```json
+/// {"role": "maintainer"}
+/// ```
+pub fn add_member(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct AddMemberRequest {
+        role: Option<TeamRole>,
+    }
+
+    let user = req.authenticate()?.user();
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let role = if body.trim().is_empty() {
+        TeamRole::Member
+    } else {
+        serde_json::from_str::<AddMemberRequest>(&body)
+            .map_err(|_| bad_request("invalid json request"))?
+            .role
+            .unwrap_or(TeamRole::Member)
+    };
+
+    let conn = req.db_write()?;
+    conn.transaction(|| {
+        let team = find_native_team(req, &conn)?;
+        let role_of_user = TeamMember::find(&conn, team.id, user.id)?.map(|member| member.role);
+        if role_of_user != Some(TeamRole::Maintainer) {
+            return Err(forbidden());
+        }
+
+        let member = find_member_user(req, &conn)?;
+        if role == TeamRole::Member {
+            ensure_other_maintainer(&conn, &team, member.id)?;
+        }
+
+        TeamMember::add(&conn, team.id, member.id, role)?;
+        Ok(())
+    })?;
+
+    ok_true()
+}
+
+/// Handles the `DELETE /teams/:team_id/members/:user_id` route.
+///
+/// Removes a user from a registry-native team. Maintainers can remove any member, other members
+/// can only remove themselves.
+pub fn remove_member(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let conn = req.db_write()?;
+
+    conn.transaction(|| {
+        let team = find_native_team(req, &conn)?;
+        let member = find_member_user(req, &conn)?;
+
+        let role_of_user = TeamMember::find(&conn, team.id, user.id)?.map(|member| member.role);
+        if member.id != user.id && role_of_user != Some(TeamRole::Maintainer) {
+            return Err(forbidden());
+        }
+
+        ensure_other_maintainer(&conn, &team, member.id)?;
+        if TeamMember::remove(&conn, team.id, member.id)? == 0 {
+            return Err(bad_request(&format_args!(
+                "user {} is not a member of team {}",
+                member.gh_login, team.login
+            )));
+        }
+        Ok(())
+    })?;
+
+    ok_true()
+}
+
+fn find_native_team(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<Team> {
+    Team::find_native(conn, &req.params()["team_id"])?.ok_or_else(not_found)
+}
+
+fn find_member_user(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<User> {
+    let login = &req.params()["user_id"];
+    User::find_by_login(conn, login)
+        .optional()?
+        .ok_or_else(|| bad_request(&format_args!("could not find user with login `{}`", login)))
+}
+
+/// Makes sure that the team keeps a maintainer once `user_id` is demoted or removed.
+fn ensure_other_maintainer(conn: &PgConnection, team: &Team, user_id: i32) -> AppResult<()> {
+    let role = TeamMember::find(conn, team.id, user_id)?.map(|member| member.role);
+    if role == Some(TeamRole::Maintainer) && TeamMember::maintainer_count(conn, team.id)? == 1 {
+        return Err(bad_request(
+            "the last maintainer of a team can't be removed",
+        ));
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+pub mod identities;
 pub mod me;
 pub mod other;
 pub mod session;
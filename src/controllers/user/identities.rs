@@ -0,0 +1,54 @@
+use crate::controllers::frontend_prelude::*;
+
+use crate::identity::GITHUB_PROVIDER;
+use crate::models::LinkedIdentity;
+use crate::schema::linked_identities;
+use crate::util::errors::not_found;
+use crate::views::EncodableLinkedIdentity;
+
+/// Handles the `GET /me/identities` route.
+///
+/// Lists the identity providers the user can log in with.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.forbid_api_token_auth()?.user_id();
+    let conn = req.db_read_prefer_primary()?;
+
+    let identities = LinkedIdentity::for_user(&conn, user_id)?
+        .into_iter()
+        .map(EncodableLinkedIdentity::from)
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "identities": identities })))
+}
+
+/// Handles the `DELETE /me/identities/:provider` route.
+///
+/// Unlinks an identity provider from the user. The GitHub identity can't be unlinked, and neither
+/// can the last identity of the user, since they couldn't log in anymore.
+pub fn unlink(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.forbid_api_token_auth()?.user_id();
+    let provider = req.params()["provider"].to_string();
+    let conn = req.db_write()?;
+
+    if provider == GITHUB_PROVIDER {
+        return Err(bad_request(
+            "the GitHub account of a user can't be unlinked",
+        ));
+    }
+
+    conn.transaction(|| {
+        let identities = LinkedIdentity::for_user(&conn, user_id)?;
+        let identity = identities
+            .iter()
+            .find(|identity| identity.provider == provider)
+            .ok_or_else(not_found)?;
+        if identities.len() == 1 {
+            return Err(bad_request("the last identity of a user can't be unlinked"));
+        }
+
+        diesel::delete(linked_identities::table.find(identity.id)).execute(&*conn)?;
+        Ok(())
+    })?;
+
+    ok_true()
+}
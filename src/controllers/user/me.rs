@@ -6,7 +6,8 @@ use crate::controllers::helpers::*;
 
 use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    Advisory, CrateOwner, CrateOwnerEmailInvitation, Email, Follow, NewEmail, OwnerKind, User,
+    Version, VersionOwnerAction,
 };
 use crate::schema::{crate_owners, crates, emails, follows, users, versions};
 use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
@@ -76,12 +77,13 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     let data = data
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, cn, pb), voas)| (v, cn, pb, voas));
+        .zip(Advisory::ids_for_versions(&conn, &versions)?.into_iter())
+        .map(|(((v, cn, pb), voas), advisories)| (v, cn, pb, voas, advisories));
 
     let versions = data
         .into_iter()
-        .map(|(version, crate_name, published_by, actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
+        .map(|(version, crate_name, published_by, actions, advisories)| {
+            EncodableVersion::from(version, &crate_name, published_by, actions, advisories)
         })
         .collect::<Vec<_>>();
 
@@ -169,13 +171,18 @@ pub fn confirm_user_email(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_write()?;
     let req_token = &req.params()["email_token"];
 
-    let updated_rows = update(emails::table.filter(emails::token.eq(req_token)))
-        .set(emails::verified.eq(true))
-        .execute(&*conn)?;
-
-    if updated_rows == 0 {
-        return Err(bad_request("Email belonging to token not found."));
-    }
+    conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        let (user_id, email) = update(emails::table.filter(emails::token.eq(req_token)))
+            .set(emails::verified.eq(true))
+            .returning((emails::user_id, emails::email))
+            .get_result::<(i32, String)>(&*conn)
+            .optional()?
+            .ok_or_else(|| bad_request("Email belonging to token not found."))?;
+
+        // Crate ownership invitations sent to this address can now be accepted
+        CrateOwnerEmailInvitation::convert_for_user(&conn, &req.app().config, user_id, &email)?;
+        Ok(())
+    })?;
 
     ok_true()
 }
@@ -6,9 +6,15 @@ use oauth2::{AuthorizationCode, Scope, TokenResponse};
 
 use crate::email::Emails;
 use crate::github::GithubUser;
-use crate::models::{NewUser, User};
+use crate::identity::{Identity, LoginState, GITHUB_PROVIDER};
+use crate::models::{LinkedIdentity, NewLinkedIdentity, NewUser, User};
 use crate::schema::users;
-use crate::util::errors::ReadOnlyMode;
+use crate::sql::lower;
+use crate::util::errors::{not_found, ReadOnlyMode};
+use crate::views::EncodableIdentityProvider;
+
+/// Session key of the `LoginState` of a login with an identity provider other than GitHub.
+const LOGIN_STATE_KEY: &str = "identity_login_state";
 
 /// Handles the `GET /api/private/session/begin` route.
 ///
@@ -123,6 +129,20 @@ fn save_user_to_database(
         access_token,
     )
     .create_or_update(user.email.as_deref(), emails, conn)
+    .and_then(|db_user| {
+        // Users with a placeholder GitHub ID can't have logged in with GitHub
+        if user.id > 0 {
+            NewLinkedIdentity {
+                user_id: db_user.id,
+                provider: GITHUB_PROVIDER,
+                subject: &user.id.to_string(),
+                login: &user.login,
+                email: user.email.as_deref(),
+            }
+            .create_or_update(conn)?;
+        }
+        Ok(db_user)
+    })
     .map_err(Into::into)
     .or_else(|e: Box<dyn AppError>| {
         // If we're in read only mode, we can't update their details
@@ -139,6 +159,174 @@ fn save_user_to_database(
     })
 }
 
+/// Handles the `GET /api/private/session/providers` route.
+///
+/// Lists the identity providers users can log in with, besides GitHub.
+///
+/// ## Response Body Example
+///
+/// ```json
+/// {
+///     "providers": [{ "name": "keycloak", "display_name": "Keycloak" }]
+/// }
+/// ```
+pub fn providers(req: &mut dyn RequestExt) -> EndpointResult {
+    let providers = req
+        .app()
+        .identity_providers
+        .iter()
+        .map(|provider| EncodableIdentityProvider {
+            name: provider.name().to_string(),
+            display_name: provider.display_name().to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(req.json(&json!({ "providers": providers })))
+}
+
+/// Handles the `GET /api/private/session/:provider/begin` route.
+///
+/// Same as `GET /api/private/session/begin`, but returns the authorization URL of the identity
+/// provider named `provider`.
+pub fn begin_with_provider(req: &mut dyn RequestExt) -> EndpointResult {
+    let app = req.app().clone();
+    let provider = app
+        .identity_provider(&req.params()["provider"])
+        .ok_or_else(not_found)?;
+
+    let (url, state) = provider.begin_login()?;
+    let csrf_token = state.csrf_token.clone();
+    req.session_mut()
+        .insert(LOGIN_STATE_KEY.to_string(), serde_json::to_string(&state)?);
+
+    Ok(req.json(&json!({ "url": url.to_string(), "state": csrf_token })))
+}
+
+/// Handles the `GET /api/private/session/:provider/authorize` route.
+///
+/// Same as `GET /api/private/session/authorize`, but for the identity provider named `provider`.
+///
+/// If the user is already logged in, the identity is linked to their account, so that they can
+/// log in with either provider afterwards. Otherwise they're logged in to the account the identity
+/// is linked to, or to a new account if the identity isn't linked yet.
+///
+/// ## Query Parameters
+///
+/// - `code` – temporary code received from the identity provider  **(Required)**
+/// - `state` – state parameter received from the identity provider  **(Required)**
+pub fn authorize_with_provider(req: &mut dyn RequestExt) -> EndpointResult {
+    let mut query = req.query();
+    let code = query.remove("code").unwrap_or_default();
+    let state = query.remove("state").unwrap_or_default();
+    let provider_name = req.params()["provider"].to_string();
+
+    // Make sure that the state we just got matches the session state that we
+    // should have issued earlier.
+    let login_state = req
+        .session_mut()
+        .remove(&LOGIN_STATE_KEY.to_string())
+        .and_then(|login_state| serde_json::from_str::<LoginState>(&login_state).ok())
+        .filter(|login_state| {
+            login_state.provider == provider_name && login_state.csrf_token == state
+        })
+        .ok_or_else(|| bad_request("invalid state parameter"))?;
+
+    let app = req.app().clone();
+    let provider = app
+        .identity_provider(&provider_name)
+        .ok_or_else(not_found)?;
+    let identity = provider.complete_login(&code, &login_state)?;
+
+    let current_user_id = req
+        .session()
+        .get("user_id")
+        .and_then(|id| id.parse::<i32>().ok());
+    let user =
+        save_identity_to_database(&identity, current_user_id, &app.emails, &*req.db_write()?)?;
+
+    // Log in by setting a cookie and the middleware authentication
+    req.session_mut()
+        .insert("user_id".to_string(), user.id.to_string());
+
+    super::me::me(req)
+}
+
+/// Returns the user an identity is linked to, linking it to the logged in user or to a new user
+/// first if it isn't linked yet.
+///
+/// Users created from another identity provider don't have a GitHub account, so their `gh_id` is
+/// `0`. Their login is suffixed with the name of the provider, e.g. `alice@keycloak`, so that it
+/// can't be mistaken for the GitHub user with the same login.
+fn save_identity_to_database(
+    identity: &Identity,
+    current_user_id: Option<i32>,
+    emails: &Emails,
+    conn: &PgConnection,
+) -> AppResult<User> {
+    conn.transaction(|| {
+        let linked =
+            LinkedIdentity::find(conn, &identity.provider, &identity.subject).optional()?;
+        let user = match (linked, current_user_id) {
+            (Some(linked), Some(user_id)) if linked.user_id != user_id => {
+                return Err(bad_request(&format!(
+                    "this {} account is already linked to another user",
+                    identity.provider
+                )));
+            }
+            (Some(linked), _) => User::find(conn, linked.user_id)?,
+            (None, Some(user_id)) => User::find(conn, user_id)?,
+            (None, None) => {
+                let login = unused_login(identity, conn)?;
+                NewUser::new(
+                    0,
+                    &login,
+                    identity.name.as_deref(),
+                    identity.avatar.as_deref(),
+                    "",
+                )
+                .create_or_update(identity.email.as_deref(), emails, conn)?
+            }
+        };
+
+        NewLinkedIdentity {
+            user_id: user.id,
+            provider: &identity.provider,
+            subject: &identity.subject,
+            login: &identity.login,
+            email: identity.email.as_deref(),
+        }
+        .create_or_update(conn)?;
+
+        Ok(user)
+    })
+}
+
+/// Returns the login of a new user created by logging in with `identity`, which is
+/// `{login}@{provider}` unless another user already has it, in which case a numeric suffix is
+/// added to the login of the identity (e.g. `alice-2@provider`).
+///
+/// GitHub logins can't contain `@`, so this can only clash with other users created through an
+/// identity provider. Their logins are also kept unique by the `users_identity_login` index.
+fn unused_login(identity: &Identity, conn: &PgConnection) -> QueryResult<String> {
+    use diesel::dsl::exists;
+    use diesel::select;
+
+    let mut login = format!("{}@{}", identity.login, identity.provider);
+    let mut suffix = 1;
+    loop {
+        let taken = select(exists(
+            users::table.filter(lower(users::gh_login).eq(login.to_lowercase())),
+        ))
+        .get_result(conn)?;
+        if !taken {
+            return Ok(login);
+        }
+
+        suffix += 1;
+        login = format!("{}-{suffix}@{}", identity.login, identity.provider);
+    }
+}
+
 /// Handles the `DELETE /api/private/session` route.
 pub fn logout(req: &mut dyn RequestExt) -> EndpointResult {
     req.session_mut().remove(&"user_id".to_string());
@@ -1,6 +1,10 @@
 pub mod deprecated;
+pub mod diff;
 pub mod downloads;
+pub mod files;
 pub mod metadata;
+pub mod resolve;
+pub mod sbom;
 pub mod yank;
 
 use super::prelude::*;
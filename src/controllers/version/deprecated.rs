@@ -7,7 +7,7 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, User, Version, VersionOwnerAction};
+use crate::models::{Advisory, Crate, User, Version, VersionOwnerAction};
 use crate::schema::*;
 use crate::views::EncodableVersion;
 
@@ -40,9 +40,12 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     let versions = versions_and_publishers
         .into_iter()
         .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, crate_name, published_by), actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
-        })
+        .zip(Advisory::ids_for_versions(&conn, &versions)?.into_iter())
+        .map(
+            |(((version, crate_name, published_by), actions), advisories)| {
+                EncodableVersion::from(version, &crate_name, published_by, actions, advisories)
+            },
+        )
         .collect::<Vec<_>>();
 
     Ok(req.json(&json!({ "versions": versions })))
@@ -66,7 +69,14 @@ pub fn show_by_id(req: &mut dyn RequestExt) -> EndpointResult {
         ))
         .first(&*conn)?;
     let audit_actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::ids_for_version(&conn, &version)?;
 
-    let version = EncodableVersion::from(version, &krate.name, published_by, audit_actions);
+    let version = EncodableVersion::from(
+        version,
+        &krate.name,
+        published_by,
+        audit_actions,
+        advisories,
+    );
     Ok(req.json(&json!({ "version": version })))
 }
@@ -0,0 +1,90 @@
+//! Endpoint for comparing the files of two versions of a crate
+
+use crate::controllers::frontend_prelude::*;
+
+use super::files::pending;
+use crate::crate_files::diff::{load_changed_files, VersionDiff};
+use crate::crate_files::{list, CrateFile};
+use crate::models::Crate;
+use crate::util::errors::not_found;
+
+/// Handles the `GET /crates/:crate_id/diff/:from/:to` route.
+///
+/// Returns a summary of the added, removed and modified files. If the `path`
+/// query parameter is given, the unified diff of that file is returned instead.
+///
+/// The files are read from the storage cache. Responds with `202 Accepted`
+/// while the files of either version are not cached yet.
+pub fn diff(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let from = &req.params()["from"];
+    let to = &req.params()["to"];
+    for num in [from, to] {
+        if semver::Version::parse(num).is_err() {
+            return Err(bad_request(&format_args!("invalid semver: {}", num)));
+        }
+    }
+    let path = req.query().get("path").cloned();
+
+    let conn = req.db_read()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+    let from = krate.find_version(&conn, from)?;
+    let to = krate.find_version(&conn, to)?;
+    drop(conn);
+
+    let app = req.app();
+    let (old_listing, new_listing) = match (
+        list(app, &krate.name, &from.num)?,
+        list(app, &krate.name, &to.num)?,
+    ) {
+        (Some(old_listing), Some(new_listing)) => (old_listing, new_listing),
+        (old_listing, new_listing) => {
+            let mut versions = Vec::new();
+            if old_listing.is_none() {
+                versions.push(from.num.as_str());
+            }
+            if new_listing.is_none() {
+                versions.push(to.num.as_str());
+            }
+            return pending(req, &krate.name, &versions);
+        }
+    };
+
+    let (old_files, new_files) = load_changed_files(
+        app,
+        &krate.name,
+        (&from.num, &old_listing),
+        (&to.num, &new_listing),
+        path.as_deref(),
+    )?;
+    let diff = VersionDiff::new(&old_files, &new_files);
+
+    match path {
+        Some(path) => {
+            // Files that are unchanged between the versions are not loaded, so
+            // the listings are used to tell them apart from missing files.
+            let patch = match diff.patch(&path) {
+                Some(patch) => patch,
+                None if contains(&old_listing, &path) && contains(&new_listing, &path) => {
+                    String::new()
+                }
+                None => return Err(not_found()),
+            };
+            Ok(req.json(&json!({
+                "from": from.num,
+                "to": to.num,
+                "path": path,
+                "patch": patch,
+            })))
+        }
+        None => Ok(req.json(&json!({
+            "from": from.num,
+            "to": to.num,
+            "files": diff.summary(),
+        }))),
+    }
+}
+
+fn contains(files: &[CrateFile], path: &str) -> bool {
+    files.iter().any(|file| file.path == path)
+}
@@ -6,9 +6,10 @@ use super::{extract_crate_name_and_semver, version_and_crate};
 use crate::controllers::prelude::*;
 use crate::db::PoolError;
 use crate::middleware::log_request::add_custom_metadata;
-use crate::models::{Crate, VersionDownload};
+use crate::models::{AgentDownloads, Crate, MonthlyDownloads, VersionDownload};
 use crate::schema::*;
-use crate::views::EncodableVersionDownload;
+use crate::util::request_header;
+use crate::views::{EncodableAgentDownloads, EncodableMonthlyDownload, EncodableVersionDownload};
 use chrono::{Duration, NaiveDate, Utc};
 
 /// Handles the `GET /crates/:crate_id/:version/download` route.
@@ -25,7 +26,9 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
 
         // The increment does not happen instantly, but it's deferred to be executed in a batch
         // along with other downloads. See crate::downloads_counter for the implementation.
-        app.downloads_counter.increment(version_id);
+        let user_agent = request_header(req, header::USER_AGENT);
+        app.downloads_counter
+            .increment_with_user_agent(version_id, user_agent);
     } else {
         app.instance_metrics.version_id_cache_misses.inc();
 
@@ -77,7 +80,9 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
 
             // The increment does not happen instantly, but it's deferred to be executed in a batch
             // along with other downloads. See crate::downloads_counter for the implementation.
-            app.downloads_counter.increment(version_id);
+            let user_agent = request_header(req, header::USER_AGENT);
+            app.downloads_counter
+                .increment_with_user_agent(version_id, user_agent);
         } else {
             // The download endpoint is the most critical route in the whole crates.io application,
             // as it's relied upon by users and automations to download crates. Keeping it working
@@ -138,5 +143,34 @@ pub fn downloads(req: &mut dyn RequestExt) -> EndpointResult {
         .map(VersionDownload::into)
         .collect::<Vec<EncodableVersionDownload>>();
 
-    Ok(req.json(&json!({ "version_downloads": downloads })))
+    let user_agents =
+        AgentDownloads::for_versions(&conn, &[version.id], cutoff_start_date, cutoff_end_date)?
+            .into_iter()
+            .map(AgentDownloads::into)
+            .collect::<Vec<EncodableAgentDownloads>>();
+
+    Ok(req.json(&json!({
+        "version_downloads": downloads,
+        "meta": {
+            "user_agents": user_agents,
+        },
+    })))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/monthly_downloads` route.
+///
+/// Unlike the `downloads` route this is not limited to the last 90 days, and returns the
+/// downloads of every month since the version was published.
+pub fn monthly_downloads(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+
+    let conn = req.db_read()?;
+    let (version, _) = version_and_crate(&conn, crate_name, semver)?;
+
+    let downloads = MonthlyDownloads::for_versions(&conn, &[version.id])?
+        .into_iter()
+        .map(MonthlyDownloads::into)
+        .collect::<Vec<EncodableMonthlyDownload>>();
+
+    Ok(req.json(&json!({ "monthly_downloads": downloads })))
 }
@@ -0,0 +1,68 @@
+//! Endpoints for browsing the files inside a published version
+
+use conduit::{Body, Response};
+
+use super::{extract_crate_name_and_semver, version_and_crate};
+use crate::controllers::frontend_prelude::*;
+use crate::crate_files;
+use crate::util::errors::not_found;
+
+/// How long clients should wait before retrying while the files are cached.
+const RETRY_AFTER_SECONDS: u32 = 5;
+
+/// Handles the `GET /crates/:crate_id/:version/files` route.
+///
+/// Responds with `202 Accepted` while the files of the version are not cached
+/// yet.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let conn = req.db_read()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+
+    match crate_files::list(req.app(), &krate.name, &version.num)? {
+        Some(files) => Ok(req.json(&json!({ "files": files }))),
+        None => pending(req, &krate.name, &[&version.num]),
+    }
+}
+
+/// Handles the `GET /crates/:crate_id/:version/files/*path` route.
+///
+/// Responds with `202 Accepted` while the files of the version are not cached
+/// yet.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let path = &req.params()["path"];
+    let conn = req.db_read()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+
+    let files = match crate_files::list(req.app(), &krate.name, &version.num)? {
+        Some(files) => files,
+        None => return pending(req, &krate.name, &[&version.num]),
+    };
+
+    let content = crate_files::read(req.app(), &files, &krate.name, &version.num, path)?
+        .ok_or_else(not_found)?;
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, crate_files::content_type(&content))
+        .header(header::CONTENT_LENGTH, content.len())
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Body::from_vec(content))?)
+}
+
+/// Enqueues the background jobs caching the files of the given versions, and
+/// asks the client to retry once they have run.
+pub(super) fn pending(req: &dyn RequestExt, crate_name: &str, versions: &[&str]) -> EndpointResult {
+    let conn = req.db_write()?;
+    for version in versions {
+        crate_files::enqueue_caching(req.app(), &conn, crate_name, version)?;
+    }
+
+    let detail = "the files of this version are being prepared, please retry shortly";
+    let mut response = req.json(&json!({ "errors": [{ "detail": detail }] }));
+    *response.status_mut() = StatusCode::ACCEPTED;
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, RETRY_AFTER_SECONDS.into());
+    Ok(response)
+}
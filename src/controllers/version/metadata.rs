@@ -6,7 +6,7 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
+use crate::models::{Advisory, VersionOwnerAction};
 use crate::views::{EncodableDependency, EncodableVersion};
 
 use super::{extract_crate_name_and_semver, version_and_crate};
@@ -52,7 +52,8 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
     let published_by = version.published_by(&conn);
     let actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::ids_for_version(&conn, &version)?;
 
-    let version = EncodableVersion::from(version, &krate.name, published_by, actions);
+    let version = EncodableVersion::from(version, &krate.name, published_by, actions, advisories);
     Ok(req.json(&json!({ "version": version })))
 }
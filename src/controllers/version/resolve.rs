@@ -0,0 +1,82 @@
+//! Endpoint for resolving the transitive dependencies of a version
+
+use super::{extract_crate_name_and_semver, version_and_crate};
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::DependencyKind;
+use crate::resolver::{self, DatabaseRegistry, Registry, ResolveOptions, Target};
+
+/// Handles the `GET /crates/:crate_id/:version/resolve` route.
+///
+/// Supported query parameters:
+///
+/// - `features`: comma separated list of features to enable
+/// - `default_features`: set to `false` to disable the default features
+/// - `target`: target triple used to evaluate `target` filters
+/// - `kinds`: comma separated list of dependency kinds of the version to
+///   include, defaults to `normal,build`
+/// - `include_yanked`: set to `true` to allow yanked versions
+pub fn resolve(req: &mut dyn RequestExt) -> EndpointResult {
+    let options = parse_options(req)?;
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let conn = req.db_read()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+
+    let mut registry = DatabaseRegistry::new(&conn);
+    let root = registry
+        .versions(krate.id)?
+        .iter()
+        .find(|candidate| candidate.version_id == version.id)
+        .cloned()
+        .ok_or_else(|| cargo_err(&format_args!("invalid semver: {}", version.num)))?;
+
+    let resolution = resolver::resolve(&mut registry, root, &options)?;
+
+    Ok(req.json(&resolution))
+}
+
+fn parse_options(req: &dyn RequestExt) -> AppResult<ResolveOptions> {
+    let query = req.query();
+    let list = |name: &str| -> Vec<String> {
+        query
+            .get(name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut options = ResolveOptions {
+        features: list("features"),
+        default_features: query.get("default_features").map(String::as_str) != Some("false"),
+        include_yanked: query.get("include_yanked").map(String::as_str) == Some("true"),
+        ..Default::default()
+    };
+
+    if let Some(triple) = query.get("target") {
+        options.target = Some(
+            Target::from_triple(triple)
+                .ok_or_else(|| bad_request(&format!("unknown target `{triple}`")))?,
+        );
+    }
+
+    let kinds = list("kinds");
+    if !kinds.is_empty() {
+        options.kinds = kinds
+            .iter()
+            .map(|kind| match kind.as_str() {
+                "normal" => Ok(DependencyKind::Normal),
+                "build" => Ok(DependencyKind::Build),
+                "dev" => Ok(DependencyKind::Dev),
+                _ => Err(bad_request(&format!("invalid dependency kind `{kind}`"))),
+            })
+            .collect::<AppResult<_>>()?;
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,58 @@
+//! Endpoint for downloading a software bill of materials of a version
+
+use conduit::{Body, Response};
+
+use super::{extract_crate_name_and_semver, version_and_crate};
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::Dependency;
+use crate::views::sbom::{self, SbomDependency, SbomSubject};
+
+/// Handles the `GET /crates/:crate_id/:version/sbom` route.
+///
+/// The `format` query parameter selects the document format, either
+/// `cyclonedx` (the default) or `spdx`.
+pub fn sbom(req: &mut dyn RequestExt) -> EndpointResult {
+    let format = req.query().get("format").cloned();
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let conn = req.db_read()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+
+    let (dependencies, crate_names): (Vec<_>, Vec<_>) =
+        version.dependencies(&conn)?.into_iter().unzip();
+    let resolved = Dependency::latest_matching_versions(&conn, &dependencies)?;
+    let dependencies = dependencies
+        .into_iter()
+        .zip(crate_names)
+        .zip(resolved)
+        .map(|((dependency, crate_name), resolved)| SbomDependency {
+            dependency,
+            crate_name,
+            resolved,
+        })
+        .collect::<Vec<_>>();
+
+    let subject = SbomSubject {
+        krate: &krate,
+        version: &version,
+        domain_name: &req.app().config.domain_name,
+    };
+    let (content_type, document) = match format.as_deref() {
+        None | Some("cyclonedx") => (
+            "application/vnd.cyclonedx+json",
+            sbom::cyclonedx(&subject, &dependencies),
+        ),
+        Some("spdx") => ("application/spdx+json", sbom::spdx(&subject, &dependencies)),
+        Some(format) => {
+            return Err(bad_request(&format!(
+                "unsupported SBOM format `{format}`, expected `cyclonedx` or `spdx`"
+            )))
+        }
+    };
+
+    let body = serde_json::to_vec(&document)?;
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, body.len())
+        .body(Body::from_vec(body))?)
+}
@@ -4,9 +4,13 @@ use swirl::Job;
 
 use super::{extract_crate_name_and_semver, version_and_crate};
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::helpers::notifications::{token_name, OwnerNotification};
+use crate::email::VersionNotification;
 use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::{insert_version_owner_action, VersionAction, WebhookEvent, WebhookPayload};
 use crate::schema::versions;
+use crate::util::request_header;
+use crate::views::EncodableDependent;
 use crate::worker;
 
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
@@ -27,6 +31,28 @@ pub fn unyank(req: &mut dyn RequestExt) -> EndpointResult {
     modify_yank(req, false)
 }
 
+/// Handles the `GET /crates/:crate_id/:version/yank_impact` route.
+///
+/// Lists the dependents whose requirement can no longer be satisfied once the
+/// version is yanked, sorted by the downloads of the dependent crates.
+pub fn impact(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let conn = req.db_read()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+
+    let dependents = krate
+        .yank_impact(&conn, &version)?
+        .into_iter()
+        .map(EncodableDependent::from)
+        .collect::<Vec<_>>();
+    let total = dependents.len();
+
+    Ok(req.json(&json!({
+        "dependents": dependents,
+        "meta": { "total": total },
+    })))
+}
+
 /// Changes `yanked` flag on a crate version record
 fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
     // FIXME: Should reject bad requests before authentication, but can't due to
@@ -38,9 +64,9 @@ fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
     let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
     let api_token_id = authenticated_user.api_token_id();
     let user = authenticated_user.user();
-    let owners = krate.owners(&conn)?;
+    let owners = krate.owners_with_roles(&conn)?;
 
-    if user.rights(req.app(), &owners)? < Rights::Publish {
+    if user.rights(req.app(), &conn, &owners)? < Rights::Yank {
         return Err(cargo_err("must already be an owner to yank or unyank"));
     }
 
@@ -49,19 +75,40 @@ fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
         return ok_true();
     }
 
-    diesel::update(&version)
-        .set(versions::yanked.eq(yanked))
-        .execute(&*conn)?;
+    let user_agent = request_header(req, header::USER_AGENT);
+    let notification = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        diesel::update(&version)
+            .set(versions::yanked.eq(yanked))
+            .execute(&*conn)?;
+
+        let (action, event) = if yanked {
+            (VersionAction::Yank, WebhookEvent::Yank)
+        } else {
+            (VersionAction::Unyank, WebhookEvent::Unyank)
+        };
+
+        insert_version_owner_action(&conn, version.id, user.id, api_token_id, action)?;
+
+        let payload =
+            WebhookPayload::new(event, &krate.name, &user.gh_login).with_version(&version.num);
+        worker::notify_webhooks(&conn, krate.id, &payload)?;
+
+        let notification = VersionNotification {
+            crate_name: krate.name.clone(),
+            version: version.num.clone(),
+            action,
+            user: user.gh_login.clone(),
+            token_name: token_name(&conn, api_token_id)?,
+            user_agent: user_agent.to_string(),
+        };
+        let notification = OwnerNotification::new(&conn, krate.id, notification)?;
 
-    let action = if yanked {
-        VersionAction::Yank
-    } else {
-        VersionAction::Unyank
-    };
+        worker::sync_yanked(krate.name.clone(), version.num.clone()).enqueue(&conn)?;
 
-    insert_version_owner_action(&conn, version.id, user.id, api_token_id, action)?;
+        Ok(notification)
+    })?;
 
-    worker::sync_yanked(krate.name, version.num).enqueue(&conn)?;
+    notification.send(&req.app().emails);
 
     ok_true()
 }
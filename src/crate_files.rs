@@ -0,0 +1,227 @@
+//! Access to the source files inside the uploaded `.crate` archives.
+//!
+//! The files are only served from storage. The first time the files of a
+//! version are requested, the `cache_crate_files` background job is enqueued
+//! to extract the archive and store the file listing and the individual files,
+//! and the API asks the client to retry once the job has run.
+
+use std::io::Read;
+use std::path::{Component, Path};
+
+use diesel::prelude::*;
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use swirl::Job;
+
+use crate::schema::background_jobs;
+use crate::util::errors::{bad_request, internal, AppResult};
+use crate::util::LimitErrorReader;
+use crate::{worker, App};
+
+pub mod diff;
+
+/// The maximum size of a single file that is returned by the API.
+pub const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// A single file inside a `.crate` archive.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CrateFile {
+    /// Path of the file, relative to the root of the package.
+    pub path: String,
+    pub size: u64,
+    pub mode: u32,
+    /// Hex encoded SHA256 of the content. Listings that were cached before
+    /// checksums were recorded don't have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+}
+
+/// Returns the files inside the archive of a version, sorted by path, or
+/// `None` if they have not been cached yet.
+pub fn list(app: &App, crate_name: &str, version: &str) -> AppResult<Option<Vec<CrateFile>>> {
+    let uploader = app.config.uploader();
+    let listing = uploader
+        .download_crate_files_listing(app.http_client(), crate_name, version)
+        .map_err(|e| internal(&format_args!("failed to download file listing: {e}")))?;
+
+    match listing {
+        Some(listing) => Ok(Some(serde_json::from_slice(&listing)?)),
+        None => Ok(None),
+    }
+}
+
+/// Enqueues the `cache_crate_files` job for a version, unless it is already in
+/// the queue.
+pub fn enqueue_caching(
+    app: &App,
+    conn: &PgConnection,
+    crate_name: &str,
+    version: &str,
+) -> AppResult<()> {
+    let job = worker::cache_crate_files(
+        crate_name.to_string(),
+        version.to_string(),
+        app.config.max_unpack_size,
+    );
+    let data = serde_json::to_value(&job)?;
+
+    let enqueued: i64 = background_jobs::table
+        .filter(background_jobs::job_type.eq("cache_crate_files"))
+        .filter(background_jobs::data.eq(data))
+        .count()
+        .get_result(conn)?;
+    if enqueued == 0 {
+        job.enqueue(conn)?;
+    }
+    Ok(())
+}
+
+/// Returns the contents of a single file inside the archive of a version, or
+/// `None` if the archive does not contain the file.
+///
+/// `files` is the cached listing of the version, as returned by `list`.
+pub fn read(
+    app: &App,
+    files: &[CrateFile],
+    crate_name: &str,
+    version: &str,
+    path: &str,
+) -> AppResult<Option<Vec<u8>>> {
+    let file = match files.iter().find(|file| file.path == path) {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+
+    if file.size > MAX_FILE_SIZE {
+        return Err(bad_request(&format_args!(
+            "file is too large to be displayed ({} bytes, the maximum is {} bytes)",
+            file.size, MAX_FILE_SIZE
+        )));
+    }
+
+    let uploader = app.config.uploader();
+    uploader
+        .download_crate_file(app.http_client(), crate_name, version, path)
+        .map_err(|e| internal(&format_args!("failed to download file: {e}")))
+}
+
+/// Extracts all regular files from a `.crate` archive.
+///
+/// The `$name-$vers/` prefix is removed from the paths. Entries that are not
+/// regular files, or whose path would escape the package root, are skipped.
+pub fn extract(
+    tarball: &[u8],
+    pkg_name: &str,
+    max_unpack: u64,
+) -> anyhow::Result<Vec<(CrateFile, Vec<u8>)>> {
+    let decoder = LimitErrorReader::new(GzDecoder::new(tarball), max_unpack);
+    let mut archive = tar::Archive::new(decoder);
+
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let entry_path = entry.path()?.into_owned();
+        let path = match entry_path.strip_prefix(pkg_name) {
+            Ok(path) if is_normal(path) => path,
+            _ => continue,
+        };
+        let path = match path.to_str() {
+            Some(path) => path.replace('\\', "/"),
+            None => continue,
+        };
+
+        let mode = entry.header().mode()?;
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+
+        files.push((
+            CrateFile {
+                path,
+                size: content.len() as u64,
+                mode,
+                checksum: Some(hex::encode(Sha256::digest(&content))),
+            },
+            content,
+        ));
+    }
+
+    files.sort_by(|(a, _), (b, _)| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn is_normal(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Returns the content type used to serve a file.
+///
+/// Files are never served as HTML, to prevent them from being rendered by
+/// browsers.
+pub fn content_type(content: &[u8]) -> &'static str {
+    if std::str::from_utf8(content).is_ok() {
+        "text/plain; charset=utf-8"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::render_readmes::tests::add_file;
+    use flate2::read::GzEncoder;
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut pkg = tar::Builder::new(vec![]);
+        for (path, content) in files {
+            add_file(&mut pkg, path, content);
+        }
+        let mut serialized_archive = vec![];
+        GzEncoder::new(pkg.into_inner().unwrap().as_slice(), Default::default())
+            .read_to_end(&mut serialized_archive)
+            .unwrap();
+        serialized_archive
+    }
+
+    #[test]
+    fn extract_strips_prefix_and_sorts() {
+        let tarball = archive(&[
+            ("foo-0.0.1/src/lib.rs", b"pub fn foo() {}"),
+            ("foo-0.0.1/Cargo.toml", b"[package]"),
+            ("bar-0.0.1/Cargo.toml", b"[package]"),
+        ]);
+
+        let files = extract(&tarball, "foo-0.0.1", 512 * 1024 * 1024).unwrap();
+        let paths = files
+            .iter()
+            .map(|(file, _)| file.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["Cargo.toml", "src/lib.rs"]);
+        assert_eq!(files[1].0.size, 15);
+        assert_eq!(
+            files[1].0.checksum.as_deref(),
+            Some("68af18a64d8c9b31664ad36347fc31ffe1ace9483091105fa4f5f77b374f9771")
+        );
+        assert_eq!(files[1].1, b"pub fn foo() {}");
+    }
+
+    #[test]
+    fn extract_respects_unpack_limit() {
+        let tarball = archive(&[("foo-0.0.1/big.txt", &[b'a'; 4096])]);
+        assert_err!(extract(&tarball, "foo-0.0.1", 1024));
+    }
+
+    #[test]
+    fn content_types() {
+        assert_eq!(content_type(b"fn main() {}"), "text/plain; charset=utf-8");
+        assert_eq!(
+            content_type(&[0xff, 0xfe, 0x00]),
+            "application/octet-stream"
+        );
+    }
+}
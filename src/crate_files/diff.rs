@@ -0,0 +1,342 @@
+//! Line based diffs between the files of two versions of a crate.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+
+use similar::{ChangeTag, TextDiff};
+
+use super::CrateFile;
+use crate::util::errors::{bad_request, internal, AppResult};
+use crate::App;
+
+/// Upper bound for the time spent diffing a single file. Once it is exceeded
+/// the diff is still correct, but not necessarily minimal.
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Upper bound for the time spent on comparing two versions, including the
+/// download of the changed files.
+const MAX_DIFF_DURATION: Duration = Duration::from_secs(5);
+
+/// The maximum number of changed files that are compared.
+const MAX_CHANGED_FILES: usize = 300;
+
+/// The maximum total size of the changed files that are compared.
+const MAX_CHANGED_SIZE: u64 = 20 * 1024 * 1024;
+
+const CONTEXT_RADIUS: usize = 3;
+
+/// The changed files of the old and the new version, with their content.
+pub type ChangedFiles = (Vec<(CrateFile, Vec<u8>)>, Vec<(CrateFile, Vec<u8>)>);
+
+/// Downloads the files that differ between two versions from the storage
+/// cache, given the cached listings of both versions.
+///
+/// If `path` is given, only that file is considered. Files with the same
+/// checksum in both versions are skipped.
+pub fn load_changed_files<'a>(
+    app: &App,
+    crate_name: &str,
+    (old_version, old): (&str, &'a [CrateFile]),
+    (new_version, new): (&str, &'a [CrateFile]),
+    path: Option<&str>,
+) -> AppResult<ChangedFiles> {
+    let deadline = Instant::now() + MAX_DIFF_DURATION;
+
+    let selected = |files: &'a [CrateFile]| {
+        files
+            .iter()
+            .filter(|file| path.map_or(true, |path| file.path == path))
+            .collect::<Vec<_>>()
+    };
+    let old = selected(old);
+    let new = selected(new);
+
+    let old_checksums = checksums(&old);
+    let new_checksums = checksums(&new);
+
+    let changed = |files: &[&'a CrateFile], others: &HashMap<&str, Option<&str>>| {
+        files
+            .iter()
+            .copied()
+            .filter(|file| match others.get(file.path.as_str()) {
+                Some(checksum) => file.checksum.is_none() || file.checksum.as_deref() != *checksum,
+                None => true,
+            })
+            .collect::<Vec<_>>()
+    };
+    let old_changed = changed(&old, &new_checksums);
+    let new_changed = changed(&new, &old_checksums);
+
+    let paths = old_changed
+        .iter()
+        .chain(&new_changed)
+        .map(|file| file.path.as_str())
+        .collect::<BTreeSet<_>>();
+    if paths.len() > MAX_CHANGED_FILES {
+        return Err(bad_request(&format_args!(
+            "the versions differ in more than {MAX_CHANGED_FILES} files and can't be compared"
+        )));
+    }
+    let size = old_changed
+        .iter()
+        .chain(&new_changed)
+        .map(|file| file.size)
+        .sum::<u64>();
+    if size > MAX_CHANGED_SIZE {
+        return Err(bad_request(&format_args!(
+            "the changed files are larger than {MAX_CHANGED_SIZE} bytes and can't be compared"
+        )));
+    }
+
+    let download = |version: &str, files: Vec<&CrateFile>| {
+        files
+            .into_iter()
+            .map(|file| {
+                if Instant::now() > deadline {
+                    return Err(bad_request("comparing the versions took too long"));
+                }
+                let content = app
+                    .config
+                    .uploader()
+                    .download_crate_file(app.http_client(), crate_name, version, &file.path)
+                    .map_err(|e| internal(&format_args!("failed to download file: {e}")))?
+                    .ok_or_else(|| {
+                        internal(&format_args!(
+                            "cached file `{}` of {crate_name} {version} is missing",
+                            file.path
+                        ))
+                    })?;
+                Ok((file.clone(), content))
+            })
+            .collect::<AppResult<Vec<_>>>()
+    };
+
+    Ok((
+        download(old_version, old_changed)?,
+        download(new_version, new_changed)?,
+    ))
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Summary of the changes to a single file.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: String,
+    pub status: FileStatus,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Binary files are not diffed, so their line counts are always zero.
+    pub binary: bool,
+}
+
+/// The files of both versions, indexed by path.
+pub struct VersionDiff<'a> {
+    old: BTreeMap<&'a str, &'a [u8]>,
+    new: BTreeMap<&'a str, &'a [u8]>,
+    deadline: Instant,
+}
+
+impl<'a> VersionDiff<'a> {
+    pub fn new(old: &'a [(CrateFile, Vec<u8>)], new: &'a [(CrateFile, Vec<u8>)]) -> Self {
+        let index = |files: &'a [(CrateFile, Vec<u8>)]| {
+            files
+                .iter()
+                .map(|(file, content)| (file.path.as_str(), content.as_slice()))
+                .collect()
+        };
+        Self {
+            old: index(old),
+            new: index(new),
+            deadline: Instant::now() + MAX_DIFF_DURATION,
+        }
+    }
+
+    /// Returns a summary of all added, removed and modified files, sorted by
+    /// path. Unchanged files are not included.
+    pub fn summary(&self) -> Vec<FileDiff> {
+        let paths = self
+            .old
+            .keys()
+            .chain(self.new.keys())
+            .collect::<BTreeSet<_>>();
+        paths
+            .into_iter()
+            .filter_map(|path| self.file_summary(path))
+            .collect()
+    }
+
+    /// Returns the unified diff of a single file, or `None` if neither version
+    /// contains the file. The diff is empty if the file is unchanged.
+    pub fn patch(&self, path: &str) -> Option<String> {
+        let old = self.old.get(path).copied();
+        let new = self.new.get(path).copied();
+        if old.is_none() && new.is_none() {
+            return None;
+        }
+
+        let old_header = old.map_or_else(|| "/dev/null".to_string(), |_| format!("a/{path}"));
+        let new_header = new.map_or_else(|| "/dev/null".to_string(), |_| format!("b/{path}"));
+
+        let (old_text, new_text) = match texts(old, new) {
+            Some(texts) => texts,
+            None if old == new => return Some(String::new()),
+            None => {
+                return Some(format!(
+                    "Binary files {old_header} and {new_header} differ\n"
+                ))
+            }
+        };
+
+        let diff = diff(old_text, new_text, self.deadline);
+        let patch = diff
+            .unified_diff()
+            .context_radius(CONTEXT_RADIUS)
+            .header(&old_header, &new_header)
+            .to_string();
+        Some(patch)
+    }
+
+    fn file_summary(&self, path: &str) -> Option<FileDiff> {
+        let old = self.old.get(path).copied();
+        let new = self.new.get(path).copied();
+
+        let status = match (old, new) {
+            (None, Some(_)) => FileStatus::Added,
+            (Some(_), None) => FileStatus::Removed,
+            (Some(old), Some(new)) if old != new => FileStatus::Modified,
+            _ => return None,
+        };
+
+        let (additions, deletions, binary) = match texts(old, new) {
+            Some((old_text, new_text)) => {
+                let (additions, deletions) = count_changes(old_text, new_text, self.deadline);
+                (additions, deletions, false)
+            }
+            None => (0, 0, true),
+        };
+
+        Some(FileDiff {
+            path: path.to_string(),
+            status,
+            additions,
+            deletions,
+            binary,
+        })
+    }
+}
+
+fn checksums<'a>(files: &[&'a CrateFile]) -> HashMap<&'a str, Option<&'a str>> {
+    files
+        .iter()
+        .map(|file| (file.path.as_str(), file.checksum.as_deref()))
+        .collect()
+}
+
+/// Returns both sides as text, treating missing files as empty. Returns `None`
+/// if either side is not valid UTF-8.
+fn texts<'a>(old: Option<&'a [u8]>, new: Option<&'a [u8]>) -> Option<(&'a str, &'a str)> {
+    let old = std::str::from_utf8(old.unwrap_or_default()).ok()?;
+    let new = std::str::from_utf8(new.unwrap_or_default()).ok()?;
+    Some((old, new))
+}
+
+/// Diffs the lines of both texts. The time spent is bounded by `DIFF_TIMEOUT`,
+/// and by the `deadline` of the whole comparison.
+fn diff<'a>(old: &'a str, new: &'a str, deadline: Instant) -> TextDiff<'a, 'a, 'a, str> {
+    TextDiff::configure()
+        .deadline(deadline.min(Instant::now() + DIFF_TIMEOUT))
+        .diff_lines(old, new)
+}
+
+fn count_changes(old: &str, new: &str, deadline: Instant) -> (usize, usize) {
+    diff(old, new, deadline)
+        .iter_all_changes()
+        .fold((0, 0), |(additions, deletions), change| {
+            match change.tag() {
+                ChangeTag::Insert => (additions + 1, deletions),
+                ChangeTag::Delete => (additions, deletions + 1),
+                ChangeTag::Equal => (additions, deletions),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &[u8]) -> (CrateFile, Vec<u8>) {
+        let file = CrateFile {
+            path: path.to_string(),
+            size: content.len() as u64,
+            mode: 0o644,
+            checksum: None,
+        };
+        (file, content.to_vec())
+    }
+
+    #[test]
+    fn summary() {
+        let old = vec![
+            file("Cargo.toml", b"[package]\nversion = \"1.0.0\"\n"),
+            file("README.md", b"# foo\n"),
+            file("src/lib.rs", b"pub fn foo() {}\n"),
+            file("logo.png", &[0x89, 0x50, 0xff]),
+        ];
+        let new = vec![
+            file("Cargo.toml", b"[package]\nversion = \"1.1.0\"\n"),
+            file("src/lib.rs", b"pub fn foo() {}\n"),
+            file("src/bar.rs", b"pub fn bar() {}\npub fn baz() {}\n"),
+            file("logo.png", &[0x89, 0x50, 0xfe]),
+        ];
+
+        let summary = VersionDiff::new(&old, &new).summary();
+        let summary = summary
+            .iter()
+            .map(|f| {
+                (
+                    f.path.as_str(),
+                    f.status,
+                    f.additions,
+                    f.deletions,
+                    f.binary,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("Cargo.toml", FileStatus::Modified, 1, 1, false),
+                ("README.md", FileStatus::Removed, 0, 1, false),
+                ("logo.png", FileStatus::Modified, 0, 0, true),
+                ("src/bar.rs", FileStatus::Added, 2, 0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn patch() {
+        let old = vec![file("src/lib.rs", b"fn a() {}\nfn b() {}\n")];
+        let new = vec![
+            file("src/lib.rs", b"fn a() {}\nfn c() {}\n"),
+            file("new.txt", b"hello\n"),
+        ];
+        let diff = VersionDiff::new(&old, &new);
+
+        assert_eq!(
+            diff.patch("src/lib.rs").unwrap(),
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n"
+        );
+        assert_eq!(
+            diff.patch("new.txt").unwrap(),
+            "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+hello\n"
+        );
+        assert_none!(diff.patch("missing.rs"));
+    }
+}
@@ -1,8 +1,14 @@
+mod journal;
+pub mod user_agent;
+
 use crate::App;
 use anyhow::Error;
 use dashmap::{DashMap, SharedValue};
 use diesel::{pg::upsert::excluded, prelude::*};
+use journal::Journal;
 use std::collections::HashSet;
+use std::io;
+use std::path::Path;
 use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 
 /// crates.io receives a lot of download requests, and we can't execute a write query to the
@@ -22,34 +28,95 @@ use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 /// persisted, so it's possible to lose some of them if the process exits ungracefully. While
 /// that's far from ideal, the advantage of batching database updates far outweights potentially
 /// losing some download counts.
+///
+/// To avoid that, the counter can optionally record every download in a local append-only journal
+/// (see `DownloadsCounter::with_journal`). The journal is replayed when the counter is created,
+/// so downloads that were not persisted before the process exited, or whose persisting failed,
+/// are counted on the next start instead.
+///
+/// Downloads are also counted per class of `User-Agent` (see `user_agent::classify`), and that
+/// breakdown is persisted in the `version_downloads_by_agent` table along with the totals. The
+/// breakdown is not recorded in the journal, so it can be less accurate than the totals.
 #[derive(Debug)]
 pub struct DownloadsCounter {
     /// Inner storage for the download counts.
     inner: DashMap<i32, AtomicUsize>,
+    /// Download counts per version and class of `User-Agent`. Each shard is persisted along with
+    /// the shard of `inner` with the same index.
+    agents: DashMap<(i32, &'static str), AtomicUsize>,
     /// Index of the next shard that should be persisted by `persist_next_shard`.
     shard_idx: AtomicUsize,
     /// Number of downloads that are not yet persisted on the database. This is just used as a
     /// metric included in log lines, and it's not guaranteed to be accurate.
     pending_count: AtomicI64,
+    /// Journal of the downloads that are not yet persisted on the database, if enabled.
+    journal: Option<Journal>,
 }
 
 impl DownloadsCounter {
     pub(crate) fn new() -> Self {
         Self {
             inner: DashMap::new(),
+            agents: DashMap::new(),
             shard_idx: AtomicUsize::new(0),
             pending_count: AtomicI64::new(0),
+            journal: None,
         }
     }
 
+    /// Creates a counter backed by the journal at `path`, restoring the downloads that were
+    /// recorded in it but not persisted on the database yet.
+    pub(crate) fn with_journal(path: &Path) -> io::Result<Self> {
+        let (journal, counts) = Journal::open(path)?;
+
+        let counter = Self {
+            journal: Some(journal),
+            ..Self::new()
+        };
+        let pending = counts.values().sum::<usize>();
+        counter.restore(counts);
+        counter
+            .pending_count
+            .store(pending as i64, Ordering::SeqCst);
+        Ok(counter)
+    }
+
     pub(crate) fn increment(&self, version_id: i32) {
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.record_download(version_id) {
+                println!("downloads_counter journal error: {err}");
+            }
+        }
+
         self.pending_count.fetch_add(1, Ordering::SeqCst);
+        self.add(version_id, 1);
+    }
+
+    /// Counts a download like `increment`, also counting it in the breakdown by `User-Agent`.
+    pub(crate) fn increment_with_user_agent(&self, version_id: i32, user_agent: &str) {
+        self.increment(version_id);
 
+        let key = (version_id, user_agent::classify(user_agent));
+        if let Some(counter) = self.agents.get(&key) {
+            counter.value().fetch_add(1, Ordering::SeqCst);
+        } else {
+            self.agents
+                .entry(key)
+                .and_modify(|counter| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .or_insert_with(|| AtomicUsize::new(1));
+        }
+    }
+
+    /// Adds downloads to the in-memory counts, without recording them in the journal or in the
+    /// pending count.
+    fn add(&self, version_id: i32, count: usize) {
         if let Some(counter) = self.inner.get(&version_id) {
             // The version is already recorded in the DashMap, so we don't need to lock the whole
             // shard in write mode. The shard is instead locked in read mode, which allows an
             // unbounded number of readers as long as there are no write locks.
-            counter.value().fetch_add(1, Ordering::SeqCst);
+            counter.value().fetch_add(count, Ordering::SeqCst);
         } else {
             // The version is not in the DashMap, so we need to lock the whole shard in write mode
             // and insert the version into it. This has worse performance than the above case.
@@ -58,9 +125,17 @@ impl DownloadsCounter {
                 .and_modify(|counter| {
                     // Handle the version being inserted by another thread while we were waiting
                     // for the write lock on the shard.
-                    counter.fetch_add(1, Ordering::SeqCst);
+                    counter.fetch_add(count, Ordering::SeqCst);
                 })
-                .or_insert_with(|| AtomicUsize::new(1));
+                .or_insert_with(|| AtomicUsize::new(count));
+        }
+    }
+
+    /// Puts back downloads that are recorded in the journal but not persisted yet, either when
+    /// replaying the journal or after persisting them failed.
+    fn restore(&self, counts: impl IntoIterator<Item = (i32, usize)>) {
+        for (version_id, count) in counts {
+            self.add(version_id, count);
         }
     }
 
@@ -80,6 +155,14 @@ impl DownloadsCounter {
             let shard = std::mem::take(&mut *shard.write());
             stats = stats.merge(self.persist_shard(conn, shard.iter())?);
         }
+        for shard in self.agents.shards() {
+            let shard = std::mem::take(&mut *shard.write());
+            self.persist_agents_shard(conn, shard.iter())?;
+        }
+
+        // Every download recorded before this point has been persisted, so the journal only
+        // contains downloads counted concurrently and can be truncated.
+        self.compact_journal();
 
         Ok(stats)
     }
@@ -94,13 +177,59 @@ impl DownloadsCounter {
 
         let mut stats = self.persist_shard(conn, shard.iter())?;
         stats.shard = Some(idx);
+
+        let agent_shards = self.agents.shards();
+        let agent_shard = std::mem::take(&mut *agent_shards[idx % agent_shards.len()].write());
+        self.persist_agents_shard(conn, agent_shard.iter())?;
+
+        // Compact the journal once per rotation through all the shards, to keep it from growing
+        // indefinitely.
+        if idx == shards.len() - 1 {
+            self.compact_journal();
+        }
+
         Ok(stats)
     }
 
+    fn compact_journal(&self) {
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.compact() {
+                println!("downloads_counter journal error: {err}");
+            }
+        }
+    }
+
     fn persist_shard(
         &self,
         conn: &PgConnection,
         shard: hashbrown::hash_map::Iter<'_, i32, SharedValue<AtomicUsize>>,
+    ) -> Result<PersistStats, Error> {
+        let to_insert = shard
+            .map(|(id, atomic)| (*id, atomic.get().load(Ordering::SeqCst)))
+            .collect::<Vec<_>>();
+
+        match self.insert_downloads(conn, to_insert.clone()) {
+            Ok(stats) => {
+                if let Some(journal) = &self.journal {
+                    if let Err(err) = journal.record_persisted(&to_insert) {
+                        println!("downloads_counter journal error: {err}");
+                    }
+                }
+                Ok(stats)
+            }
+            Err(err) => {
+                // The shard was already removed from the DashMap, so without putting the
+                // downloads back they would be lost until the journal is replayed.
+                self.restore(to_insert);
+                Err(err)
+            }
+        }
+    }
+
+    fn insert_downloads(
+        &self,
+        conn: &PgConnection,
+        mut to_insert: Vec<(i32, usize)>,
     ) -> Result<PersistStats, Error> {
         use crate::schema::{version_downloads, versions};
 
@@ -108,10 +237,6 @@ impl DownloadsCounter {
         let mut counted_downloads = 0;
         let mut counted_versions = 0;
 
-        let mut to_insert = shard
-            .map(|(id, atomic)| (*id, atomic.get().load(Ordering::SeqCst)))
-            .collect::<Vec<_>>();
-
         if !to_insert.is_empty() {
             // The rows we're about to insert need to be sorted to avoid deadlocks when multiple
             // instances of crates.io are running at the same time.
@@ -195,6 +320,74 @@ impl DownloadsCounter {
         })
     }
 
+    fn persist_agents_shard(
+        &self,
+        conn: &PgConnection,
+        shard: hashbrown::hash_map::Iter<'_, (i32, &'static str), SharedValue<AtomicUsize>>,
+    ) -> Result<(), Error> {
+        use crate::schema::{version_downloads_by_agent, versions};
+
+        let mut to_insert = shard
+            .map(|(key, atomic)| (*key, atomic.get().load(Ordering::SeqCst)))
+            .collect::<Vec<_>>();
+        if to_insert.is_empty() {
+            return Ok(());
+        }
+
+        // Sorted and filtered for the same reasons as the rows in `insert_downloads`.
+        to_insert.sort();
+
+        let result = (|| {
+            let version_ids = to_insert.iter().map(|((id, _), _)| *id).collect::<Vec<_>>();
+            let existing_version_ids: HashSet<i32> = versions::table
+                .select(versions::id)
+                .for_share()
+                .filter(versions::id.eq_any(version_ids))
+                .load(conn)?
+                .into_iter()
+                .collect();
+
+            let values = to_insert
+                .iter()
+                .filter(|((id, _), _)| existing_version_ids.contains(id))
+                .map(|((id, class), count)| {
+                    (
+                        version_downloads_by_agent::version_id.eq(*id),
+                        version_downloads_by_agent::agent_class.eq(*class),
+                        version_downloads_by_agent::downloads.eq(*count as i32),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(version_downloads_by_agent::table)
+                .values(&values)
+                .on_conflict((
+                    version_downloads_by_agent::version_id,
+                    version_downloads_by_agent::date,
+                    version_downloads_by_agent::agent_class,
+                ))
+                .do_update()
+                .set(
+                    version_downloads_by_agent::downloads.eq(version_downloads_by_agent::downloads
+                        + excluded(version_downloads_by_agent::downloads)),
+                )
+                .execute(conn)
+        })();
+
+        if let Err(err) = result {
+            // Put the counts back, so that they're retried with the next persist.
+            for (key, count) in to_insert {
+                self.agents
+                    .entry(key)
+                    .or_insert_with(|| AtomicUsize::new(0))
+                    .fetch_add(count, Ordering::SeqCst);
+            }
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
     pub fn shards_count(&self) -> usize {
         self.inner.shards().len()
     }
@@ -365,6 +558,42 @@ mod tests {
         state.assert_downloads_count(&conn, v2, 5);
     }
 
+    #[test]
+    fn test_journal_replayed_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("downloads.journal");
+        let conn = crate::db::test_conn();
+        let mut state = State::new(&conn);
+
+        let v1 = state.new_version(&conn);
+        let v2 = state.new_version(&conn);
+
+        let counter = DownloadsCounter::with_journal(&path).unwrap();
+        for _ in 0..3 {
+            counter.increment(v1);
+        }
+        counter.increment(v2);
+
+        // Simulate the process being killed before the downloads are persisted, without running
+        // any destructors.
+        std::mem::forget(counter);
+
+        let counter = DownloadsCounter::with_journal(&path).unwrap();
+        assert_eq!(4, counter.pending_count());
+
+        let stats = counter
+            .persist_all_shards_with_conn(&conn)
+            .expect("failed to persist all shards");
+        assert_eq!(stats.counted_downloads, 4);
+        state.assert_downloads_count(&conn, v1, 3);
+        state.assert_downloads_count(&conn, v2, 1);
+
+        // Persisted downloads are removed from the journal, and not counted again.
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        let counter = DownloadsCounter::with_journal(&path).unwrap();
+        assert_eq!(0, counter.pending_count());
+    }
+
     #[test]
     fn test_increment_existing_and_missing_version_same_shard() {
         test_increment_existing_and_missing_version(|map, v1, v2| {
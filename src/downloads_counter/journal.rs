@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Append-only journal of the download counts that are not yet persisted in the database.
+///
+/// Every download appends a `{version_id} 1` line, and every successfully persisted batch of
+/// downloads appends `{version_id} -{count}` lines. Summing up the lines of each version thus
+/// yields the downloads that still need to be persisted, which is what gets replayed when the
+/// process starts again after a crash or a failed flush.
+///
+/// Every line is written to the file before the download is acknowledged, so downloads survive
+/// the process being killed. Lines are not synced to disk, so a crash of the whole machine can
+/// still lose the most recent ones.
+#[derive(Debug)]
+pub(super) struct Journal {
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if missing, and returns the download counts it
+    /// contains. The journal is compacted right away, which also drops any partially written line
+    /// so that new lines can't be appended to it.
+    pub(super) fn open(path: &Path) -> io::Result<(Self, HashMap<i32, usize>)> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let counts = read_counts(&mut file)?;
+        rewrite(&mut file, &counts)?;
+        let journal = Self {
+            file: Mutex::new(file),
+        };
+        Ok((journal, counts))
+    }
+
+    /// Records a download. The line is written with a single `write` call, so that it can't be
+    /// interleaved with other lines.
+    pub(super) fn record_download(&self, version_id: i32) -> io::Result<()> {
+        let line = format!("{version_id} 1\n");
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+
+    /// Records a persisted batch of downloads.
+    pub(super) fn record_persisted(&self, counts: &[(i32, usize)]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let mut writer = BufWriter::new(&mut *file);
+        for (id, count) in counts {
+            writeln!(writer, "{id} -{count}")?;
+        }
+        writer.flush()
+    }
+
+    /// Rewrites the journal with a single line per version that still has downloads to persist.
+    /// Once every download is persisted this truncates the journal.
+    pub(super) fn compact(&self) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let counts = read_counts(&mut file)?;
+        rewrite(&mut file, &counts)
+    }
+}
+
+fn rewrite(file: &mut File, counts: &HashMap<i32, usize>) -> io::Result<()> {
+    let mut counts = counts
+        .iter()
+        .map(|(id, count)| (*id, *count as i64))
+        .collect::<Vec<_>>();
+    counts.sort_unstable();
+
+    file.set_len(0)?;
+    let mut writer = BufWriter::new(file);
+    for (id, count) in counts {
+        writeln!(writer, "{id} {count}")?;
+    }
+    writer.flush()
+}
+
+/// Sums up the entries of the journal. Malformed lines, like the last line of a journal that was
+/// being written to when the process was killed, are ignored.
+fn read_counts(file: &mut File) -> io::Result<HashMap<i32, usize>> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut content)?;
+
+    let mut counts: HashMap<i32, i64> = HashMap::new();
+    for line in content.lines() {
+        let entry = line
+            .split_once(' ')
+            .and_then(|(id, count)| Some((id.parse::<i32>().ok()?, count.parse::<i64>().ok()?)));
+        if let Some((id, count)) = entry {
+            *counts.entry(id).or_default() += count;
+        }
+    }
+
+    Ok(counts
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(id, count)| (id, count as usize))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("downloads.journal");
+
+        let (journal, counts) = Journal::open(&path).unwrap();
+        assert!(counts.is_empty());
+
+        for _ in 0..3 {
+            journal.record_download(1).unwrap();
+        }
+        journal.record_download(2).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1 1\n1 1\n1 1\n2 1\n"
+        );
+        journal.record_persisted(&[(1, 2)]).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "1 1\n1 1\n1 1\n2 1\n1 -2\n"
+        );
+        drop(journal);
+
+        // A partially written line is ignored.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"3 ")
+            .unwrap();
+
+        let (journal, counts) = Journal::open(&path).unwrap();
+        assert_eq!(counts, HashMap::from([(1, 1), (2, 1)]));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1 1\n2 1\n");
+
+        journal.record_persisted(&[(1, 1), (2, 1)]).unwrap();
+        journal.compact().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    }
+}
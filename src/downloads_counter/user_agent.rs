@@ -0,0 +1,119 @@
+//! Classification of the `User-Agent` of download requests, to break down download counts by
+//! the kind of client.
+
+use lazy_static::lazy_static;
+
+/// Class of requests without a `User-Agent`, or with one we don't recognize.
+pub const UNKNOWN: &str = "unknown";
+
+/// Substrings identifying crawlers and other bots, matched case-insensitively. These are checked
+/// before the other tools, as most bots also pretend to be a browser.
+const BOTS: &[&str] = &["bot", "crawler", "spider", "slurp"];
+
+/// Prefixes of the `User-Agent` of other known tools, and the class they're counted as.
+const TOOLS: &[(&str, &str)] = &[
+    ("curl/", "curl"),
+    ("Wget/", "wget"),
+    ("python-requests/", "python"),
+    ("Python-urllib/", "python"),
+    ("Go-http-client/", "go"),
+    ("Java/", "java"),
+    ("node-fetch", "node"),
+    ("axios/", "node"),
+    ("reqwest/", "reqwest"),
+    ("cargo-binstall", "cargo-binstall"),
+    ("Mozilla/", "browser"),
+];
+
+/// Highest minor version of cargo 1.x that gets its own class. Newer versions are counted as
+/// `cargo/unknown` until this is bumped, which also keeps clients sending made-up versions from
+/// creating an unbounded number of classes.
+const MAX_CARGO_MINOR: u64 = 200;
+
+lazy_static! {
+    /// The `cargo/1.{minor}` classes, built once so that classifying a download doesn't allocate.
+    static ref CARGO_CLASSES: Vec<String> = (0..=MAX_CARGO_MINOR)
+        .map(|minor| format!("cargo/1.{minor}"))
+        .collect();
+}
+
+/// Returns the class of a `User-Agent`:
+///
+/// - `cargo/1.{minor}` for cargo, e.g. `cargo/1.62` for `cargo 1.62.0 (a748cf5a3 2022-06-08)`.
+///   Nightly and beta versions are counted as their upcoming release.
+/// - `bot` for crawlers.
+/// - the name of the tool for other known tools, e.g. `curl`.
+/// - `unknown` for everything else.
+pub fn classify(user_agent: &str) -> &'static str {
+    if let Some(version) = user_agent.strip_prefix("cargo ") {
+        return cargo_minor_version(version)
+            .and_then(|minor| CARGO_CLASSES.get(minor as usize))
+            .map(String::as_str)
+            .unwrap_or("cargo/unknown");
+    }
+
+    if BOTS
+        .iter()
+        .any(|bot| contains_ignore_ascii_case(user_agent, bot))
+    {
+        return "bot";
+    }
+
+    TOOLS
+        .iter()
+        .find(|(prefix, _)| user_agent.starts_with(prefix))
+        .map(|(_, class)| *class)
+        .unwrap_or(UNKNOWN)
+}
+
+/// Extracts the minor version of cargo 1.x from the version in cargo's `User-Agent`.
+fn cargo_minor_version(version: &str) -> Option<u64> {
+    let version = version.split(' ').next()?;
+    let mut parts = version.splitn(3, '.');
+    if parts.next()? != "1" {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
+
+/// Checks whether `haystack` contains `needle`, which must be lowercase, ignoring ASCII case.
+fn contains_ignore_ascii_case(haystack: &str, needle: &str) -> bool {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_user_agents() {
+        let cases = [
+            ("cargo 1.62.0 (a748cf5a3 2022-06-08)", "cargo/1.62"),
+            ("cargo 1.65.0-nightly (4ed54cecc 2022-08-27)", "cargo/1.65"),
+            ("cargo 1.9.0", "cargo/1.9"),
+            ("cargo something", "cargo/unknown"),
+            ("cargo 2.0.0", "cargo/unknown"),
+            ("cargo 1.99999.0", "cargo/unknown"),
+            ("curl/7.79.1", "curl"),
+            ("python-requests/2.28.1", "python"),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64; rv:103.0) Gecko/20100101 Firefox/103.0",
+                "browser",
+            ),
+            (
+                "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+                "bot",
+            ),
+            ("Some-Crawler/1.0", "bot"),
+            ("", "unknown"),
+            ("my-own-tool/1.0", "unknown"),
+        ];
+
+        for (user_agent, expected) in cases {
+            assert_eq!(classify(user_agent), expected, "{user_agent}");
+        }
+    }
+}
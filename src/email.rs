@@ -5,6 +5,7 @@ use crate::util::errors::{server_error, AppResult};
 
 use crate::config;
 use crate::middleware::log_request::add_custom_metadata;
+use crate::models::VersionAction;
 use crate::Env;
 use lettre::transport::file::FileTransport;
 use lettre::transport::smtp::authentication::{Credentials, Mechanism};
@@ -91,6 +92,95 @@ or go to https://{domain}/me/pending-invites to manage all of your crate ownersh
         self.send(email, subject, &body)
     }
 
+    /// Attempts to send an ownership invitation to an email address that doesn't belong to an
+    /// account yet.
+    pub fn send_owner_email_invite(
+        &self,
+        email: &str,
+        user_name: &str,
+        crate_name: &str,
+    ) -> AppResult<()> {
+        let subject = "Crate ownership invitation";
+        let body = format!(
+            "{user_name} has invited you to become an owner of the crate {crate_name}!\n
+Log in to https://{domain}/ and add and verify this email address in your account settings,
+then go to https://{domain}/me/pending-invites to accept this invitation.",
+            domain = crate::config::domain_name()
+        );
+
+        self.send(email, subject, &body)
+    }
+
+    /// Attempts to send an invitation to take over the ownership of a crate.
+    pub fn send_ownership_transfer(
+        &self,
+        email: &str,
+        user_name: &str,
+        crate_name: &str,
+        previous_owner: &str,
+        token: &str,
+    ) -> AppResult<()> {
+        let subject = "Crate ownership transfer";
+        let body = format!(
+            "{user_name} would like you to take over the ownership of the crate {crate_name} from {previous_owner}!\n
+Visit https://{domain}/accept-invite/{token} to accept this transfer,
+or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
+            domain = crate::config::domain_name()
+        );
+
+        self.send(email, subject, &body)
+    }
+
+    /// Attempts to notify a crate owner about a version being published,
+    /// yanked or unyanked.
+    pub fn send_version_notification(
+        &self,
+        email: &str,
+        user_name: &str,
+        notification: &VersionNotification,
+    ) -> AppResult<()> {
+        let VersionNotification {
+            crate_name,
+            version,
+            action,
+            user,
+            token_name,
+            user_agent,
+        } = notification;
+
+        let (subject_action, body_action) = match action {
+            VersionAction::Publish => ("published", "A new version of"),
+            VersionAction::Yank => ("yanked", "A version of"),
+            VersionAction::Unyank => ("unyanked", "A version of"),
+        };
+        let token = match token_name {
+            Some(name) => format!("the API token \"{name}\""),
+            None => "a browser session".to_string(),
+        };
+        let user_agent = if user_agent.is_empty() {
+            "unknown"
+        } else {
+            user_agent.as_str()
+        };
+
+        let subject = format!("Crate {crate_name} {version} was {subject_action}");
+        let body = format!(
+            "Hello {user_name}!\n
+{body_action} the crate {crate_name} was {subject_action}:\n
+Version: {version}
+By user: {user}
+Using: {token}
+User agent: {user_agent}\n
+If you did not expect this, please revoke any API tokens that may have been compromised
+at https://{domain}/me and contact help@crates.io.\n
+You are receiving this email because you have notifications enabled for {crate_name}.
+Visit https://{domain}/me to change your notification settings.",
+            domain = crate::config::domain_name()
+        );
+
+        self.send(email, &subject, &body)
+    }
+
     /// This is supposed to be used only during tests, to retrieve the messages stored in the
     /// "memory" backend. It's not cfg'd away because our integration tests need to access this.
     pub fn mails_in_memory(&self) -> Option<Vec<StoredEmail>> {
@@ -211,6 +301,20 @@ impl std::fmt::Debug for EmailBackend {
     }
 }
 
+/// Details about a change to a version of a crate, which are sent to the
+/// owners of the crate that have email notifications enabled.
+#[derive(Debug)]
+pub struct VersionNotification {
+    pub crate_name: String,
+    pub version: String,
+    pub action: VersionAction,
+    /// Login of the user that made the change.
+    pub user: String,
+    /// Name of the API token that was used, if any.
+    pub token_name: Option<String>,
+    pub user_agent: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct StoredEmail {
     pub to: String,
@@ -239,4 +343,26 @@ mod tests {
 
         assert_ok!(emails.send("someone@example.com", "test", "test"));
     }
+
+    #[test]
+    fn version_notification_mentions_token_and_user_agent() {
+        let emails = Emails::new_in_memory();
+        let notification = VersionNotification {
+            crate_name: "foo".into(),
+            version: "1.0.0".into(),
+            action: VersionAction::Yank,
+            user: "bar".into(),
+            token_name: Some("ci".into()),
+            user_agent: "cargo 1.62.0".into(),
+        };
+
+        assert_ok!(emails.send_version_notification("someone@example.com", "baz", &notification));
+
+        let mails = emails.mails_in_memory().unwrap();
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].subject, "Crate foo 1.0.0 was yanked");
+        assert!(mails[0].body.contains("By user: bar"));
+        assert!(mails[0].body.contains("the API token \"ci\""));
+        assert!(mails[0].body.contains("User agent: cargo 1.62.0"));
+    }
 }
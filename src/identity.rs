@@ -0,0 +1,62 @@
+//! Identity providers users can log in with, in addition to GitHub.
+//!
+//! Every provider implements the authorization code flow: `begin_login` returns the URL the user
+//! is redirected to, and the provider redirects back with a `code` that `complete_login` exchanges
+//! for the `Identity` of the user. The identities users logged in with are recorded in the
+//! `linked_identities` table, so that a user can log in with several providers.
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::util::errors::AppResult;
+
+pub mod oidc;
+
+pub use self::oidc::{OidcProvider, OidcProviderConfig};
+
+/// Name of GitHub in the `linked_identities` table. GitHub logins are handled by the
+/// `session` controller directly rather than by an `IdentityProvider`.
+pub const GITHUB_PROVIDER: &str = "github";
+
+pub trait IdentityProvider: Send + Sync {
+    /// Short name of the provider, used in the URLs of the login routes and recorded in the
+    /// `linked_identities` table, e.g. `keycloak`.
+    fn name(&self) -> &str;
+
+    /// Human readable name of the provider, e.g. `Keycloak`.
+    fn display_name(&self) -> &str;
+
+    /// Returns the URL to redirect the user to, and the state to keep in their session until
+    /// they're redirected back.
+    fn begin_login(&self) -> AppResult<(Url, LoginState)>;
+
+    /// Exchanges the `code` the user was redirected back with for their identity. The `state`
+    /// query parameter must already have been checked against `LoginState::csrf_token`.
+    fn complete_login(&self, code: &str, state: &LoginState) -> AppResult<Identity>;
+}
+
+/// Secrets of a login in progress, kept in the session of the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginState {
+    /// Name of the provider the login was started with.
+    pub provider: String,
+    /// Value of the `state` parameter the provider redirects back with.
+    pub csrf_token: String,
+    /// PKCE code verifier, if the provider uses PKCE.
+    pub pkce_verifier: Option<String>,
+    /// Nonce included in the ID token, if the provider uses OpenID Connect.
+    pub nonce: Option<String>,
+}
+
+/// The identity of a user at a provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub provider: String,
+    /// Stable identifier of the user at the provider.
+    pub subject: String,
+    pub login: String,
+    pub name: Option<String>,
+    /// Email address of the user, only if the provider verified it.
+    pub email: Option<String>,
+    pub avatar: Option<String>,
+}
@@ -0,0 +1,357 @@
+//! Generic OpenID Connect identity provider, e.g. Keycloak or GitLab.
+//!
+//! The provider is configured from its discovery document, and logs users in with the
+//! authorization code flow using PKCE. The ID token returned by the token endpoint is validated
+//! against the keys published by the provider before its claims are trusted.
+
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use oauth2::{CsrfToken, PkceCodeChallenge};
+use reqwest::blocking::Client;
+use serde::de::DeserializeOwned;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use super::{Identity, IdentityProvider, LoginState, GITHUB_PROVIDER};
+use crate::env;
+use crate::util::errors::{bad_request, server_error, AppError, AppResult};
+
+/// How long the discovery document and the keys of a provider are cached. The keys are fetched
+/// again before that if an ID token is signed with an unknown key.
+const DISCOVERY_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Clock skew tolerated when validating the expiration of ID tokens.
+const CLOCK_LEEWAY_SECONDS: u64 = 60;
+
+/// Configuration of an OpenID Connect provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub display_name: String,
+    /// URL of the issuer, which serves its discovery document at
+    /// `/.well-known/openid-configuration`.
+    pub issuer_url: String,
+    pub client_id: String,
+    /// Secret of the client, if it is a confidential client.
+    pub client_secret: Option<String>,
+    /// URL the provider redirects users back to once they're logged in. It must forward the
+    /// `code` and `state` query parameters to `/api/private/session/{name}/authorize`.
+    pub redirect_url: String,
+    /// Scopes requested in addition to `openid`.
+    pub scopes: Vec<String>,
+}
+
+impl OidcProviderConfig {
+    /// Reads the configuration of the providers listed in the comma separated `OIDC_PROVIDERS`
+    /// environment variable. Each provider is configured with the following variables, where
+    /// `{NAME}` is the uppercase name of the provider:
+    ///
+    /// - `OIDC_{NAME}_ISSUER_URL`: URL of the issuer.
+    /// - `OIDC_{NAME}_CLIENT_ID`: ID of the client registered at the provider.
+    /// - `OIDC_{NAME}_CLIENT_SECRET`: Secret of the client, if it is a confidential client.
+    /// - `OIDC_{NAME}_REDIRECT_URL`: URL the provider redirects users back to.
+    /// - `OIDC_{NAME}_DISPLAY_NAME`: Name of the provider shown to users. Defaults to the name.
+    /// - `OIDC_{NAME}_SCOPES`: Space separated scopes requested in addition to `openid`. Defaults
+    ///   to `profile email`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a required variable of a listed provider is missing.
+    pub fn all_from_environment() -> Vec<Self> {
+        dotenv::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(Self::from_environment)
+            .collect()
+    }
+
+    fn from_environment(name: &str) -> Self {
+        assert_ne!(
+            name, GITHUB_PROVIDER,
+            "OIDC_PROVIDERS can't include `github`"
+        );
+
+        let prefix = format!("OIDC_{}", name.to_uppercase().replace('-', "_"));
+        let var = |suffix: &str| format!("{prefix}_{suffix}");
+
+        let scopes = dotenv::var(var("SCOPES")).unwrap_or_else(|_| "profile email".into());
+
+        Self {
+            name: name.to_string(),
+            display_name: dotenv::var(var("DISPLAY_NAME")).unwrap_or_else(|_| name.to_string()),
+            issuer_url: env(&var("ISSUER_URL")),
+            client_id: env(&var("CLIENT_ID")),
+            client_secret: dotenv::var(var("CLIENT_SECRET")).ok(),
+            redirect_url: env(&var("REDIRECT_URL")),
+            scopes: scopes.split_whitespace().map(String::from).collect(),
+        }
+    }
+}
+
+pub struct OidcProvider {
+    config: OidcProviderConfig,
+    client: Client,
+    discovered: RwLock<Option<Discovered>>,
+}
+
+#[derive(Clone)]
+struct Discovered {
+    metadata: ProviderMetadata,
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// The fields of the discovery document we use.
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    jwks_uri: Url,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// The claims of ID tokens we use, besides the ones validated by `jsonwebtoken`.
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+    picture: Option<String>,
+}
+
+impl OidcProvider {
+    pub fn new(config: OidcProviderConfig, client: Client) -> Self {
+        Self {
+            config,
+            client,
+            discovered: RwLock::new(None),
+        }
+    }
+
+    /// Returns the discovery document and keys of the provider, fetching them if they're not
+    /// cached or if `refresh` is set.
+    fn discover(&self, refresh: bool) -> AppResult<Discovered> {
+        if !refresh {
+            if let Some(discovered) = &*self.discovered.read().unwrap() {
+                if discovered.fetched_at.elapsed() < DISCOVERY_TTL {
+                    return Ok(discovered.clone());
+                }
+            }
+        }
+
+        let issuer_url = self.config.issuer_url.trim_end_matches('/');
+        let url = format!("{issuer_url}/.well-known/openid-configuration");
+        let metadata: ProviderMetadata = self.get(&url)?;
+        if metadata.issuer != self.config.issuer_url {
+            return Err(server_error(&format!(
+                "issuer `{}` of the `{}` provider doesn't match its configuration",
+                metadata.issuer, self.config.name
+            )));
+        }
+        let keys = self.get(metadata.jwks_uri.as_str())?;
+
+        let discovered = Discovered {
+            metadata,
+            keys,
+            fetched_at: Instant::now(),
+        };
+        *self.discovered.write().unwrap() = Some(discovered.clone());
+        Ok(discovered)
+    }
+
+    fn get<T: DeserializeOwned>(&self, url: &str) -> AppResult<T> {
+        self.client
+            .get(url)
+            .send()?
+            .error_for_status()
+            .map_err(|err| err.chain(server_error("Error contacting the identity provider")))?
+            .json()
+            .map_err(Into::into)
+    }
+
+    fn exchange_code(
+        &self,
+        discovered: &Discovered,
+        code: &str,
+        pkce_verifier: &str,
+    ) -> AppResult<TokenResponse> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.config.redirect_url),
+            ("client_id", &self.config.client_id),
+            ("code_verifier", pkce_verifier),
+        ];
+        if let Some(client_secret) = &self.config.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        self.client
+            .post(discovered.metadata.token_endpoint.clone())
+            .form(&form)
+            .send()?
+            .error_for_status()
+            .map_err(|err| err.chain(server_error("Error obtaining token")))?
+            .json()
+            .map_err(Into::into)
+    }
+
+    /// Validates the signature, issuer, audience, expiration and nonce of an ID token, and
+    /// returns its claims.
+    fn validate_id_token(
+        &self,
+        discovered: Discovered,
+        id_token: &str,
+        nonce: &str,
+    ) -> AppResult<Claims> {
+        let invalid =
+            |err: jsonwebtoken::errors::Error| bad_request(&format!("invalid ID token: {err}"));
+
+        let header = jsonwebtoken::decode_header(id_token).map_err(invalid)?;
+        let key = match header.alg {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                // Tokens signed with the client secret can only come from the provider
+                let client_secret = self.config.client_secret.as_ref().ok_or_else(|| {
+                    bad_request("invalid ID token: signed with the secret of a public client")
+                })?;
+                DecodingKey::from_secret(client_secret.as_bytes())
+            }
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => {
+                let kid = header.kid.as_deref();
+                let (n, e) = match find_rsa_key(&discovered.keys, kid) {
+                    Some(key) => key,
+                    // The provider may have rotated its keys since we fetched them
+                    None => find_rsa_key(&self.discover(true)?.keys, kid)
+                        .ok_or_else(|| bad_request("invalid ID token: unknown signing key"))?,
+                };
+                DecodingKey::from_rsa_components(&n, &e).map_err(invalid)?
+            }
+            alg => {
+                return Err(bad_request(&format!(
+                    "invalid ID token: unsupported algorithm {alg:?}"
+                )))
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&discovered.metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.leeway = CLOCK_LEEWAY_SECONDS;
+
+        let claims = jsonwebtoken::decode::<Claims>(id_token, &key, &validation)
+            .map_err(invalid)?
+            .claims;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(bad_request("invalid ID token: nonce mismatch"));
+        }
+        Ok(claims)
+    }
+}
+
+/// Returns the modulus and exponent of the RSA key with the `kid` key ID, or of the only RSA key
+/// if the token doesn't name one.
+fn find_rsa_key(keys: &JwkSet, kid: Option<&str>) -> Option<(String, String)> {
+    let mut rsa_keys = keys.keys.iter().filter_map(|jwk| match &jwk.algorithm {
+        AlgorithmParameters::RSA(rsa) => Some((jwk.common.key_id.as_deref(), rsa)),
+        _ => None,
+    });
+
+    let rsa = match kid {
+        Some(kid) => rsa_keys.find(|(key_id, _)| *key_id == Some(kid))?.1,
+        None => {
+            let (_, rsa) = rsa_keys.next()?;
+            if rsa_keys.next().is_some() {
+                return None;
+            }
+            rsa
+        }
+    };
+    Some((rsa.n.clone(), rsa.e.clone()))
+}
+
+impl IdentityProvider for OidcProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn display_name(&self) -> &str {
+        &self.config.display_name
+    }
+
+    fn begin_login(&self) -> AppResult<(Url, LoginState)> {
+        let discovered = self.discover(false)?;
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let csrf_token = CsrfToken::new_random();
+        let nonce = CsrfToken::new_random();
+
+        let scopes = std::iter::once("openid")
+            .chain(self.config.scopes.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut url = discovered.metadata.authorization_endpoint;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_url)
+            .append_pair("scope", &scopes)
+            .append_pair("state", csrf_token.secret())
+            .append_pair("nonce", nonce.secret())
+            .append_pair("code_challenge", pkce_challenge.as_str())
+            .append_pair("code_challenge_method", "S256");
+
+        let state = LoginState {
+            provider: self.config.name.clone(),
+            csrf_token: csrf_token.secret().clone(),
+            pkce_verifier: Some(pkce_verifier.secret().clone()),
+            nonce: Some(nonce.secret().clone()),
+        };
+        Ok((url, state))
+    }
+
+    fn complete_login(&self, code: &str, state: &LoginState) -> AppResult<Identity> {
+        let (pkce_verifier, nonce) = match (&state.pkce_verifier, &state.nonce) {
+            (Some(pkce_verifier), Some(nonce)) => (pkce_verifier, nonce),
+            _ => return Err(bad_request("invalid login state")),
+        };
+
+        let discovered = self.discover(false)?;
+        let token = self.exchange_code(&discovered, code, pkce_verifier)?;
+        let claims = self.validate_id_token(discovered, &token.id_token, nonce)?;
+
+        let email = claims.email.filter(|_| claims.email_verified);
+        let login = claims
+            .preferred_username
+            .or_else(|| {
+                let email = email.as_deref()?;
+                email.split('@').next().map(String::from)
+            })
+            .unwrap_or_else(|| claims.sub.clone());
+
+        Ok(Identity {
+            provider: self.config.name.clone(),
+            subject: claims.sub,
+            login,
+            name: claims.name,
+            email,
+            avatar: claims.picture,
+        })
+    }
+}
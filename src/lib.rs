@@ -39,14 +39,18 @@ pub mod admin;
 mod app;
 pub mod background_jobs;
 pub mod boot;
+pub mod cdn_logs;
 pub mod config;
+pub mod crate_files;
 pub mod db;
 mod downloads_counter;
 pub mod email;
 pub mod github;
+pub mod identity;
 pub mod metrics;
 pub mod middleware;
 mod publish_rate_limit;
+pub mod resolver;
 pub mod schema;
 pub mod sql;
 mod test_util;
@@ -57,6 +57,14 @@ metrics! {
         pub version_id_cache_hits: IntCounter,
         /// Number of version ID cache misses on the download endpoint.
         pub version_id_cache_misses: IntCounter,
+
+        /// Number of GitHub team membership checks answered from the cache.
+        pub team_membership_cache_hits: IntCounter,
+        /// Number of GitHub team membership checks that had to ask GitHub.
+        pub team_membership_cache_misses: IntCounter,
+        /// Number of GitHub team membership checks answered with an expired cached result,
+        /// because GitHub could not be reached.
+        pub team_membership_cache_stale_hits: IntCounter,
     }
 
     // All instance metrics will be prefixed with this namespace.
@@ -1,25 +1,44 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::action::{
+    insert_crate_owner_action, insert_version_owner_action, CrateOwnerAction, CrateOwnerActionKind,
+    VersionAction, VersionOwnerAction,
+};
+pub use self::advisory::{Advisory, NewAdvisory, VersionAdvisory};
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
+pub use self::cached_team_membership::CachedTeamMembership;
 pub use self::category::{Category, CrateCategory, NewCategory};
+pub use self::crate_owner_email_invitation::CrateOwnerEmailInvitation;
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitationOutcome};
 pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
-pub use self::download::VersionDownload;
+pub use self::download::{AgentDownloads, MonthlyDownloads, VersionDownload};
 pub use self::email::{Email, NewEmail};
 pub use self::follow::Follow;
 pub use self::keyword::{CrateKeyword, Keyword};
-pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
-pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::krate::{
+    Crate, CrateVersions, NewCrate, OwnerInvitationEmail, OwnershipTransferEmail,
+    RecentCrateDownloads, ReverseDependencyFilter,
+};
+pub use self::linked_identity::{LinkedIdentity, NewLinkedIdentity};
+pub use self::owner::{CrateOwner, Owner, OwnerKind, OwnerRole};
 pub use self::rights::Rights;
-pub use self::team::{NewTeam, Team};
+pub(crate) use self::team::team_with_gh_id_contains_user;
+pub use self::team::{NewTeam, Team, NATIVE_TEAM_PREFIX};
+pub use self::team_member::{TeamMember, TeamRole};
 pub use self::token::{ApiToken, CreatedApiToken};
 pub use self::user::{NewUser, User};
-pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::version::{NewVersion, TopVersions, Version, VersionFileStats};
+pub use self::webhook::{
+    check_webhook_url, resolve_webhook_url, CrateWebhook, NewCrateWebhook, WebhookDelivery,
+    WebhookEvent, WebhookPayload, WebhookResolveError,
+};
 
 pub mod helpers;
 
 mod action;
+pub mod advisory;
 mod badge;
+mod cached_team_membership;
 pub mod category;
+mod crate_owner_email_invitation;
 mod crate_owner_invitation;
 pub mod dependency;
 mod download;
@@ -27,9 +46,12 @@ mod email;
 mod follow;
 mod keyword;
 pub mod krate;
+mod linked_identity;
 mod owner;
 mod rights;
 mod team;
+mod team_member;
 mod token;
 pub mod user;
 mod version;
+pub mod webhook;
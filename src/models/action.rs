@@ -8,7 +8,7 @@ use diesel::{
 };
 use std::io::Write;
 
-use crate::models::{ApiToken, User, Version};
+use crate::models::{ApiToken, Crate, User, Version};
 use crate::schema::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
@@ -114,3 +114,72 @@ pub fn insert_version_owner_action(
         ))
         .get_result(conn)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum CrateOwnerActionKind {
+    TransferRequested = 0,
+    Add = 1,
+    Remove = 2,
+}
+
+impl FromSql<Integer, Pg> for CrateOwnerActionKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(CrateOwnerActionKind::TransferRequested),
+            1 => Ok(CrateOwnerActionKind::Add),
+            2 => Ok(CrateOwnerActionKind::Remove),
+            n => Err(format!("unknown crate owner action: {n}").into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for CrateOwnerActionKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// An audit entry for a change of the owners of a crate, made by `user_id`.
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[table_name = "crate_owner_actions"]
+pub struct CrateOwnerAction {
+    pub id: i32,
+    pub crate_id: i32,
+    pub user_id: i32,
+    pub action: CrateOwnerActionKind,
+    pub owner_id: i32,
+    pub owner_kind: i32,
+    pub time: NaiveDateTime,
+}
+
+impl CrateOwnerAction {
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        crate_owner_actions::table
+            .filter(crate_owner_actions::crate_id.eq(crate_id))
+            .order(crate_owner_actions::id)
+            .load(conn)
+    }
+}
+
+pub fn insert_crate_owner_action(
+    conn: &PgConnection,
+    crate_id: i32,
+    user_id: i32,
+    action: CrateOwnerActionKind,
+    owner_id: i32,
+    owner_kind: i32,
+) -> QueryResult<CrateOwnerAction> {
+    diesel::insert_into(crate_owner_actions::table)
+        .values((
+            crate_owner_actions::crate_id.eq(crate_id),
+            crate_owner_actions::user_id.eq(user_id),
+            crate_owner_actions::action.eq(action),
+            crate_owner_actions::owner_id.eq(owner_id),
+            crate_owner_actions::owner_kind.eq(owner_kind),
+        ))
+        .get_result(conn)
+}
@@ -0,0 +1,125 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use semver::VersionReq;
+
+use crate::models::{Crate, Version};
+use crate::schema::{advisories, version_advisories};
+
+/// A security advisory imported from a RustSec-format advisory database.
+#[derive(Queryable, Identifiable, Debug, Clone)]
+#[table_name = "advisories"]
+pub struct Advisory {
+    pub id: String,
+    pub crate_name: String,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub imported_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[table_name = "advisories"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewAdvisory<'a> {
+    pub id: &'a str,
+    pub crate_name: &'a str,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub url: Option<&'a str>,
+    pub date: NaiveDate,
+    pub patched: &'a [String],
+    pub unaffected: &'a [String],
+}
+
+/// Links a version to an advisory that affects it.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Version)]
+#[primary_key(version_id, advisory_id)]
+#[table_name = "version_advisories"]
+pub struct VersionAdvisory {
+    pub version_id: i32,
+    pub advisory_id: String,
+}
+
+impl Advisory {
+    /// Returns all advisories for the given crate, newest first.
+    pub fn for_crate(conn: &PgConnection, krate: &Crate) -> QueryResult<Vec<Advisory>> {
+        use crate::sql::canon_crate_name;
+
+        advisories::table
+            .filter(canon_crate_name(advisories::crate_name).eq(canon_crate_name(&krate.name)))
+            .order((advisories::date.desc(), advisories::id.desc()))
+            .load(conn)
+    }
+
+    /// Returns the ids of the advisories affecting each of the given versions.
+    ///
+    /// The returned list is in the same order as `versions`.
+    pub fn ids_for_versions(
+        conn: &PgConnection,
+        versions: &[Version],
+    ) -> QueryResult<Vec<Vec<String>>> {
+        Ok(VersionAdvisory::belonging_to(versions)
+            .order(version_advisories::advisory_id)
+            .load::<VersionAdvisory>(conn)?
+            .grouped_by(versions)
+            .into_iter()
+            .map(|advisories| advisories.into_iter().map(|a| a.advisory_id).collect())
+            .collect())
+    }
+
+    /// Returns the ids of the advisories affecting a single version.
+    pub fn ids_for_version(conn: &PgConnection, version: &Version) -> QueryResult<Vec<String>> {
+        VersionAdvisory::belonging_to(version)
+            .select(version_advisories::advisory_id)
+            .order(version_advisories::advisory_id)
+            .load(conn)
+    }
+}
+
+/// Returns `true` if `version` is matched by neither the `patched` nor the `unaffected`
+/// requirements of an advisory.
+pub fn is_affected(
+    version: &semver::Version,
+    patched: &[VersionReq],
+    unaffected: &[VersionReq],
+) -> bool {
+    !patched
+        .iter()
+        .chain(unaffected)
+        .any(|req| req.matches(version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_affected;
+    use semver::{Version, VersionReq};
+
+    fn reqs(reqs: &[&str]) -> Vec<VersionReq> {
+        reqs.iter()
+            .map(|req| VersionReq::parse(req).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn affected_versions() {
+        let patched = reqs(&[">= 1.2.3, < 2.0.0", ">= 2.0.1"]);
+        let unaffected = reqs(&["< 1.0.0"]);
+
+        let affected = |v: &str| is_affected(&Version::parse(v).unwrap(), &patched, &unaffected);
+        assert!(!affected("0.9.0"));
+        assert!(affected("1.0.0"));
+        assert!(affected("1.2.2"));
+        assert!(!affected("1.2.3"));
+        assert!(affected("2.0.0"));
+        assert!(!affected("2.0.1"));
+    }
+
+    #[test]
+    fn everything_is_affected_without_requirements() {
+        assert!(is_affected(&Version::parse("1.0.0").unwrap(), &[], &[]));
+    }
+}
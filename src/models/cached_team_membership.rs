@@ -0,0 +1,99 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use std::time::Duration;
+
+use crate::models::{Team, User};
+use crate::schema::{cached_team_memberships, teams, users};
+
+/// The result of asking GitHub whether a user is a member of a team.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[belongs_to(Team)]
+#[belongs_to(User)]
+#[primary_key(team_id, user_id)]
+#[table_name = "cached_team_memberships"]
+pub struct CachedTeamMembership {
+    pub team_id: i32,
+    pub user_id: i32,
+    pub is_member: bool,
+    pub checked_at: NaiveDateTime,
+    /// When the membership was last used to check the rights of the user.
+    pub last_used_at: NaiveDateTime,
+}
+
+impl CachedTeamMembership {
+    pub fn find(conn: &PgConnection, team_id: i32, user_id: i32) -> QueryResult<Option<Self>> {
+        cached_team_memberships::table
+            .find((team_id, user_id))
+            .first(conn)
+            .optional()
+    }
+
+    /// Records the result of a membership check made just now. This doesn't count as a use of
+    /// the membership, see `mark_used`.
+    pub fn record(
+        conn: &PgConnection,
+        team_id: i32,
+        user_id: i32,
+        is_member: bool,
+    ) -> QueryResult<Self> {
+        use diesel::dsl::now;
+
+        diesel::insert_into(cached_team_memberships::table)
+            .values((
+                cached_team_memberships::team_id.eq(team_id),
+                cached_team_memberships::user_id.eq(user_id),
+                cached_team_memberships::is_member.eq(is_member),
+            ))
+            .on_conflict((
+                cached_team_memberships::team_id,
+                cached_team_memberships::user_id,
+            ))
+            .do_update()
+            .set((
+                cached_team_memberships::is_member.eq(is_member),
+                cached_team_memberships::checked_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+
+    /// Records that the membership was just used to check the rights of the user.
+    pub fn mark_used(conn: &PgConnection, team_id: i32, user_id: i32) -> QueryResult<()> {
+        use diesel::dsl::now;
+
+        diesel::update(cached_team_memberships::table.find((team_id, user_id)))
+            .set(cached_team_memberships::last_used_at.eq(now))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns up to `limit` of the memberships checked before `cutoff`, least recently checked
+    /// first, along with their team and user.
+    pub fn checked_before(
+        conn: &PgConnection,
+        cutoff: NaiveDateTime,
+        limit: i64,
+    ) -> QueryResult<Vec<(Self, Team, User)>> {
+        cached_team_memberships::table
+            .inner_join(teams::table)
+            .inner_join(users::table)
+            .filter(cached_team_memberships::checked_at.lt(cutoff))
+            .order(cached_team_memberships::checked_at)
+            .limit(limit)
+            .load(conn)
+    }
+
+    /// Deletes the memberships last used before `cutoff`, returning how many were deleted.
+    pub fn delete_unused_since(conn: &PgConnection, cutoff: NaiveDateTime) -> QueryResult<usize> {
+        diesel::delete(
+            cached_team_memberships::table.filter(cached_team_memberships::last_used_at.lt(cutoff)),
+        )
+        .execute(conn)
+    }
+
+    /// How long ago the membership was checked.
+    pub fn age(&self) -> Duration {
+        (Utc::now().naive_utc() - self.checked_at)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::config;
+use crate::models::{CrateOwnerInvitation, OwnerRole};
+use crate::schema::crate_owner_email_invitations;
+use crate::sql::lower;
+use crate::util::errors::{cargo_err, AppResult};
+
+/// Maximum number of pending invitations by email address a crate can have at a time, so that
+/// owners can't use a crate to send emails to arbitrary addresses in bulk.
+const MAX_PENDING_EMAIL_INVITATIONS_PER_CRATE: i64 = 10;
+
+/// An invitation to become an owner of a crate, sent to an email address that doesn't belong to
+/// a user yet. It is turned into a regular `CrateOwnerInvitation` once a user verifies the email
+/// address.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct CrateOwnerEmailInvitation {
+    pub id: i32,
+    pub crate_id: i32,
+    pub email: String,
+    pub invited_by_user_id: i32,
+    pub role: OwnerRole,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateOwnerEmailInvitation {
+    /// Creates an invitation for `email`, returning `false` if one already exists. Fails if the
+    /// crate already has `MAX_PENDING_EMAIL_INVITATIONS_PER_CRATE` pending invitations.
+    pub fn create(
+        email: &str,
+        invited_by_user_id: i32,
+        crate_id: i32,
+        role: OwnerRole,
+        conn: &PgConnection,
+        config: &config::Server,
+    ) -> AppResult<bool> {
+        #[derive(Insertable, Clone, Copy, Debug)]
+        #[table_name = "crate_owner_email_invitations"]
+        struct NewRecord<'a> {
+            crate_id: i32,
+            email: &'a str,
+            invited_by_user_id: i32,
+            role: OwnerRole,
+        }
+
+        // Expired invitations are replaced, like regular invitations are.
+        let existing: Option<Self> = crate_owner_email_invitations::table
+            .filter(crate_owner_email_invitations::crate_id.eq(crate_id))
+            .filter(lower(crate_owner_email_invitations::email).eq(email.to_lowercase()))
+            .for_update()
+            .first(conn)
+            .optional()?;
+        if let Some(existing) = existing {
+            if !existing.is_expired(config) {
+                return Ok(false);
+            }
+            diesel::delete(&existing).execute(conn)?;
+        }
+
+        let days = chrono::Duration::days(config.ownership_invitations_expiration_days as i64);
+        let pending: i64 = crate_owner_email_invitations::table
+            .filter(crate_owner_email_invitations::crate_id.eq(crate_id))
+            .filter(crate_owner_email_invitations::created_at.gt(Utc::now().naive_utc() - days))
+            .count()
+            .get_result(conn)?;
+        if pending >= MAX_PENDING_EMAIL_INVITATIONS_PER_CRATE {
+            return Err(cargo_err(
+                "this crate has too many pending invitations by email, \
+                 please wait for some of them to be accepted or to expire",
+            ));
+        }
+
+        let inserted = diesel::insert_into(crate_owner_email_invitations::table)
+            .values(&NewRecord {
+                crate_id,
+                email,
+                invited_by_user_id,
+                role,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// Turns the pending invitations for `email` into regular invitations of the user that just
+    /// verified it. Expired invitations are dropped. Returns the number of converted invitations.
+    pub fn convert_for_user(
+        conn: &PgConnection,
+        config: &config::Server,
+        user_id: i32,
+        email: &str,
+    ) -> AppResult<usize> {
+        conn.transaction(|| {
+            let invitations: Vec<Self> = crate_owner_email_invitations::table
+                .filter(lower(crate_owner_email_invitations::email).eq(email.to_lowercase()))
+                .for_update()
+                .load(conn)?;
+
+            let mut converted = 0;
+            for invitation in invitations {
+                if !invitation.is_expired(config) {
+                    CrateOwnerInvitation::create(
+                        user_id,
+                        invitation.invited_by_user_id,
+                        invitation.crate_id,
+                        invitation.role,
+                        None,
+                        conn,
+                        config,
+                    )?;
+                    converted += 1;
+                }
+                diesel::delete(&invitation).execute(conn)?;
+            }
+            Ok(converted)
+        })
+    }
+
+    pub fn is_expired(&self, config: &config::Server) -> bool {
+        let days = chrono::Duration::days(config.ownership_invitations_expiration_days as i64);
+        self.created_at + days <= Utc::now().naive_utc()
+    }
+}
@@ -2,7 +2,9 @@ use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 
 use crate::config;
-use crate::models::{CrateOwner, OwnerKind};
+use crate::models::{
+    insert_crate_owner_action, CrateOwner, CrateOwnerActionKind, OwnerKind, OwnerRole,
+};
 use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::util::errors::{AppResult, OwnershipInvitationExpired};
 
@@ -22,6 +24,10 @@ pub struct CrateOwnerInvitation {
     pub created_at: NaiveDateTime,
     pub token: String,
     pub token_created_at: Option<NaiveDateTime>,
+    pub role: OwnerRole,
+    /// The owner that is replaced by the invited user once the invitation is accepted, if this
+    /// invitation is an ownership transfer.
+    pub transfer_from_user_id: Option<i32>,
 }
 
 impl CrateOwnerInvitation {
@@ -29,6 +35,8 @@ impl CrateOwnerInvitation {
         invited_user_id: i32,
         invited_by_user_id: i32,
         crate_id: i32,
+        role: OwnerRole,
+        transfer_from_user_id: Option<i32>,
         conn: &PgConnection,
         config: &config::Server,
     ) -> AppResult<NewCrateOwnerInvitationOutcome> {
@@ -38,6 +46,8 @@ impl CrateOwnerInvitation {
             invited_user_id: i32,
             invited_by_user_id: i32,
             crate_id: i32,
+            role: OwnerRole,
+            transfer_from_user_id: Option<i32>,
         }
 
         // Before actually creating the invite, check if an expired invitation already exists
@@ -65,6 +75,8 @@ impl CrateOwnerInvitation {
                 invited_user_id,
                 invited_by_user_id,
                 crate_id,
+                role,
+                transfer_from_user_id,
             })
             // The ON CONFLICT DO NOTHING clause results in not creating the invite if another one
             // already exists. This does not cause problems with expired invitation as those are
@@ -110,18 +122,50 @@ impl CrateOwnerInvitation {
                     created_by: self.invited_by_user_id,
                     owner_kind: OwnerKind::User as i32,
                     email_notifications: true,
+                    role: self.role,
                 })
                 .on_conflict(crate_owners::table.primary_key())
                 .do_update()
-                .set(crate_owners::deleted.eq(false))
+                .set((
+                    crate_owners::deleted.eq(false),
+                    crate_owners::role.eq(self.role),
+                ))
                 .execute(conn)?;
 
+            if let Some(previous_owner_id) = self.transfer_from_user_id {
+                self.complete_transfer(conn, previous_owner_id)?;
+            }
+
             diesel::delete(&self).execute(conn)?;
 
             Ok(())
         })
     }
 
+    /// Removes the previous owner of a transferred crate, recording both sides of the swap.
+    fn complete_transfer(&self, conn: &PgConnection, previous_owner_id: i32) -> AppResult<()> {
+        let target =
+            crate_owners::table.find((self.crate_id, previous_owner_id, OwnerKind::User as i32));
+        diesel::update(target)
+            .set(crate_owners::deleted.eq(true))
+            .execute(conn)?;
+
+        for (action, owner_id) in [
+            (CrateOwnerActionKind::Add, self.invited_user_id),
+            (CrateOwnerActionKind::Remove, previous_owner_id),
+        ] {
+            insert_crate_owner_action(
+                conn,
+                self.crate_id,
+                self.invited_user_id,
+                action,
+                owner_id,
+                OwnerKind::User as i32,
+            )?;
+        }
+        Ok(())
+    }
+
     pub fn decline(self, conn: &PgConnection) -> AppResult<()> {
         // The check to prevent declining expired invitations is *explicitly* missing. We do not
         // care if an expired invitation is declined, as that just removes the invitation from the
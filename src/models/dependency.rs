@@ -1,12 +1,15 @@
+use std::collections::HashMap;
+
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
+use diesel::prelude::*;
 use diesel::sql_types::Integer;
 
 use crate::models::{Crate, Version};
 use crate::schema::*;
 use cargo_registry_index::DependencyKind as IndexDependencyKind;
 
-#[derive(Identifiable, Associations, Debug, Queryable, QueryableByName)]
+#[derive(Identifiable, Associations, Debug, Clone, Queryable, QueryableByName)]
 #[belongs_to(Version)]
 #[belongs_to(Crate)]
 #[table_name = "dependencies"]
@@ -20,6 +23,50 @@ pub struct Dependency {
     pub features: Vec<String>,
     pub target: Option<String>,
     pub kind: DependencyKind,
+    /// The name of the dependency in the `Cargo.toml` of the depending crate,
+    /// if it was renamed there.
+    pub explicit_name: Option<String>,
+}
+
+impl Dependency {
+    /// Returns the highest non-yanked version of the depended on crate that
+    /// matches the version requirement, for each of the given dependencies.
+    ///
+    /// The returned list is in the same order as `dependencies`.
+    pub fn latest_matching_versions(
+        conn: &PgConnection,
+        dependencies: &[Dependency],
+    ) -> QueryResult<Vec<Option<semver::Version>>> {
+        let crate_ids = dependencies
+            .iter()
+            .map(|dep| dep.crate_id)
+            .collect::<Vec<_>>();
+        let nums: Vec<(i32, String)> = versions::table
+            .filter(versions::crate_id.eq_any(crate_ids))
+            .filter(versions::yanked.eq(false))
+            .select((versions::crate_id, versions::num))
+            .load(conn)?;
+
+        let mut versions_by_crate: HashMap<i32, Vec<semver::Version>> = HashMap::new();
+        for (crate_id, num) in nums {
+            if let Ok(version) = semver::Version::parse(&num) {
+                versions_by_crate.entry(crate_id).or_default().push(version);
+            }
+        }
+
+        Ok(dependencies
+            .iter()
+            .map(|dep| {
+                let req = semver::VersionReq::parse(&dep.req).ok()?;
+                versions_by_crate
+                    .get(&dep.crate_id)?
+                    .iter()
+                    .filter(|version| req.matches(version))
+                    .max()
+                    .cloned()
+            })
+            .collect())
+    }
 }
 
 #[derive(Debug, QueryableByName)]
@@ -31,9 +78,11 @@ pub struct ReverseDependency {
     #[sql_type = "::diesel::sql_types::Text"]
     #[column_name = "crate_name"]
     pub name: String,
+    #[sql_type = "::diesel::sql_types::Text"]
+    pub version_num: String,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug, FromSqlRow)]
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, FromSqlRow)]
 #[serde(rename_all = "lowercase")]
 #[repr(u32)]
 pub enum DependencyKind {
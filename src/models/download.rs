@@ -1,6 +1,9 @@
 use crate::models::Version;
-use crate::schema::version_downloads;
+use crate::schema::{version_downloads, version_downloads_by_agent};
 use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, BigInt, Integer, Text};
 
 #[derive(Queryable, Identifiable, Associations, Debug, Clone, Copy)]
 #[belongs_to(Version)]
@@ -12,3 +15,72 @@ pub struct VersionDownload {
     pub date: NaiveDate,
     pub processed: bool,
 }
+
+/// Download counts of one or more versions in a calendar month, combining the archived monthly
+/// totals with the daily counts that are not archived yet.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct MonthlyDownloads {
+    /// The month, formatted as `YYYY-MM`.
+    #[sql_type = "Text"]
+    pub month: String,
+    #[sql_type = "BigInt"]
+    pub downloads: i64,
+}
+
+impl MonthlyDownloads {
+    /// Returns the monthly download counts summed over all of the given versions, sorted by month.
+    pub fn for_versions(conn: &PgConnection, version_ids: &[i32]) -> QueryResult<Vec<Self>> {
+        sql_query(
+            "SELECT to_char(month, 'YYYY-MM') AS month, SUM(downloads)::bigint AS downloads \
+            FROM ( \
+                SELECT month, downloads \
+                FROM version_downloads_monthly \
+                WHERE version_id = ANY($1) \
+                UNION ALL \
+                SELECT date_trunc('month', date)::date, downloads \
+                FROM version_downloads \
+                WHERE version_id = ANY($1) \
+            ) monthly \
+            GROUP BY 1 \
+            ORDER BY 1",
+        )
+        .bind::<Array<Integer>, _>(version_ids)
+        .load(conn)
+    }
+}
+
+/// Download counts of one or more versions for a class of `User-Agent`, see
+/// `crate::downloads_counter::user_agent::classify`.
+#[derive(Queryable, Debug, Clone)]
+pub struct AgentDownloads {
+    pub agent_class: String,
+    pub downloads: i64,
+}
+
+impl AgentDownloads {
+    /// Returns the downloads of the given versions between `start` and `end` (inclusive) per class
+    /// of `User-Agent`, sorted by the number of downloads.
+    pub fn for_versions(
+        conn: &PgConnection,
+        version_ids: &[i32],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> QueryResult<Vec<Self>> {
+        use diesel::dsl::sql;
+
+        let sum_downloads = sql::<BigInt>("SUM(version_downloads_by_agent.downloads)");
+        version_downloads_by_agent::table
+            .select((
+                version_downloads_by_agent::agent_class,
+                sum_downloads.clone(),
+            ))
+            .filter(version_downloads_by_agent::version_id.eq_any(version_ids))
+            .filter(version_downloads_by_agent::date.between(start, end))
+            .group_by(version_downloads_by_agent::agent_class)
+            .order((
+                sum_downloads.desc(),
+                version_downloads_by_agent::agent_class,
+            ))
+            .load(conn)
+    }
+}
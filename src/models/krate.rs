@@ -7,10 +7,12 @@ use url::Url;
 
 use crate::app::App;
 use crate::controllers::helpers::pagination::*;
+use crate::email::Emails;
 use crate::models::version::TopVersions;
 use crate::models::{
-    Badge, CrateOwner, CrateOwnerInvitation, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    insert_crate_owner_action, Badge, CrateOwner, CrateOwnerActionKind, CrateOwnerEmailInvitation,
+    CrateOwnerInvitation, DependencyKind, NewCrateOwnerInvitationOutcome, Owner, OwnerKind,
+    OwnerRole, ReverseDependency, Team, User, Version,
 };
 use crate::util::errors::{cargo_err, AppResult};
 
@@ -43,6 +45,94 @@ pub struct Crate {
     pub max_upload_size: Option<i32>,
 }
 
+/// Filters applied to the reverse dependencies of a crate.
+#[derive(Debug, Default)]
+pub struct ReverseDependencyFilter {
+    pub kind: Option<DependencyKind>,
+    pub optional: Option<bool>,
+    /// Whether the requirement has to (or must not) match the newest
+    /// non-yanked version of the crate.
+    pub matches_latest: Option<bool>,
+    /// Only keep dependents whose requirement matches at least one non-yanked
+    /// version of the crate within this range.
+    pub range: Option<semver::VersionReq>,
+}
+
+impl ReverseDependencyFilter {
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none()
+            && self.optional.is_none()
+            && self.matches_latest.is_none()
+            && self.range.is_none()
+    }
+}
+
+/// An email inviting a user to take over a crate, which is only sent once the
+/// transaction that created the invitation is committed.
+#[derive(Debug)]
+pub struct OwnershipTransferEmail {
+    email: String,
+    user_name: String,
+    crate_name: String,
+    previous_owner: String,
+    token: String,
+}
+
+impl OwnershipTransferEmail {
+    /// Sends the email, swallowing any error since the invitation is listed on
+    /// the pending invites page either way.
+    pub fn send(&self, emails: &Emails) {
+        let _ = emails.send_ownership_transfer(
+            &self.email,
+            &self.user_name,
+            &self.crate_name,
+            &self.previous_owner,
+            &self.token,
+        );
+    }
+}
+
+/// An email inviting a user or an email address to become an owner of a crate,
+/// which is only sent once the transaction that created the invitation is
+/// committed.
+#[derive(Debug)]
+pub enum OwnerInvitationEmail {
+    /// Sent to the verified email address of an existing user.
+    User {
+        email: String,
+        user_name: String,
+        crate_name: String,
+        token: String,
+    },
+    /// Sent to an email address that doesn't belong to a user yet.
+    Address {
+        email: String,
+        user_name: String,
+        crate_name: String,
+    },
+}
+
+impl OwnerInvitationEmail {
+    /// Sends the email, swallowing any error. Whether or not the email is sent,
+    /// the invitation is listed on https://crates.io/me/pending-invites/ once
+    /// the address is verified.
+    pub fn send(&self, emails: &Emails) {
+        let _ = match self {
+            Self::User {
+                email,
+                user_name,
+                crate_name,
+                token,
+            } => emails.send_owner_invite(email, user_name, crate_name, token),
+            Self::Address {
+                email,
+                user_name,
+                crate_name,
+            } => emails.send_owner_email_invite(email, user_name, crate_name),
+        };
+    }
+}
+
 /// We literally never want to select `textsearchable_index_col`
 /// so we provide this type and constant to pass to `.select`
 type AllColumns = (
@@ -187,6 +277,7 @@ impl<'a> NewCrate<'a> {
                     created_by: user_id,
                     owner_kind: OwnerKind::User as i32,
                     email_notifications: true,
+                    role: OwnerRole::Admin,
                 };
                 diesel::insert_into(crate_owners::table)
                     .values(&owner)
@@ -317,88 +408,328 @@ impl Crate {
     }
 
     pub fn owners(&self, conn: &PgConnection) -> QueryResult<Vec<Owner>> {
+        Ok(self
+            .owners_with_roles(conn)?
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect())
+    }
+
+    /// Returns the owners of the crate along with their role, users first.
+    pub fn owners_with_roles(&self, conn: &PgConnection) -> QueryResult<Vec<(Owner, OwnerRole)>> {
         let users = CrateOwner::by_owner_kind(OwnerKind::User)
             .filter(crate_owners::crate_id.eq(self.id))
             .inner_join(users::table)
-            .select(users::all_columns)
-            .load(conn)?
+            .select((users::all_columns, crate_owners::role))
+            .load::<(User, OwnerRole)>(conn)?
             .into_iter()
-            .map(Owner::User);
+            .map(|(user, role)| (Owner::User(user), role));
         let teams = CrateOwner::by_owner_kind(OwnerKind::Team)
             .filter(crate_owners::crate_id.eq(self.id))
             .inner_join(teams::table)
-            .select(teams::all_columns)
-            .load(conn)?
+            .select((teams::all_columns, crate_owners::role))
+            .load::<(Team, OwnerRole)>(conn)?
             .into_iter()
-            .map(Owner::Team);
+            .map(|(team, role)| (Owner::Team(team), role));
 
         Ok(users.chain(teams).collect())
     }
 
+    /// Invites the user or email address `login` to become an owner of the crate, or adds the
+    /// team `login` as an owner right away.
+    ///
+    /// The invitation email, if any, is returned instead of being sent, so that it's only sent
+    /// once the invitation is committed.
     pub fn owner_add(
         &self,
         app: &App,
         conn: &PgConnection,
         req_user: &User,
         login: &str,
-    ) -> AppResult<String> {
-        use diesel::insert_into;
+        role: Option<OwnerRole>,
+    ) -> AppResult<(String, Option<OwnerInvitationEmail>)> {
+        // Email addresses can be invited before they belong to a user. Some logins contain an `@`
+        // too, so those take precedence.
+        if is_email_address(app, login) && User::find_by_login(conn, login).optional()?.is_none() {
+            let role = role.unwrap_or(OwnerRole::Admin);
+            return self.owner_add_by_email(app, conn, req_user, login, role);
+        }
 
         let owner = Owner::find_or_create_by_login(app, conn, req_user, login)?;
+        let role = role.unwrap_or_else(|| OwnerRole::default_for(&owner));
+        check_role_allowed(&owner, role)?;
 
         match owner {
             // Users are invited and must accept before being added
             Owner::User(user) => {
                 let config = &app.config;
-                match CrateOwnerInvitation::create(user.id, req_user.id, self.id, conn, config)? {
+                match CrateOwnerInvitation::create(
+                    user.id,
+                    req_user.id,
+                    self.id,
+                    role,
+                    None,
+                    conn,
+                    config,
+                )? {
                     NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
-                        if let Ok(Some(email)) = user.verified_email(conn) {
-                            // Swallow any error. Whether or not the email is sent, the invitation
-                            // entry will be created in the database and the user will see the
-                            // invitation when they visit https://crates.io/me/pending-invites/.
-                            let _ = app.emails.send_owner_invite(
-                                &email,
-                                &req_user.gh_login,
-                                &self.name,
-                                &plaintext_token,
-                            );
-                        }
+                        let email = user.verified_email(conn).ok().flatten().map(|email| {
+                            OwnerInvitationEmail::User {
+                                email,
+                                user_name: req_user.gh_login.clone(),
+                                crate_name: self.name.clone(),
+                                token: plaintext_token,
+                            }
+                        });
 
-                        Ok(format!(
+                        let msg = format!(
                             "user {} has been invited to be an owner of crate {}",
                             user.gh_login, self.name
-                        ))
+                        );
+                        Ok((msg, email))
+                    }
+                    NewCrateOwnerInvitationOutcome::AlreadyExists => {
+                        let msg = format!(
+                            "user {} already has a pending invitation to be an owner of crate {}",
+                            user.gh_login, self.name
+                        );
+                        Ok((msg, None))
                     }
-                    NewCrateOwnerInvitationOutcome::AlreadyExists => Ok(format!(
-                        "user {} already has a pending invitation to be an owner of crate {}",
-                        user.gh_login, self.name
-                    )),
                 }
             }
             // Teams are added as owners immediately
             owner @ Owner::Team(_) => {
-                insert_into(crate_owners::table)
-                    .values(&CrateOwner {
-                        crate_id: self.id,
-                        owner_id: owner.id(),
-                        created_by: req_user.id,
-                        owner_kind: OwnerKind::Team as i32,
-                        email_notifications: true,
-                    })
-                    .on_conflict(crate_owners::table.primary_key())
-                    .do_update()
-                    .set(crate_owners::deleted.eq(false))
-                    .execute(conn)?;
+                self.add_team_owner(conn, req_user, &owner, role)?;
 
-                Ok(format!(
+                let msg = format!(
                     "team {} has been added as an owner of crate {}",
                     owner.login(),
                     self.name
-                ))
+                );
+                Ok((msg, None))
             }
         }
     }
 
+    /// Invites the user with the verified email address `email`, or stores the invitation until
+    /// someone verifies that address. The response is the same in both cases, so that it doesn't
+    /// reveal which user the address belongs to.
+    fn owner_add_by_email(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        req_user: &User,
+        email: &str,
+        role: OwnerRole,
+    ) -> AppResult<(String, Option<OwnerInvitationEmail>)> {
+        let config = &app.config;
+        let msg = format!(
+            "{} has been invited to be an owner of crate {}",
+            email, self.name
+        );
+
+        if let Some(user) = User::find_by_verified_email(conn, email)? {
+            if let NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } =
+                CrateOwnerInvitation::create(
+                    user.id,
+                    req_user.id,
+                    self.id,
+                    role,
+                    None,
+                    conn,
+                    config,
+                )?
+            {
+                let email = OwnerInvitationEmail::User {
+                    email: email.to_string(),
+                    user_name: req_user.gh_login.clone(),
+                    crate_name: self.name.clone(),
+                    token: plaintext_token,
+                };
+                return Ok((msg, Some(email)));
+            }
+            return Ok((msg, None));
+        }
+
+        if CrateOwnerEmailInvitation::create(email, req_user.id, self.id, role, conn, config)? {
+            let email = OwnerInvitationEmail::Address {
+                email: email.to_string(),
+                user_name: req_user.gh_login.clone(),
+                crate_name: self.name.clone(),
+            };
+            return Ok((msg, Some(email)));
+        }
+        Ok((msg, None))
+    }
+
+    fn add_team_owner(
+        &self,
+        conn: &PgConnection,
+        req_user: &User,
+        team: &Owner,
+        role: OwnerRole,
+    ) -> QueryResult<()> {
+        diesel::insert_into(crate_owners::table)
+            .values(&CrateOwner {
+                crate_id: self.id,
+                owner_id: team.id(),
+                created_by: req_user.id,
+                owner_kind: OwnerKind::Team as i32,
+                email_notifications: true,
+                role,
+            })
+            .on_conflict(crate_owners::table.primary_key())
+            .do_update()
+            .set((crate_owners::deleted.eq(false), crate_owners::role.eq(role)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Transfers the ownership of the crate from the owner `from`, who has the given `role`, to
+    /// the user or team named `to`.
+    ///
+    /// Users are sent an invitation, and only replace `from` once they accept it. Teams can't
+    /// accept invitations, so they replace `from` right away, but can't be given more than the
+    /// publisher role.
+    ///
+    /// The email notifying the invited user, if they have a verified email address, is returned
+    /// instead of being sent, so that it's only sent once the transfer is committed.
+    pub fn transfer_ownership(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        req_user: &User,
+        from: &Owner,
+        role: OwnerRole,
+        to: &str,
+    ) -> AppResult<(String, Option<OwnershipTransferEmail>)> {
+        let owner = Owner::find_or_create_by_login(app, conn, req_user, to)?;
+        let is_owner = diesel::select(diesel::dsl::exists(
+            crate_owners::table
+                .find((self.id, owner.id(), owner.kind()))
+                .filter(crate_owners::deleted.eq(false)),
+        ))
+        .get_result::<bool>(conn)?;
+        if is_owner {
+            return Err(cargo_err(&format_args!(
+                "`{}` is already an owner of crate {}",
+                owner.login(),
+                self.name
+            )));
+        }
+
+        match (from, owner) {
+            (Owner::User(previous_owner), Owner::User(user)) => {
+                let config = &app.config;
+                let outcome = CrateOwnerInvitation::create(
+                    user.id,
+                    req_user.id,
+                    self.id,
+                    role,
+                    Some(previous_owner.id),
+                    conn,
+                    config,
+                )?;
+                let plaintext_token = match outcome {
+                    NewCrateOwnerInvitationOutcome::InviteCreated { plaintext_token } => {
+                        plaintext_token
+                    }
+                    NewCrateOwnerInvitationOutcome::AlreadyExists => {
+                        return Err(cargo_err(&format_args!(
+                            "user {} already has a pending invitation to be an owner of crate {}",
+                            user.gh_login, self.name
+                        )));
+                    }
+                };
+
+                insert_crate_owner_action(
+                    conn,
+                    self.id,
+                    req_user.id,
+                    CrateOwnerActionKind::TransferRequested,
+                    user.id,
+                    OwnerKind::User as i32,
+                )?;
+
+                let email = user
+                    .verified_email(conn)?
+                    .map(|email| OwnershipTransferEmail {
+                        email,
+                        user_name: req_user.gh_login.clone(),
+                        crate_name: self.name.clone(),
+                        previous_owner: previous_owner.gh_login.clone(),
+                        token: plaintext_token,
+                    });
+
+                let msg = format!(
+                    "user {} has been invited to take over crate {} from {}",
+                    user.gh_login, self.name, previous_owner.gh_login
+                );
+                Ok((msg, email))
+            }
+            (_, team @ Owner::Team(_)) => {
+                self.add_team_owner(conn, req_user, &team, role.min(OwnerRole::Publisher))?;
+
+                let target = crate_owners::table.find((self.id, from.id(), from.kind()));
+                diesel::update(target)
+                    .set(crate_owners::deleted.eq(true))
+                    .execute(conn)?;
+
+                insert_crate_owner_action(
+                    conn,
+                    self.id,
+                    req_user.id,
+                    CrateOwnerActionKind::Add,
+                    team.id(),
+                    team.kind(),
+                )?;
+                insert_crate_owner_action(
+                    conn,
+                    self.id,
+                    req_user.id,
+                    CrateOwnerActionKind::Remove,
+                    from.id(),
+                    from.kind(),
+                )?;
+
+                let msg = format!(
+                    "team {} has replaced {} as an owner of crate {}",
+                    team.login(),
+                    from.login(),
+                    self.name
+                );
+                Ok((msg, None))
+            }
+            (Owner::Team(_), Owner::User(_)) => Err(cargo_err(&format_args!(
+                "team {} can only be replaced by another team",
+                from.login()
+            ))),
+        }
+    }
+
+    /// Changes the role of an existing owner of the crate.
+    pub fn owner_set_role(
+        &self,
+        conn: &PgConnection,
+        owner: &Owner,
+        role: OwnerRole,
+    ) -> AppResult<String> {
+        check_role_allowed(owner, role)?;
+
+        let target = crate_owners::table.find((self.id(), owner.id(), owner.kind()));
+        diesel::update(target)
+            .set(crate_owners::role.eq(role))
+            .execute(conn)?;
+
+        let role: &'static str = role.into();
+        Ok(format!(
+            "{} now has the {} role on crate {}",
+            owner.login(),
+            role,
+            self.name
+        ))
+    }
+
     pub fn owner_remove(
         &self,
         app: &App,
@@ -426,20 +757,187 @@ impl Crate {
         &self,
         conn: &PgConnection,
         options: PaginationOptions,
+    ) -> AppResult<(Vec<ReverseDependency>, i64)> {
+        let offset = options.offset().unwrap_or_default();
+        let filter = ReverseDependencyFilter::default();
+        self.reverse_dependency_rows(
+            conn,
+            &filter,
+            None,
+            i64::from(offset),
+            i64::from(options.per_page),
+        )
+    }
+
+    /// Returns all reverse dependencies, sorted by the downloads of the
+    /// dependent crates.
+    pub(crate) fn all_reverse_dependencies(
+        &self,
+        conn: &PgConnection,
+    ) -> AppResult<Vec<ReverseDependency>> {
+        let filter = ReverseDependencyFilter::default();
+        let (rows, _) = self.reverse_dependency_rows(conn, &filter, None, 0, i64::MAX)?;
+        Ok(rows)
+    }
+
+    /// Returns the reverse dependencies matching `filter`, sorted by the
+    /// downloads of the dependent crates, and the total number of matching
+    /// reverse dependencies.
+    pub(crate) fn filtered_reverse_dependencies(
+        &self,
+        conn: &PgConnection,
+        filter: &ReverseDependencyFilter,
+        offset: i64,
+        limit: i64,
+    ) -> AppResult<(Vec<ReverseDependency>, i64)> {
+        let reqs = self.matching_dependency_reqs(conn, filter)?;
+        self.reverse_dependency_rows(conn, filter, reqs, offset, limit)
+    }
+
+    /// Returns the distinct requirements on this crate that satisfy the
+    /// `matches_latest` and `range` conditions of `filter`, or `None` if
+    /// neither is set.
+    ///
+    /// The database can't match semver requirements, but there are far fewer
+    /// distinct requirements than dependents, so they are matched here and
+    /// the dependents are filtered by the database.
+    fn matching_dependency_reqs(
+        &self,
+        conn: &PgConnection,
+        filter: &ReverseDependencyFilter,
+    ) -> QueryResult<Option<Vec<String>>> {
+        if filter.matches_latest.is_none() && filter.range.is_none() {
+            return Ok(None);
+        }
+
+        let nums: Vec<String> = versions::table
+            .filter(versions::crate_id.eq(self.id))
+            .filter(versions::yanked.eq(false))
+            .select(versions::num)
+            .load(conn)?;
+        let versions = nums
+            .iter()
+            .filter_map(|num| semver::Version::parse(num).ok())
+            .collect::<Vec<_>>();
+        let latest = versions.iter().max();
+        let in_range = versions
+            .iter()
+            .filter(|version| filter.range.as_ref().map_or(true, |r| r.matches(version)))
+            .collect::<Vec<_>>();
+
+        let reqs: Vec<String> = dependencies::table
+            .filter(dependencies::crate_id.eq(self.id))
+            .select(dependencies::req)
+            .distinct()
+            .load(conn)?;
+
+        Ok(Some(
+            reqs.into_iter()
+                .filter(|req| {
+                    let req = match semver::VersionReq::parse(req) {
+                        Ok(req) => req,
+                        Err(_) => return false,
+                    };
+                    if let Some(matches_latest) = filter.matches_latest {
+                        if latest.map_or(false, |latest| req.matches(latest)) != matches_latest {
+                            return false;
+                        }
+                    }
+                    filter.range.is_none() || in_range.iter().any(|version| req.matches(version))
+                })
+                .collect(),
+        ))
+    }
+
+    fn reverse_dependency_rows(
+        &self,
+        conn: &PgConnection,
+        filter: &ReverseDependencyFilter,
+        reqs: Option<Vec<String>>,
+        offset: i64,
+        limit: i64,
     ) -> AppResult<(Vec<ReverseDependency>, i64)> {
         use diesel::sql_query;
-        use diesel::sql_types::{BigInt, Integer};
+        use diesel::sql_types::{Array, BigInt, Integer, Nullable};
 
-        let offset = options.offset().unwrap_or_default();
         let rows: Vec<WithCount<ReverseDependency>> =
             sql_query(include_str!("krate_reverse_dependencies.sql"))
                 .bind::<Integer, _>(self.id)
-                .bind::<BigInt, _>(i64::from(offset))
-                .bind::<BigInt, _>(i64::from(options.per_page))
+                .bind::<BigInt, _>(offset)
+                .bind::<BigInt, _>(limit)
+                .bind::<Nullable<Integer>, _>(filter.kind.map(|kind| kind as i32))
+                .bind::<Nullable<Bool>, _>(filter.optional)
+                .bind::<Nullable<Array<Text>>, _>(reqs)
                 .load(conn)?;
 
         Ok(rows.records_and_total())
     }
+
+    /// Returns the reverse dependencies whose requirement is only satisfied by
+    /// `version`, or by `version` and other yanked versions. These dependents
+    /// can no longer be resolved once `version` is yanked.
+    ///
+    /// Like `reverse_dependencies`, only the newest non-yanked version of each
+    /// dependent is considered, and the result is sorted by the downloads of
+    /// the dependent crates.
+    pub fn yank_impact(
+        &self,
+        conn: &PgConnection,
+        version: &Version,
+    ) -> AppResult<Vec<ReverseDependency>> {
+        let yanked_version = semver::Version::parse(&version.num)
+            .map_err(|_| cargo_err(&format_args!("invalid semver: {}", version.num)))?;
+
+        let remaining: Vec<String> = versions::table
+            .filter(versions::crate_id.eq(self.id))
+            .filter(versions::id.ne(version.id))
+            .filter(versions::yanked.eq(false))
+            .select(versions::num)
+            .load(conn)?;
+        let remaining = remaining
+            .iter()
+            .filter_map(|num| semver::Version::parse(num).ok())
+            .collect::<Vec<_>>();
+
+        Ok(self
+            .all_reverse_dependencies(conn)?
+            .into_iter()
+            .filter(
+                |rev_dep| match semver::VersionReq::parse(&rev_dep.dependency.req) {
+                    Ok(req) => {
+                        req.matches(&yanked_version) && !remaining.iter().any(|v| req.matches(v))
+                    }
+                    Err(_) => false,
+                },
+            )
+            .collect())
+    }
+}
+
+/// Whether `login` is an email address, rather than the login of a user created through an
+/// identity provider, which looks like `{login}@{provider}`.
+fn is_email_address(app: &App, login: &str) -> bool {
+    let address = match login.parse::<lettre::Address>() {
+        Ok(address) => address,
+        Err(_) => return false,
+    };
+    let domain = address.domain();
+    domain.contains('.')
+        && !app
+            .config
+            .oidc_providers
+            .iter()
+            .any(|provider| provider.name.eq_ignore_ascii_case(domain))
+}
+
+/// Teams can't manage the owners of a crate, so they can't be given the admin role.
+fn check_role_allowed(owner: &Owner, role: OwnerRole) -> AppResult<()> {
+    match owner {
+        Owner::Team(_) if role == OwnerRole::Admin => {
+            Err(cargo_err("teams can't be given the admin role"))
+        }
+        _ => Ok(()),
+    }
 }
 
 #[cfg(test)]
@@ -4,7 +4,8 @@ SELECT *, COUNT(*) OVER () as total FROM (
     SELECT DISTINCT ON (crate_downloads, crate_name)
     dependencies.*,
     crates.downloads AS crate_downloads,
-    crates.name AS crate_name
+    crates.name AS crate_name,
+    versions.num AS version_num
     FROM dependencies
     -- We only want the crates whose *max* version is dependent, so we join on a
     -- subselect that includes the versions with their ordinal position
@@ -31,6 +32,11 @@ SELECT *, COUNT(*) OVER () as total FROM (
       ON crates.id = versions.crate_id
     WHERE dependencies.crate_id = $1
       AND rn = 1
+      -- Optional filters, which are skipped if the parameter is NULL. $6 is
+      -- the list of requirements that match the semver filters.
+      AND ($4::int IS NULL OR dependencies.kind = $4)
+      AND ($5::bool IS NULL OR dependencies.optional = $5)
+      AND ($6::text[] IS NULL OR dependencies.req = ANY($6))
     ORDER BY crate_downloads DESC
 ) t
 OFFSET $2
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::linked_identities;
+
+/// An account of a user at an identity provider they can log in with.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+#[table_name = "linked_identities"]
+pub struct LinkedIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub provider: String,
+    pub subject: String,
+    pub login: String,
+    pub email: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_login_at: NaiveDateTime,
+}
+
+impl LinkedIdentity {
+    pub fn find(conn: &PgConnection, provider: &str, subject: &str) -> QueryResult<Self> {
+        linked_identities::table
+            .filter(linked_identities::provider.eq(provider))
+            .filter(linked_identities::subject.eq(subject))
+            .first(conn)
+    }
+
+    pub fn for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Vec<Self>> {
+        linked_identities::table
+            .filter(linked_identities::user_id.eq(user_id))
+            .order(linked_identities::created_at)
+            .load(conn)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "linked_identities"]
+pub struct NewLinkedIdentity<'a> {
+    pub user_id: i32,
+    pub provider: &'a str,
+    pub subject: &'a str,
+    pub login: &'a str,
+    pub email: Option<&'a str>,
+}
+
+impl NewLinkedIdentity<'_> {
+    /// Links the identity to the user, or updates the details of the identity and records the
+    /// login if it is already linked.
+    pub fn create_or_update(&self, conn: &PgConnection) -> QueryResult<LinkedIdentity> {
+        use diesel::dsl::now;
+        use diesel::pg::upsert::excluded;
+
+        diesel::insert_into(linked_identities::table)
+            .values(self)
+            .on_conflict((linked_identities::provider, linked_identities::subject))
+            .do_update()
+            .set((
+                linked_identities::login.eq(excluded(linked_identities::login)),
+                linked_identities::email.eq(excluded(linked_identities::email)),
+                linked_identities::last_login_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+}
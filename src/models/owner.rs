@@ -1,12 +1,17 @@
 use diesel::pg::Pg;
 use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
 
 use crate::app::App;
 use crate::util::errors::{cargo_err, AppResult};
 
-use crate::models::{Crate, Team, User};
-use crate::schema::{crate_owners, users};
-use crate::sql::lower;
+use crate::models::{Crate, Rights, Team, User};
+use crate::schema::crate_owners;
 
 #[derive(Insertable, Associations, Identifiable, Debug, Clone, Copy)]
 #[belongs_to(Crate)]
@@ -20,6 +25,7 @@ pub struct CrateOwner {
     pub created_by: i32,
     pub owner_kind: i32,
     pub email_notifications: bool,
+    pub role: OwnerRole,
 }
 
 type BoxedQuery<'a> = crate_owners::BoxedQuery<'a, Pg, crate_owners::SqlType>;
@@ -37,6 +43,78 @@ impl CrateOwner {
     }
 }
 
+/// Role of an owner of a crate, deciding what they're allowed to do with it.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    FromSqlRow,
+    AsExpression,
+    Serialize,
+    Deserialize,
+)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+#[serde(rename_all = "kebab-case")]
+pub enum OwnerRole {
+    /// Can yank and unyank versions.
+    YankOnly = 0,
+    /// Can also publish new versions.
+    Publisher = 1,
+    /// Can also manage the owners of the crate.
+    Admin = 2,
+}
+
+impl OwnerRole {
+    /// The role given to new owners when none is requested. Teams can't manage owners, so they
+    /// are publishers by default.
+    pub fn default_for(owner: &Owner) -> Self {
+        match owner {
+            Owner::User(_) => OwnerRole::Admin,
+            Owner::Team(_) => OwnerRole::Publisher,
+        }
+    }
+
+    pub fn rights(self) -> Rights {
+        match self {
+            OwnerRole::YankOnly => Rights::Yank,
+            OwnerRole::Publisher => Rights::Publish,
+            OwnerRole::Admin => Rights::Full,
+        }
+    }
+}
+
+impl From<OwnerRole> for &'static str {
+    fn from(role: OwnerRole) -> Self {
+        match role {
+            OwnerRole::YankOnly => "yank-only",
+            OwnerRole::Publisher => "publisher",
+            OwnerRole::Admin => "admin",
+        }
+    }
+}
+
+impl FromSql<Integer, Pg> for OwnerRole {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(OwnerRole::YankOnly),
+            1 => Ok(OwnerRole::Publisher),
+            2 => Ok(OwnerRole::Admin),
+            n => Err(format!("unknown owner role: {n}").into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for OwnerRole {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(u32)]
 pub enum OwnerKind {
@@ -69,11 +147,7 @@ impl Owner {
                 app, conn, name, req_user,
             )?))
         } else {
-            users::table
-                .filter(lower(users::gh_login).eq(name.to_lowercase()))
-                .filter(users::gh_id.ne(-1))
-                .order(users::gh_id.desc())
-                .first(conn)
+            User::find_by_login(conn, name)
                 .map(Owner::User)
                 .map_err(|_| cargo_err(&format_args!("could not find user with login `{}`", name)))
         }
@@ -1,27 +1,37 @@
 use diesel::prelude::*;
 
 use crate::app::App;
-use crate::util::errors::{cargo_err, AppResult, NotFound};
+use crate::github::GitHubClient;
+use crate::util::errors::{bad_request, cargo_err, AppResult, NotFound};
 
 use oauth2::AccessToken;
 
-use crate::models::{Crate, CrateOwner, Owner, OwnerKind, User};
+use crate::models::{
+    CachedTeamMembership, Crate, CrateOwner, Owner, OwnerKind, TeamMember, TeamRole, User,
+};
 use crate::schema::{crate_owners, teams};
 
-/// For now, just a Github Team. Can be upgraded to other teams
-/// later if desirable.
+/// Prefix of the login of registry-native teams, e.g. `registry:serde-maintainers`.
+pub const NATIVE_TEAM_PREFIX: &str = "registry";
+
+/// Maximum length of the name of a registry-native team, without the prefix.
+const MAX_NATIVE_TEAM_NAME_LENGTH: usize = 64;
+
+/// Either a Github Team, or a registry-native team whose members are
+/// managed through the API and stored in the `team_members` table.
 #[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
 pub struct Team {
     /// Unique table id
     pub id: i32,
-    /// "github:org:team"
+    /// "github:org:team" or "registry:team"
     /// An opaque unique ID, that was at one point parsed out to query Github.
     /// We only query membership with github using the github_id, though.
     /// This is the only name we should ever talk to Cargo about.
     pub login: String,
     /// The GitHub API works on team ID numbers. This can change, if a team
     /// is deleted and then recreated with the same name!!!
-    pub github_id: i32,
+    /// `None` for registry-native teams.
+    pub github_id: Option<i32>,
     /// Sugary goodness
     pub name: Option<String>,
     pub avatar: Option<String>,
@@ -104,9 +114,18 @@ impl Team {
                     req_user,
                 )
             }
+            // registry:serde-maintainers
+            NATIVE_TEAM_PREFIX => {
+                let team = Team::find_native(conn, login)?
+                    .ok_or_else(|| cargo_err(&format_args!("could not find the team {}", login)))?;
+                if !team.contains_user(app, conn, req_user)? {
+                    return Err(cargo_err("only members of a team can add it as an owner"));
+                }
+                Ok(team)
+            }
             _ => Err(cargo_err(
                 "unknown organization handler, \
-                 only 'github:org:team' is supported",
+                 only 'github:org:team' and 'registry:team' are supported",
             )),
         }
     }
@@ -170,18 +189,146 @@ impl Team {
         .map_err(Into::into)
     }
 
-    /// Phones home to Github to ask if this User is a member of the given team.
+    /// Creates a registry-native team named `registry:{name}`, with `creator` as its first
+    /// maintainer.
+    pub fn create_native(
+        conn: &PgConnection,
+        name: &str,
+        display_name: Option<&str>,
+        creator: &User,
+    ) -> AppResult<Self> {
+        use diesel::insert_into;
+
+        fn is_allowed_char(c: char) -> bool {
+            matches!(c, 'a'..='z' | '0'..='9' | '-' | '_')
+        }
+
+        if name.is_empty() || name.len() > MAX_NATIVE_TEAM_NAME_LENGTH {
+            return Err(bad_request(&format_args!(
+                "team names must be between 1 and {} characters long",
+                MAX_NATIVE_TEAM_NAME_LENGTH
+            )));
+        }
+        if let Some(c) = name.chars().find(|c| !is_allowed_char(*c)) {
+            return Err(bad_request(&format_args!(
+                "team names can only contain lowercase letters, numbers, `-` and `_`, not {}",
+                c
+            )));
+        }
+
+        let login = format!("{NATIVE_TEAM_PREFIX}:{name}");
+        conn.transaction(|| {
+            let team: Option<Team> = insert_into(teams::table)
+                .values((teams::login.eq(&login), teams::name.eq(display_name)))
+                .on_conflict_do_nothing()
+                .get_result(conn)
+                .optional()?;
+            let team =
+                team.ok_or_else(|| bad_request(&format_args!("team {} already exists", login)))?;
+
+            TeamMember::add(conn, team.id, creator.id, TeamRole::Maintainer)?;
+            Ok(team)
+        })
+    }
+
+    /// Returns the registry-native team with the given login, if it exists.
+    pub fn find_native(conn: &PgConnection, login: &str) -> QueryResult<Option<Self>> {
+        teams::table
+            .filter(teams::login.eq(login.to_lowercase()))
+            .filter(teams::github_id.is_null())
+            .first(conn)
+            .optional()
+    }
+
+    /// Whether this is a registry-native team rather than a GitHub team.
+    pub fn is_native(&self) -> bool {
+        self.github_id.is_none()
+    }
+
+    /// Returns whether the User is a member of the given team. Registry-native
+    /// teams are checked against the `team_members` table, GitHub teams by
+    /// phoning home to Github, unless the answer is cached.
     /// Note that we're assuming that the given user is the one interested in
     /// the answer. If this is not the case, then we could accidentally leak
     /// private membership information here.
-    pub fn contains_user(&self, app: &App, user: &User) -> AppResult<bool> {
-        match self.org_id {
-            Some(org_id) => team_with_gh_id_contains_user(app, org_id, self.github_id, user),
+    pub fn contains_user(&self, app: &App, conn: &PgConnection, user: &User) -> AppResult<bool> {
+        match (self.github_id, self.org_id) {
+            (None, _) => Ok(TeamMember::find(conn, self.id, user.id)?.is_some()),
+            (Some(github_id), Some(org_id)) => {
+                self.github_team_contains_user(app, conn, org_id, github_id, user)
+            }
             // This means we don't have an org_id on file for the `self` team. It much
             // probably was deleted from github by the time we backfilled the database.
             // Short-circuiting to false since a non-existent team cannot contain any
             // user
-            None => Ok(false),
+            (Some(_), None) => Ok(false),
+        }
+    }
+
+    /// Checks the membership of a user in a GitHub team through the
+    /// `cached_team_memberships` table, so that GitHub is asked at most once
+    /// per `TeamMembershipCacheConfig::ttl`. If GitHub can't be reached, an
+    /// expired result is used as long as it is younger than
+    /// `TeamMembershipCacheConfig::max_staleness`.
+    fn github_team_contains_user(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        org_id: i32,
+        github_id: i32,
+        user: &User,
+    ) -> AppResult<bool> {
+        let config = &app.config.team_membership_cache;
+        let metrics = &app.instance_metrics;
+
+        let cached = CachedTeamMembership::find(conn, self.id, user.id)?;
+        if let Some(cached) = &cached {
+            if cached.age() < config.ttl {
+                metrics.team_membership_cache_hits.inc();
+                self.mark_membership_used(conn, user);
+                return Ok(cached.is_member);
+            }
+        }
+        metrics.team_membership_cache_misses.inc();
+
+        match team_with_gh_id_contains_user(&*app.github, org_id, github_id, user) {
+            Ok(is_member) => {
+                // The connection may be a read-only replica, in which case the
+                // result is just not cached. The result is recorded in a savepoint,
+                // so that a failure doesn't abort the transaction of the request.
+                let recorded = conn.transaction(|| {
+                    CachedTeamMembership::record(conn, self.id, user.id, is_member)?;
+                    CachedTeamMembership::mark_used(conn, self.id, user.id)
+                });
+                if let Err(error) = recorded {
+                    warn!("Could not cache the membership of {}: {error}", self.login);
+                }
+                Ok(is_member)
+            }
+            Err(error) => match cached {
+                Some(cached) if cached.age() < config.max_staleness => {
+                    warn!(
+                        "Using an expired membership of {}, GitHub is unreachable: {error}",
+                        self.login
+                    );
+                    metrics.team_membership_cache_stale_hits.inc();
+                    self.mark_membership_used(conn, user);
+                    Ok(cached.is_member)
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    /// Records that the cached membership of `user` was used, so that it is kept and refreshed.
+    /// Like recording the membership, this is done in a savepoint and failures are ignored.
+    fn mark_membership_used(&self, conn: &PgConnection, user: &User) {
+        let marked = conn.transaction(|| CachedTeamMembership::mark_used(conn, self.id, user.id));
+        if let Err(error) = marked {
+            warn!(
+                "Could not mark the membership of {} as used: {error}",
+                self.login
+            );
         }
     }
 
@@ -200,8 +347,10 @@ impl Team {
 }
 
 fn can_add_team(app: &App, org_id: i32, team_id: i32, user: &User) -> AppResult<bool> {
-    Ok(team_with_gh_id_contains_user(app, org_id, team_id, user)?
-        || is_gh_org_owner(app, org_id, user)?)
+    Ok(
+        team_with_gh_id_contains_user(&*app.github, org_id, team_id, user)?
+            || is_gh_org_owner(app, org_id, user)?,
+    )
 }
 
 fn is_gh_org_owner(app: &App, org_id: i32, user: &User) -> AppResult<bool> {
@@ -213,8 +362,8 @@ fn is_gh_org_owner(app: &App, org_id: i32, user: &User) -> AppResult<bool> {
     }
 }
 
-fn team_with_gh_id_contains_user(
-    app: &App,
+pub(crate) fn team_with_gh_id_contains_user(
+    github: &dyn GitHubClient,
     github_org_id: i32,
     github_team_id: i32,
     user: &User,
@@ -224,10 +373,7 @@ fn team_with_gh_id_contains_user(
 
     let token = AccessToken::new(user.gh_access_token.clone());
     let membership =
-        match app
-            .github
-            .team_membership(github_org_id, github_team_id, &user.gh_login, &token)
-        {
+        match github.team_membership(github_org_id, github_team_id, &user.gh_login, &token) {
             // Officially how `false` is returned
             Err(ref e) if e.is::<NotFound>() => return Ok(false),
             x => x?,
@@ -0,0 +1,114 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::models::{Team, User};
+use crate::schema::{team_members, users};
+
+/// Role of a user in a registry-native team.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    FromSqlRow,
+    AsExpression,
+    Serialize,
+    Deserialize,
+)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+#[serde(rename_all = "lowercase")]
+pub enum TeamRole {
+    /// Members get publish rights on the crates owned by the team.
+    Member = 0,
+    /// Maintainers can also manage the members of the team.
+    Maintainer = 1,
+}
+
+impl FromSql<Integer, Pg> for TeamRole {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(TeamRole::Member),
+            1 => Ok(TeamRole::Maintainer),
+            n => Err(format!("unknown team role: {n}").into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for TeamRole {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// The membership of a user in a registry-native team.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[belongs_to(Team)]
+#[belongs_to(User)]
+#[primary_key(team_id, user_id)]
+#[table_name = "team_members"]
+pub struct TeamMember {
+    pub team_id: i32,
+    pub user_id: i32,
+    pub role: TeamRole,
+    pub created_at: NaiveDateTime,
+}
+
+impl TeamMember {
+    pub fn find(conn: &PgConnection, team_id: i32, user_id: i32) -> QueryResult<Option<Self>> {
+        team_members::table
+            .find((team_id, user_id))
+            .first(conn)
+            .optional()
+    }
+
+    /// Returns the members of a team along with their user, maintainers first.
+    pub fn for_team(conn: &PgConnection, team_id: i32) -> QueryResult<Vec<(Self, User)>> {
+        team_members::table
+            .inner_join(users::table)
+            .filter(team_members::team_id.eq(team_id))
+            .order((team_members::role.desc(), users::gh_login))
+            .load(conn)
+    }
+
+    /// Adds the user to the team, or changes their role if they're already a member.
+    pub fn add(
+        conn: &PgConnection,
+        team_id: i32,
+        user_id: i32,
+        role: TeamRole,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(team_members::table)
+            .values((
+                team_members::team_id.eq(team_id),
+                team_members::user_id.eq(user_id),
+                team_members::role.eq(role),
+            ))
+            .on_conflict((team_members::team_id, team_members::user_id))
+            .do_update()
+            .set(team_members::role.eq(role))
+            .get_result(conn)
+    }
+
+    pub fn remove(conn: &PgConnection, team_id: i32, user_id: i32) -> QueryResult<usize> {
+        diesel::delete(team_members::table.find((team_id, user_id))).execute(conn)
+    }
+
+    pub fn maintainer_count(conn: &PgConnection, team_id: i32) -> QueryResult<i64> {
+        team_members::table
+            .filter(team_members::team_id.eq(team_id))
+            .filter(team_members::role.eq(TeamRole::Maintainer))
+            .count()
+            .get_result(conn)
+    }
+}
@@ -6,8 +6,11 @@ use crate::app::App;
 use crate::email::Emails;
 use crate::util::errors::AppResult;
 
-use crate::models::{ApiToken, Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, Rights};
+use crate::models::{
+    ApiToken, Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, OwnerRole, Rights,
+};
 use crate::schema::{crate_owners, emails, users};
+use crate::sql::lower;
 
 /// The model representing a row in the `users` database table.
 #[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, AsChangeset, Associations)]
@@ -114,6 +117,29 @@ impl User {
         users::table.find(id).first(conn)
     }
 
+    /// Queries the database for a user with a certain login, ignoring case. Users whose GitHub
+    /// ID could not be backfilled are ignored, and the most recent GitHub user wins if several
+    /// GitHub users share the same login.
+    ///
+    /// Users created through another identity provider (with a `gh_id` of `0`) are only matched
+    /// if no GitHub user has the login. Their logins are unique, so there is nothing to break.
+    pub fn find_by_login(conn: &PgConnection, login: &str) -> QueryResult<User> {
+        let github_user = users::table
+            .filter(lower(users::gh_login).eq(login.to_lowercase()))
+            .filter(users::gh_id.gt(0))
+            .order(users::gh_id.desc())
+            .first(conn)
+            .optional()?;
+
+        match github_user {
+            Some(user) => Ok(user),
+            None => users::table
+                .filter(lower(users::gh_login).eq(login.to_lowercase()))
+                .filter(users::gh_id.eq(0))
+                .first(conn),
+        }
+    }
+
     /// Queries the database for a user with a certain `api_token` value.
     pub fn find_by_api_token(conn: &PgConnection, token: &str) -> AppResult<User> {
         let api_token = ApiToken::find_by_api_token(conn, token)?;
@@ -133,33 +159,59 @@ impl User {
         Ok(users.collect())
     }
 
-    /// Given this set of owners, determines the strongest rights the
-    /// user has.
+    /// Returns whether the user is a crates.io administrator, as configured by
+    /// `GH_ADMIN_USER_IDS`. Users that didn't log in with GitHub are never
+    /// administrators.
+    pub fn is_admin(&self, app: &App) -> bool {
+        self.gh_id > 0 && app.config.gh_admin_user_ids.contains(&self.gh_id)
+    }
+
+    /// Given this set of owners and their roles, determines the strongest
+    /// rights the user has.
     ///
-    /// Shortcircuits on `Full` because you can't beat it. In practice we'll always
-    /// see `[user, user, user, ..., team, team, team]`, so we could shortcircuit on
-    /// `Publish` as well, but this is a non-obvious invariant so we don't bother.
-    /// Sweet free optimization if teams are proving burdensome to check.
-    /// More than one team isn't really expected, though.
-    pub fn rights(&self, app: &App, owners: &[Owner]) -> AppResult<Rights> {
+    /// The rights of an owner are given by their role, but teams never get more
+    /// than `Publish`. Shortcircuits on `Full` because you can't beat it, and
+    /// teams are only checked if they could improve on the rights found so far,
+    /// as checking them may require phoning home to GitHub.
+    pub fn rights(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        owners: &[(Owner, OwnerRole)],
+    ) -> AppResult<Rights> {
         let mut best = Rights::None;
-        for owner in owners {
+        for (owner, role) in owners {
             match *owner {
                 Owner::User(ref other_user) => {
                     if other_user.id == self.id {
-                        return Ok(Rights::Full);
+                        best = best.max(role.rights());
                     }
                 }
                 Owner::Team(ref team) => {
-                    if team.contains_user(app, self)? {
-                        best = Rights::Publish;
+                    let rights = role.rights().min(Rights::Publish);
+                    if rights > best && team.contains_user(app, conn, self)? {
+                        best = rights;
                     }
                 }
             }
+            if best == Rights::Full {
+                break;
+            }
         }
         Ok(best)
     }
 
+    /// Finds the user that verified the given email address, ignoring case.
+    pub fn find_by_verified_email(conn: &PgConnection, email: &str) -> QueryResult<Option<User>> {
+        users::table
+            .inner_join(emails::table)
+            .filter(lower(emails::email).eq(email.to_lowercase()))
+            .filter(emails::verified.eq(true))
+            .select(users::all_columns)
+            .first(conn)
+            .optional()
+    }
+
     /// Queries the database for the verified emails
     /// belonging to a given user
     pub fn verified_email(&self, conn: &PgConnection) -> QueryResult<Option<String>> {
@@ -23,6 +23,13 @@ pub struct Version {
     pub license: Option<String>,
     pub crate_size: Option<i32>,
     pub published_by: Option<i32>,
+    pub unpacked_size: Option<i64>,
+    pub file_count: Option<i32>,
+    pub largest_file_path: Option<String>,
+    pub largest_file_size: Option<i64>,
+    /// Hex encoded SHA256 of the crate file. It is `None` for versions that were published
+    /// before checksums were recorded, until `crates-admin backfill-checksums` has run.
+    pub checksum: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -36,6 +43,17 @@ pub struct NewVersion {
     published_by: i32,
 }
 
+/// Statistics about the files inside the `.crate` archive of a version.
+#[derive(AsChangeset, Debug, Default, PartialEq, Eq)]
+#[table_name = "versions"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct VersionFileStats {
+    pub unpacked_size: i64,
+    pub file_count: i32,
+    pub largest_file_path: Option<String>,
+    pub largest_file_size: Option<i64>,
+}
+
 /// The highest version (semver order) and the most recently updated version.
 /// Typically used for a single crate.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -110,6 +128,14 @@ impl Version {
             .execute(conn)
     }
 
+    pub fn record_file_stats(
+        &self,
+        conn: &PgConnection,
+        stats: &VersionFileStats,
+    ) -> QueryResult<usize> {
+        diesel::update(self).set(stats).execute(conn)
+    }
+
     /// Gets the User who ran `cargo publish` for this version, if recorded.
     /// Not for use when you have a group of versions you need the publishers for.
     pub fn published_by(&self, conn: &PgConnection) -> Option<User> {
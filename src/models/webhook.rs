@@ -0,0 +1,306 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use url::Url;
+
+use crate::models::Crate;
+use crate::schema::{crate_webhooks, webhook_deliveries};
+use crate::util::rfc3339;
+
+/// An HTTP endpoint that is notified about changes to a crate.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(Crate)]
+#[table_name = "crate_webhooks"]
+pub struct CrateWebhook {
+    pub id: i32,
+    pub crate_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "crate_webhooks"]
+pub struct NewCrateWebhook<'a> {
+    pub crate_id: i32,
+    pub url: &'a str,
+    pub secret: &'a str,
+    pub created_by: i32,
+}
+
+impl NewCrateWebhook<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<CrateWebhook> {
+        diesel::insert_into(crate_webhooks::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+/// A single attempt to notify a webhook about an event, including the outcome
+/// of the most recent HTTP request.
+#[derive(Queryable, Identifiable, Associations, Debug, Clone)]
+#[belongs_to(CrateWebhook, foreign_key = "webhook_id")]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl WebhookDelivery {
+    /// Creates a pending delivery of `payload` for every webhook of the given
+    /// crate and returns the ids of the new deliveries.
+    pub fn create_for_crate(
+        conn: &PgConnection,
+        crate_id: i32,
+        payload: &WebhookPayload,
+    ) -> QueryResult<Vec<i64>> {
+        let webhook_ids: Vec<i32> = crate_webhooks::table
+            .filter(crate_webhooks::crate_id.eq(crate_id))
+            .select(crate_webhooks::id)
+            .load(conn)?;
+
+        if webhook_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let event = payload.event.as_str();
+        let payload = serde_json::to_value(payload)
+            .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+
+        let deliveries = webhook_ids
+            .into_iter()
+            .map(|webhook_id| {
+                (
+                    webhook_deliveries::webhook_id.eq(webhook_id),
+                    webhook_deliveries::event.eq(event),
+                    webhook_deliveries::payload.eq(&payload),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .returning(webhook_deliveries::id)
+            .get_results(conn)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    Publish,
+    Yank,
+    Unyank,
+    OwnerAdd,
+    OwnerRemove,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::Publish => "publish",
+            WebhookEvent::Yank => "yank",
+            WebhookEvent::Unyank => "unyank",
+            WebhookEvent::OwnerAdd => "owner_add",
+            WebhookEvent::OwnerRemove => "owner_remove",
+        }
+    }
+}
+
+/// The JSON body that is sent to webhook endpoints.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Login of the user that triggered the event.
+    pub user: String,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, krate: &str, user: &str) -> Self {
+        Self {
+            event,
+            krate: krate.to_string(),
+            version: None,
+            owner: None,
+            user: user.to_string(),
+            time: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_string());
+        self
+    }
+}
+
+/// Checks that `url` is an `https` URL whose host is not a non-public IP address.
+///
+/// Webhooks are requested from the background worker, so they must not be able to reach the
+/// loopback interface, private networks or cloud metadata endpoints. Hosts given as a domain name
+/// are not resolved here, to keep DNS lookups out of request handlers. They're checked by
+/// `resolve_webhook_url` right before every delivery instead, since DNS records can change anyway.
+pub fn check_webhook_url(url: &str) -> Result<Url, String> {
+    let url = Url::parse(url).map_err(|_| "url must be a valid https URL".to_string())?;
+    if url.scheme() != "https" {
+        return Err("url must be a valid https URL".into());
+    }
+    let host = webhook_host(&url).ok_or_else(|| "url must have a host".to_string())?;
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if !is_public_address(ip) {
+            return Err(format!(
+                "the host `{host}` resolves to a non-public address"
+            ));
+        }
+    }
+    Ok(url)
+}
+
+/// The reasons a webhook can't be delivered to the addresses its host resolves to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WebhookResolveError {
+    /// The host could not be resolved, which may be temporary.
+    Unresolved(String),
+    /// The host resolves to a non-public address, which is never delivered to.
+    NonPublic(String),
+}
+
+/// Resolves the host of a webhook URL that passed `check_webhook_url`, and checks that it only
+/// resolves to public addresses. This does a blocking DNS lookup, so it's only done by the
+/// background worker.
+pub fn resolve_webhook_url(url: &Url) -> Result<Vec<SocketAddr>, WebhookResolveError> {
+    let host = webhook_host(url).unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let unresolved =
+        || WebhookResolveError::Unresolved(format!("could not resolve the host `{host}`"));
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|_| unresolved())?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(unresolved());
+    }
+    if addrs.iter().any(|addr| !is_public_address(addr.ip())) {
+        return Err(WebhookResolveError::NonPublic(format!(
+            "the host `{host}` resolves to a non-public address"
+        )));
+    }
+
+    Ok(addrs)
+}
+
+/// Returns the host of `url`, without the brackets `host_str` keeps around IPv6 addresses.
+fn webhook_host(url: &Url) -> Option<&str> {
+    let host = url.host_str()?;
+    Some(host.trim_start_matches('[').trim_end_matches(']'))
+}
+
+/// Returns `false` for loopback, private, link-local (including the `169.254.169.254` metadata
+/// endpoint), shared, unspecified, broadcast and multicast addresses.
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 0.0.0.0/8 and the shared address space 100.64.0.0/10
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            // IPv4-mapped and IPv4-compatible addresses, including `::1` and `::`
+            if let Some(ip) = ip.to_ipv4() {
+                return is_public_address(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_webhook_url, is_public_address, resolve_webhook_url, WebhookResolveError};
+    use url::Url;
+
+    #[test]
+    fn public_addresses() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::ffff:127.0.0.1",
+            "fd00:ec2::254",
+            "fe80::1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn webhook_urls() {
+        assert!(check_webhook_url("https://93.184.216.34/hook").is_ok());
+        assert!(check_webhook_url("https://example.com/hook").is_ok());
+        assert!(check_webhook_url("http://93.184.216.34/hook").is_err());
+        assert!(check_webhook_url("ftp://93.184.216.34/hook").is_err());
+        assert!(check_webhook_url("https://127.0.0.1/hook").is_err());
+        assert!(check_webhook_url("https://[::1]:8443/hook").is_err());
+        assert!(check_webhook_url("https://169.254.169.254/latest/meta-data").is_err());
+    }
+
+    #[test]
+    fn resolve_webhook_urls() {
+        let resolve = |url| resolve_webhook_url(&Url::parse(url).unwrap());
+        assert_eq!(
+            resolve("https://93.184.216.34/hook"),
+            Ok(vec!["93.184.216.34:443".parse().unwrap()])
+        );
+        assert_eq!(
+            resolve("https://[::1]:8443/hook"),
+            Err(WebhookResolveError::NonPublic(
+                "the host `::1` resolves to a non-public address".into()
+            ))
+        );
+        assert!(matches!(
+            resolve("https://does-not-exist.invalid/hook"),
+            Err(WebhookResolveError::Unresolved(_))
+        ));
+    }
+}
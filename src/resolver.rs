@@ -0,0 +1,687 @@
+//! Resolution of the transitive dependencies of a version, using only the
+//! data stored in the registry.
+//!
+//! This is a simplified version of what cargo does: every dependency is
+//! resolved to the newest version matching its requirement, and features are
+//! unified per resolved version. There is no backtracking, and no lockfile of
+//! the consumer is taken into account, so the result describes what a fresh
+//! `cargo generate-lockfile` would most likely pick.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use cfg_expr::targets::{get_builtin_target_by_triple, TargetInfo};
+use cfg_expr::{Expression, Predicate};
+use diesel::prelude::*;
+
+use crate::models::{Dependency, DependencyKind};
+use crate::schema::{crates, dependencies, versions};
+use crate::util::errors::{cargo_err, AppResult};
+
+/// The maximum number of packages in a resolved graph.
+const MAX_PACKAGES: usize = 2000;
+
+/// A published version that dependencies can be resolved to.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub version_id: i32,
+    pub name: String,
+    pub num: semver::Version,
+    pub yanked: bool,
+    pub license: Option<String>,
+    pub features: BTreeMap<String, Vec<String>>,
+}
+
+/// The source of the versions and dependencies used during resolution.
+pub trait Registry {
+    /// Returns all versions of a crate.
+    fn versions(&mut self, crate_id: i32) -> AppResult<&[Candidate]>;
+
+    /// Returns the dependencies of a version, together with the names of the
+    /// depended on crates.
+    fn dependencies(&mut self, version_id: i32) -> AppResult<&[(Dependency, String)]>;
+}
+
+/// A [`Registry`] reading from the database, caching the versions of every
+/// crate and the dependencies of every version it has seen.
+pub struct DatabaseRegistry<'a> {
+    conn: &'a PgConnection,
+    versions: HashMap<i32, Vec<Candidate>>,
+    dependencies: HashMap<i32, Vec<(Dependency, String)>>,
+}
+
+impl<'a> DatabaseRegistry<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        Self {
+            conn,
+            versions: HashMap::new(),
+            dependencies: HashMap::new(),
+        }
+    }
+}
+
+impl Registry for DatabaseRegistry<'_> {
+    fn versions(&mut self, crate_id: i32) -> AppResult<&[Candidate]> {
+        if !self.versions.contains_key(&crate_id) {
+            let rows: Vec<(i32, String, String, bool, Option<String>, serde_json::Value)> =
+                versions::table
+                    .inner_join(crates::table)
+                    .filter(versions::crate_id.eq(crate_id))
+                    .select((
+                        versions::id,
+                        crates::name,
+                        versions::num,
+                        versions::yanked,
+                        versions::license,
+                        versions::features,
+                    ))
+                    .load(self.conn)?;
+
+            let candidates = rows
+                .into_iter()
+                .filter_map(|(version_id, name, num, yanked, license, features)| {
+                    Some(Candidate {
+                        version_id,
+                        name,
+                        num: semver::Version::parse(&num).ok()?,
+                        yanked,
+                        license,
+                        features: serde_json::from_value(features).unwrap_or_default(),
+                    })
+                })
+                .collect();
+            self.versions.insert(crate_id, candidates);
+        }
+
+        Ok(&self.versions[&crate_id])
+    }
+
+    fn dependencies(&mut self, version_id: i32) -> AppResult<&[(Dependency, String)]> {
+        if !self.dependencies.contains_key(&version_id) {
+            let dependencies = dependencies::table
+                .inner_join(crates::table)
+                .filter(dependencies::version_id.eq(version_id))
+                .select((dependencies::all_columns, crates::name))
+                .load(self.conn)?;
+            self.dependencies.insert(version_id, dependencies);
+        }
+
+        Ok(&self.dependencies[&version_id])
+    }
+}
+
+/// A compilation target that `target` filters of dependencies are evaluated
+/// against.
+#[derive(Debug, Clone)]
+pub struct Target {
+    triple: String,
+    info: &'static TargetInfo,
+}
+
+impl Target {
+    /// Returns `None` if the target triple is not known.
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        let info = get_builtin_target_by_triple(triple)?;
+        Some(Self {
+            triple: triple.to_string(),
+            info,
+        })
+    }
+
+    /// Returns whether a dependency with the given `target` field, either a
+    /// target triple or a `cfg()` expression, applies to this target.
+    fn matches(&self, dep_target: &str) -> bool {
+        if !dep_target.starts_with("cfg(") {
+            return dep_target == self.triple;
+        }
+        match Expression::parse(dep_target) {
+            Ok(expression) => expression.eval(|predicate| match predicate {
+                Predicate::Target(target) => target.matches(self.info),
+                _ => false,
+            }),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolveOptions {
+    /// Features to enable on the root version, in the same syntax as the
+    /// `--features` flag of cargo.
+    pub features: Vec<String>,
+    pub default_features: bool,
+    /// If set, dependencies that don't apply to this target are skipped.
+    /// Otherwise dependencies for all targets are included.
+    pub target: Option<Target>,
+    /// The kinds of dependencies of the root version to include. Dev
+    /// dependencies of other versions are never included, and build
+    /// dependencies only if they are requested here.
+    pub kinds: Vec<DependencyKind>,
+    /// Whether yanked versions can be picked.
+    pub include_yanked: bool,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        Self {
+            features: vec![],
+            default_features: true,
+            target: None,
+            kinds: vec![DependencyKind::Normal, DependencyKind::Build],
+            include_yanked: false,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Resolution {
+    /// All versions in the graph, including the root, sorted by name and
+    /// version.
+    pub packages: Vec<ResolvedPackage>,
+    /// Dependencies for which no matching version exists.
+    pub unresolved: Vec<UnresolvedDependency>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub version: String,
+    pub yanked: bool,
+    pub license: Option<String>,
+    /// The enabled features, including the ones enabled by other packages.
+    pub features: Vec<String>,
+    /// The dependencies of this package, as `"name version"` strings like in
+    /// `Cargo.lock`.
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnresolvedDependency {
+    pub name: String,
+    pub req: String,
+    pub required_by: String,
+}
+
+type PackageId = (String, semver::Version);
+
+struct Node {
+    candidate: Candidate,
+    requested_features: BTreeSet<String>,
+    enabled_features: BTreeSet<String>,
+    dependencies: BTreeSet<PackageId>,
+}
+
+/// The features and optional dependencies enabled in a single version.
+#[derive(Default)]
+struct Activation {
+    features: BTreeSet<String>,
+    optional_dependencies: HashSet<String>,
+    dependency_features: HashMap<String, BTreeSet<String>>,
+}
+
+impl Activation {
+    /// Expands the requested features using the feature table of the version.
+    fn new(candidate: &Candidate, requested: &BTreeSet<String>, optional: &HashSet<&str>) -> Self {
+        let mut activation = Activation::default();
+        let mut weak = vec![];
+
+        let mut stack = requested.iter().cloned().collect::<Vec<_>>();
+        while let Some(entry) = stack.pop() {
+            if let Some(dep) = entry.strip_prefix("dep:") {
+                activation.optional_dependencies.insert(dep.to_string());
+            } else if let Some((dep, feature)) = entry.split_once('/') {
+                if let Some(dep) = dep.strip_suffix('?') {
+                    weak.push((dep.to_string(), feature.to_string()));
+                } else {
+                    activation.optional_dependencies.insert(dep.to_string());
+                    activation.enable_dependency_feature(dep, feature);
+                }
+            } else if activation.features.insert(entry.clone()) {
+                match candidate.features.get(&entry) {
+                    Some(entries) => stack.extend(entries.iter().cloned()),
+                    // Optional dependencies implicitly define a feature of
+                    // the same name
+                    None if optional.contains(entry.as_str()) => {
+                        activation.optional_dependencies.insert(entry);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        // `dep?/feature` only enables the feature if the dependency is
+        // enabled by something else
+        for (dep, feature) in weak {
+            if !optional.contains(dep.as_str()) || activation.optional_dependencies.contains(&dep) {
+                activation.enable_dependency_feature(&dep, &feature);
+            }
+        }
+
+        activation
+    }
+
+    fn enable_dependency_feature(&mut self, dep: &str, feature: &str) {
+        self.dependency_features
+            .entry(dep.to_string())
+            .or_default()
+            .insert(feature.to_string());
+    }
+}
+
+/// Resolves the transitive dependencies of `root`.
+pub fn resolve(
+    registry: &mut dyn Registry,
+    root: Candidate,
+    options: &ResolveOptions,
+) -> AppResult<Resolution> {
+    let root_id = (root.name.clone(), root.num.clone());
+
+    let mut requested_features = options.features.iter().cloned().collect::<BTreeSet<_>>();
+    if options.default_features {
+        requested_features.insert("default".to_string());
+    }
+
+    let mut nodes = BTreeMap::new();
+    nodes.insert(
+        root_id.clone(),
+        Node {
+            candidate: root,
+            requested_features,
+            enabled_features: BTreeSet::new(),
+            dependencies: BTreeSet::new(),
+        },
+    );
+
+    let mut unresolved = BTreeSet::new();
+    let mut queue = VecDeque::from([root_id.clone()]);
+    while let Some(id) = queue.pop_front() {
+        let is_root = id == root_id;
+        let node = &nodes[&id];
+        let candidate = node.candidate.clone();
+        let requested_features = node.requested_features.clone();
+
+        let dependencies = registry
+            .dependencies(candidate.version_id)?
+            .iter()
+            .filter(|(dep, _)| match dep.kind {
+                _ if is_root => options.kinds.contains(&dep.kind),
+                DependencyKind::Normal => true,
+                DependencyKind::Build => options.kinds.contains(&DependencyKind::Build),
+                DependencyKind::Dev => false,
+            })
+            .filter(|(dep, _)| match (&options.target, &dep.target) {
+                (Some(target), Some(dep_target)) => target.matches(dep_target),
+                _ => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // Features refer to dependencies by the name they have in the
+        // `Cargo.toml` of the depending crate, which differs from the crate
+        // name if the dependency was renamed.
+        let optional = dependencies
+            .iter()
+            .filter(|(dep, _)| dep.optional)
+            .map(|(dep, name)| dependency_key(dep, name))
+            .collect::<HashSet<_>>();
+        let activation = Activation::new(&candidate, &requested_features, &optional);
+
+        let mut edges = BTreeSet::new();
+        for (dep, name) in &dependencies {
+            let key = dependency_key(dep, name);
+            if dep.optional && !activation.optional_dependencies.contains(key) {
+                continue;
+            }
+
+            let resolved = match pick_version(registry, dep, options.include_yanked)? {
+                Some(resolved) => resolved,
+                None => {
+                    unresolved.insert(UnresolvedDependency {
+                        name: name.clone(),
+                        req: dep.req.clone(),
+                        required_by: format!("{} {}", candidate.name, candidate.num),
+                    });
+                    continue;
+                }
+            };
+
+            let mut features = dep.features.iter().cloned().collect::<BTreeSet<_>>();
+            if dep.default_features {
+                features.insert("default".to_string());
+            }
+            if let Some(extra) = activation.dependency_features.get(key) {
+                features.extend(extra.iter().cloned());
+            }
+
+            let dep_id = (resolved.name.clone(), resolved.num.clone());
+            edges.insert(dep_id.clone());
+
+            match nodes.get_mut(&dep_id) {
+                Some(existing) => {
+                    if !features.is_subset(&existing.requested_features) {
+                        existing.requested_features.extend(features);
+                        queue.push_back(dep_id);
+                    }
+                }
+                None => {
+                    if nodes.len() >= MAX_PACKAGES {
+                        return Err(cargo_err(&format_args!(
+                            "the dependency graph has more than {MAX_PACKAGES} packages"
+                        )));
+                    }
+                    nodes.insert(
+                        dep_id.clone(),
+                        Node {
+                            candidate: resolved,
+                            requested_features: features,
+                            enabled_features: BTreeSet::new(),
+                            dependencies: BTreeSet::new(),
+                        },
+                    );
+                    queue.push_back(dep_id);
+                }
+            }
+        }
+
+        let node = nodes.get_mut(&id).expect("nodes are never removed");
+        node.enabled_features = activation
+            .features
+            .into_iter()
+            .filter(|feature| node.candidate.features.contains_key(feature))
+            .collect();
+        node.dependencies.extend(edges);
+    }
+
+    let packages = nodes
+        .into_values()
+        .map(|node| ResolvedPackage {
+            name: node.candidate.name,
+            version: node.candidate.num.to_string(),
+            yanked: node.candidate.yanked,
+            license: node.candidate.license,
+            features: node.enabled_features.into_iter().collect(),
+            dependencies: node
+                .dependencies
+                .into_iter()
+                .map(|(name, num)| format!("{name} {num}"))
+                .collect(),
+        })
+        .collect();
+
+    Ok(Resolution {
+        packages,
+        unresolved: unresolved.into_iter().collect(),
+    })
+}
+
+/// Returns the name that features of the depending version use to refer to
+/// the dependency.
+fn dependency_key<'a>(dep: &'a Dependency, crate_name: &'a str) -> &'a str {
+    dep.explicit_name.as_deref().unwrap_or(crate_name)
+}
+
+/// Returns the newest version matching the requirement of the dependency.
+fn pick_version(
+    registry: &mut dyn Registry,
+    dep: &Dependency,
+    include_yanked: bool,
+) -> AppResult<Option<Candidate>> {
+    let req = match semver::VersionReq::parse(&dep.req) {
+        Ok(req) => req,
+        Err(_) => return Ok(None),
+    };
+
+    Ok(registry
+        .versions(dep.crate_id)?
+        .iter()
+        .filter(|candidate| include_yanked || !candidate.yanked)
+        .filter(|candidate| req.matches(&candidate.num))
+        .max_by(|a, b| a.num.cmp(&b.num))
+        .cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TestRegistry {
+        versions: HashMap<i32, Vec<Candidate>>,
+        dependencies: HashMap<i32, Vec<(Dependency, String)>>,
+        crate_ids: HashMap<String, i32>,
+        next_id: i32,
+    }
+
+    impl TestRegistry {
+        fn crate_id(&mut self, name: &str) -> i32 {
+            let next_id = self.crate_ids.len() as i32 + 1;
+            *self.crate_ids.entry(name.to_string()).or_insert(next_id)
+        }
+
+        fn add(&mut self, name: &str, num: &str, features: &[(&str, &[&str])]) -> Candidate {
+            self.next_id += 1;
+            let crate_id = self.crate_id(name);
+            let candidate = Candidate {
+                version_id: self.next_id,
+                name: name.to_string(),
+                num: semver::Version::parse(num).unwrap(),
+                yanked: false,
+                license: Some("MIT".to_string()),
+                features: features
+                    .iter()
+                    .map(|(name, entries)| {
+                        let entries = entries.iter().map(ToString::to_string).collect();
+                        (name.to_string(), entries)
+                    })
+                    .collect(),
+            };
+            self.versions
+                .entry(crate_id)
+                .or_default()
+                .push(candidate.clone());
+            candidate
+        }
+
+        fn depend(
+            &mut self,
+            from: &Candidate,
+            name: &str,
+            req: &str,
+            customize: fn(&mut Dependency),
+        ) {
+            self.next_id += 1;
+            let mut dep = Dependency {
+                id: self.next_id,
+                version_id: from.version_id,
+                crate_id: self.crate_id(name),
+                req: req.to_string(),
+                optional: false,
+                default_features: true,
+                features: vec![],
+                target: None,
+                kind: DependencyKind::Normal,
+                explicit_name: None,
+            };
+            customize(&mut dep);
+            self.dependencies
+                .entry(from.version_id)
+                .or_default()
+                .push((dep, name.to_string()));
+        }
+    }
+
+    impl Registry for TestRegistry {
+        fn versions(&mut self, crate_id: i32) -> AppResult<&[Candidate]> {
+            Ok(self.versions.get(&crate_id).map_or(&[], Vec::as_slice))
+        }
+
+        fn dependencies(&mut self, version_id: i32) -> AppResult<&[(Dependency, String)]> {
+            Ok(self
+                .dependencies
+                .get(&version_id)
+                .map_or(&[], Vec::as_slice))
+        }
+    }
+
+    fn package_ids(resolution: &Resolution) -> Vec<String> {
+        resolution
+            .packages
+            .iter()
+            .map(|package| format!("{} {}", package.name, package.version))
+            .collect()
+    }
+
+    #[test]
+    fn picks_newest_matching_non_yanked_versions() {
+        let mut registry = TestRegistry::default();
+        let root = registry.add("root", "1.0.0", &[]);
+        let a1 = registry.add("a", "1.0.0", &[]);
+        registry.add("a", "1.1.0", &[]);
+        registry.add("a", "2.0.0", &[]);
+        registry.versions.get_mut(&registry.crate_ids["a"]).unwrap()[1].yanked = true;
+        registry.add("b", "0.1.0", &[]);
+        registry.depend(&root, "a", "^1", |_| {});
+        registry.depend(&a1, "b", "^0.1", |_| {});
+        registry.depend(&root, "missing", "^1", |_| {});
+
+        let resolution = resolve(&mut registry, root.clone(), &Default::default()).unwrap();
+        assert_eq!(
+            package_ids(&resolution),
+            vec!["a 1.0.0", "b 0.1.0", "root 1.0.0"]
+        );
+        assert_eq!(resolution.packages[2].dependencies, vec!["a 1.0.0"]);
+        assert_eq!(
+            resolution.unresolved,
+            vec![UnresolvedDependency {
+                name: "missing".to_string(),
+                req: "^1".to_string(),
+                required_by: "root 1.0.0".to_string(),
+            }]
+        );
+
+        let options = ResolveOptions {
+            include_yanked: true,
+            ..Default::default()
+        };
+        let resolution = resolve(&mut registry, root, &options).unwrap();
+        assert_eq!(package_ids(&resolution), vec!["a 1.1.0", "root 1.0.0"]);
+    }
+
+    #[test]
+    fn features_and_optional_dependencies() {
+        let mut registry = TestRegistry::default();
+        let root = registry.add(
+            "root",
+            "1.0.0",
+            &[
+                ("default", &["std"]),
+                ("std", &[]),
+                ("json", &["serde/derive", "dep:serde_json"]),
+            ],
+        );
+        registry.add("serde", "1.0.0", &[("derive", &[]), ("default", &[])]);
+        registry.add("serde_json", "1.0.0", &[]);
+        registry.add("rand", "0.8.0", &[]);
+        registry.depend(&root, "serde", "^1", |dep| dep.default_features = false);
+        registry.depend(&root, "serde_json", "^1", |dep| dep.optional = true);
+        registry.depend(&root, "rand", "^0.8", |dep| dep.optional = true);
+
+        let resolution = resolve(&mut registry, root.clone(), &Default::default()).unwrap();
+        assert_eq!(package_ids(&resolution), vec!["root 1.0.0", "serde 1.0.0"]);
+        assert_eq!(resolution.packages[0].features, vec!["default", "std"]);
+        assert!(resolution.packages[1].features.is_empty());
+
+        let options = ResolveOptions {
+            features: vec!["json".to_string(), "rand".to_string()],
+            default_features: false,
+            ..Default::default()
+        };
+        let resolution = resolve(&mut registry, root, &options).unwrap();
+        assert_eq!(
+            package_ids(&resolution),
+            vec![
+                "rand 0.8.0",
+                "root 1.0.0",
+                "serde 1.0.0",
+                "serde_json 1.0.0"
+            ]
+        );
+        assert_eq!(resolution.packages[1].features, vec!["json"]);
+        assert_eq!(resolution.packages[2].features, vec!["derive"]);
+    }
+
+    #[test]
+    fn renamed_dependencies() {
+        let mut registry = TestRegistry::default();
+        let root = registry.add(
+            "root",
+            "1.0.0",
+            &[("default", &["rand08", "serde1/derive"])],
+        );
+        registry.add("serde", "1.0.0", &[("derive", &[])]);
+        registry.add("rand", "0.8.0", &[]);
+        registry.depend(&root, "serde", "^1", |dep| {
+            dep.explicit_name = Some("serde1".to_string())
+        });
+        registry.depend(&root, "rand", "^0.8", |dep| {
+            dep.optional = true;
+            dep.explicit_name = Some("rand08".to_string());
+        });
+
+        let resolution = resolve(&mut registry, root, &Default::default()).unwrap();
+        assert_eq!(
+            package_ids(&resolution),
+            vec!["rand 0.8.0", "root 1.0.0", "serde 1.0.0"]
+        );
+        assert_eq!(resolution.packages[2].features, vec!["derive"]);
+        assert_eq!(
+            resolution.packages[1].dependencies,
+            vec!["rand 0.8.0", "serde 1.0.0"]
+        );
+    }
+
+    #[test]
+    fn kinds_and_targets() {
+        let mut registry = TestRegistry::default();
+        let root = registry.add("root", "1.0.0", &[]);
+        let cc = registry.add("cc", "1.0.0", &[]);
+        registry.add("winapi", "0.3.0", &[]);
+        registry.add("libc", "0.2.0", &[]);
+        registry.add("criterion", "0.3.0", &[]);
+        registry.add("jobserver", "0.1.0", &[]);
+        registry.depend(&root, "cc", "^1", |dep| dep.kind = DependencyKind::Build);
+        registry.depend(&root, "criterion", "^0.3", |dep| {
+            dep.kind = DependencyKind::Dev
+        });
+        registry.depend(&root, "winapi", "^0.3", |dep| {
+            dep.target = Some("cfg(windows)".to_string())
+        });
+        registry.depend(&root, "libc", "^0.2", |dep| {
+            dep.target = Some("x86_64-unknown-linux-gnu".to_string())
+        });
+        registry.depend(&cc, "jobserver", "^0.1", |dep| {
+            dep.kind = DependencyKind::Dev
+        });
+
+        let resolution = resolve(&mut registry, root.clone(), &Default::default()).unwrap();
+        assert_eq!(
+            package_ids(&resolution),
+            vec!["cc 1.0.0", "libc 0.2.0", "root 1.0.0", "winapi 0.3.0"]
+        );
+
+        let options = ResolveOptions {
+            target: Target::from_triple("x86_64-unknown-linux-gnu"),
+            kinds: vec![DependencyKind::Normal, DependencyKind::Dev],
+            ..Default::default()
+        };
+        let resolution = resolve(&mut registry, root, &options).unwrap();
+        assert_eq!(
+            package_ids(&resolution),
+            vec!["criterion 0.3.0", "libc 0.2.0", "root 1.0.0"]
+        );
+    }
+
+    #[test]
+    fn unknown_targets() {
+        assert_none!(Target::from_triple("not-a-target"));
+    }
+}
@@ -61,18 +61,54 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/api/v1/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
     );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/resolve",
+        C(version::resolve::resolve),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/yank_impact",
+        C(version::yank::impact),
+    );
     router.get(
         "/api/v1/crates/:crate_id/:version/downloads",
         C(version::downloads::downloads),
     );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/monthly_downloads",
+        C(version::downloads::monthly_downloads),
+    );
     router.get(
         "/api/v1/crates/:crate_id/:version/authors",
         C(version::metadata::authors),
     );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/files",
+        C(version::files::list),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/files/*path",
+        C(version::files::show),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/:version/sbom",
+        C(version::sbom::sbom),
+    );
     router.get(
         "/api/v1/crates/:crate_id/downloads",
         C(krate::downloads::downloads),
     );
+    router.get(
+        "/api/v1/crates/:crate_id/monthly_downloads",
+        C(krate::downloads::monthly_downloads),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/advisories",
+        C(krate::advisories::list),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/diff/:from/:to",
+        C(version::diff::diff),
+    );
     router.get(
         "/api/v1/crates/:crate_id/versions",
         C(krate::metadata::versions),
@@ -98,17 +134,49 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/api/v1/crates/:crate_id/reverse_dependencies",
         C(krate::metadata::reverse_dependencies),
     );
+    router.get(
+        "/api/v1/crates/:crate_id/webhooks",
+        C(krate::webhooks::list),
+    );
+    router.put(
+        "/api/v1/crates/:crate_id/webhooks",
+        C(krate::webhooks::create),
+    );
+    router.delete(
+        "/api/v1/crates/:crate_id/webhooks/:webhook_id",
+        C(krate::webhooks::delete),
+    );
+    router.get(
+        "/api/v1/crates/:crate_id/webhooks/:webhook_id/deliveries",
+        C(krate::webhooks::deliveries),
+    );
     router.get("/api/v1/keywords", C(keyword::index));
     router.get("/api/v1/keywords/:keyword_id", C(keyword::show));
     router.get("/api/v1/categories", C(category::index));
     router.get("/api/v1/categories/:category_id", C(category::show));
     router.get("/api/v1/category_slugs", C(category::slugs));
+    router.get("/api/v1/autocomplete", C(autocomplete::autocomplete));
     router.get("/api/v1/users/:user_id", C(user::other::show));
     router.put("/api/v1/users/:user_id", C(user::me::update_user));
     router.get("/api/v1/users/:user_id/stats", C(user::other::stats));
+    router.put("/api/v1/teams", C(team::new));
     router.get("/api/v1/teams/:team_id", C(team::show_team));
+    router.get("/api/v1/teams/:team_id/members", C(team::members));
+    router.put(
+        "/api/v1/teams/:team_id/members/:user_id",
+        C(team::add_member),
+    );
+    router.delete(
+        "/api/v1/teams/:team_id/members/:user_id",
+        C(team::remove_member),
+    );
     router.get("/api/v1/me", C(user::me::me));
     router.get("/api/v1/me/updates", C(user::me::updates));
+    router.get("/api/v1/me/identities", C(user::identities::list));
+    router.delete(
+        "/api/v1/me/identities/:provider",
+        C(user::identities::unlink),
+    );
     router.get("/api/v1/me/tokens", C(token::list));
     router.put("/api/v1/me/tokens", C(token::new));
     router.delete("/api/v1/me/tokens/:id", C(token::revoke));
@@ -125,6 +193,10 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/api/v1/me/crate_owner_invitations/accept/:token",
         C(crate_owner_invitation::handle_invite_with_token),
     );
+    router.put(
+        "/api/v1/me/crate_transfers",
+        C(krate::owners::transfer_owners),
+    );
     router.put(
         "/api/v1/me/email_notifications",
         C(user::me::update_email_notifications),
@@ -147,6 +219,18 @@ pub fn build_router(app: &App) -> RouteBuilder {
         C(user::session::authorize),
     );
     router.delete("/api/private/session", C(user::session::logout));
+    router.get(
+        "/api/private/session/providers",
+        C(user::session::providers),
+    );
+    router.get(
+        "/api/private/session/:provider/begin",
+        C(user::session::begin_with_provider),
+    );
+    router.get(
+        "/api/private/session/:provider/authorize",
+        C(user::session::authorize_with_provider),
+    );
 
     // Metrics
     router.get("/api/private/metrics/:kind", C(metrics::prometheus));
@@ -15,7 +15,7 @@ extern crate tracing;
 
 use crate::util::{RequestHelper, TestApp};
 use cargo_registry::{
-    models::{Crate, CrateOwner, NewCategory, NewTeam, NewUser, Team, User},
+    models::{Crate, CrateOwner, NewCategory, NewTeam, NewUser, OwnerRole, Team, User},
     schema::crate_owners,
     views::{
         EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate, EncodableKeyword,
@@ -31,12 +31,14 @@ use diesel::prelude::*;
 
 mod account_lock;
 mod authentication;
+mod autocomplete;
 mod badge;
 mod blocked_routes;
 mod builders;
 mod categories;
 mod category;
 mod dump_db;
+mod identity;
 mod keyword;
 mod krate;
 mod metrics;
@@ -144,6 +146,7 @@ fn add_team_to_crate(t: &Team, krate: &Crate, u: &User, conn: &PgConnection) ->
         created_by: u.id,
         owner_kind: 1, // Team owner kind is 1 according to owner.rs
         email_notifications: true,
+        role: OwnerRole::Publisher,
     };
 
     diesel::insert_into(crate_owners::table)
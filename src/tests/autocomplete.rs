@@ -0,0 +1,103 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::new_category;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::EncodableAutocomplete;
+use http::StatusCode;
+
+#[test]
+fn completes_crates_keywords_and_categories() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Serialization", "serialization", "Serialization crates")
+            .create_or_update(conn)
+            .unwrap();
+        new_category("Science", "science", "Science crates")
+            .create_or_update(conn)
+            .unwrap();
+        CrateBuilder::new("serde", user.id)
+            .downloads(1000)
+            .keyword("serde")
+            .keyword("serialization")
+            .category("serialization")
+            .expect_build(conn);
+        CrateBuilder::new("serde_json", user.id)
+            .downloads(500)
+            .keyword("serialization")
+            .category("serialization")
+            .expect_build(conn);
+        CrateBuilder::new("serde-yaml", user.id)
+            .downloads(2000)
+            .expect_build(conn);
+        CrateBuilder::new("serial", user.id).expect_build(conn);
+        CrateBuilder::new("tokio", user.id).expect_build(conn);
+    });
+
+    let json: EncodableAutocomplete = anon.get_with_query("/api/v1/autocomplete", "q=Ser").good();
+    assert_eq!(json.crates, ["serde-yaml", "serde", "serde_json", "serial"]);
+    assert_eq!(json.keywords, ["serialization", "serde"]);
+    assert_eq!(json.categories, ["serialization"]);
+
+    // Crate names are matched regardless of `-` and `_`
+    let json: EncodableAutocomplete = anon
+        .get_with_query("/api/v1/autocomplete", "q=serde-")
+        .good();
+    assert_eq!(json.crates, ["serde-yaml", "serde_json"]);
+
+    let json: EncodableAutocomplete = anon
+        .get_with_query("/api/v1/autocomplete", "q=s&limit=1")
+        .good();
+    assert_eq!(json.crates, ["serde-yaml"]);
+    assert_eq!(json.keywords, ["serialization"]);
+    assert_eq!(json.categories, ["serialization"]);
+
+    // `_` and `%` are not wildcards
+    let json: EncodableAutocomplete = anon.get_with_query("/api/v1/autocomplete", "q=%25").good();
+    assert!(json.crates.is_empty());
+}
+
+#[test]
+fn invalid_parameters() {
+    let (_, anon) = TestApp::init().empty();
+
+    for query in [
+        "",
+        "q=",
+        "q=serde&limit=0",
+        "q=serde&limit=100",
+        "q=serde&limit=a",
+    ] {
+        let response = anon.get_with_query::<()>("/api/v1/autocomplete", query);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+    }
+}
+
+#[test]
+fn cache_is_cleared_on_publish() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_cached", token.as_model().user_id).expect_build(conn);
+    });
+
+    let json: EncodableAutocomplete = anon.get_with_query("/api/v1/autocomplete", "q=foo").good();
+    assert_eq!(json.crates, ["foo_cached"]);
+
+    // Crates added without publishing are not visible until the cache expires
+    app.db(|conn| {
+        CrateBuilder::new("foo_not_cached", token.as_model().user_id).expect_build(conn);
+    });
+    let json: EncodableAutocomplete = anon.get_with_query("/api/v1/autocomplete", "q=foo").good();
+    assert_eq!(json.crates, ["foo_cached"]);
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_published"))
+        .good();
+
+    let json: EncodableAutocomplete = anon.get_with_query("/api/v1/autocomplete", "q=foo").good();
+    assert_eq!(
+        json.crates,
+        ["foo_cached", "foo_not_cached", "foo_published"]
+    );
+}
@@ -0,0 +1,152 @@
+use crate::user::UserShowPrivateResponse;
+use crate::util::{FakeOidcProvider, RequestHelper};
+use crate::TestApp;
+use cargo_registry::models::LinkedIdentity;
+use cargo_registry::views::{EncodableIdentityProvider, EncodableLinkedIdentity};
+
+use conduit::{header, StatusCode};
+
+#[derive(Deserialize)]
+struct AuthResponse {
+    url: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct ProvidersResponse {
+    providers: Vec<EncodableIdentityProvider>,
+}
+
+#[derive(Deserialize)]
+struct IdentitiesResponse {
+    identities: Vec<EncodableLinkedIdentity>,
+}
+
+/// Goes through the login flow of the `fake` provider as `subject`.
+fn log_in(
+    user: &impl RequestHelper,
+    provider: &FakeOidcProvider,
+    subject: &str,
+    username: &str,
+) -> UserShowPrivateResponse {
+    let response = user.get::<AuthResponse>("/api/private/session/fake/begin");
+    let cookie = response.session_cookie();
+    let json = response.good();
+    let (code, state) = provider.log_in(&json.url, subject, username);
+    assert_eq!(state, json.state);
+
+    let mut request = user.get_request("/api/private/session/fake/authorize");
+    request.header(header::COOKIE, &cookie);
+    request.with_query(&format!("code={code}&state={state}"));
+    user.run(request).good()
+}
+
+#[test]
+fn lists_providers() {
+    let provider = FakeOidcProvider::start();
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.oidc_providers = vec![provider.config("fake")])
+        .empty();
+
+    let json: ProvidersResponse = anon.get("/api/private/session/providers").good();
+    assert_eq!(
+        json.providers,
+        vec![EncodableIdentityProvider {
+            name: "fake".into(),
+            display_name: "Fake fake".into(),
+        }]
+    );
+
+    let response = anon.get::<()>("/api/private/session/unknown/begin");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn login_creates_and_reuses_user() {
+    let provider = FakeOidcProvider::start();
+    let (app, anon) = TestApp::init()
+        .with_config(|config| config.oidc_providers = vec![provider.config("fake")])
+        .empty();
+
+    let json = log_in(&anon, &provider, "subject-1", "alice");
+    assert_eq!(json.user.login, "alice@fake");
+    assert_eq!(json.user.email.as_deref(), Some("alice@example.com"));
+    let user_id = json.user.id;
+
+    // Logging in again with the same identity logs in to the same user
+    let json = log_in(&anon, &provider, "subject-1", "alice-renamed");
+    assert_eq!(json.user.id, user_id);
+
+    let identities = app.db(|conn| LinkedIdentity::for_user(conn, user_id).unwrap());
+    assert_eq!(identities.len(), 1);
+    assert_eq!(identities[0].provider, "fake");
+    assert_eq!(identities[0].subject, "subject-1");
+    assert_eq!(identities[0].login, "alice-renamed");
+}
+
+#[test]
+fn login_suffixes_taken_logins() {
+    let provider = FakeOidcProvider::start();
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.oidc_providers = vec![provider.config("fake")])
+        .empty();
+
+    let first = log_in(&anon, &provider, "subject-1", "alice");
+    assert_eq!(first.user.login, "alice@fake");
+
+    // Another identity with the same login gets a distinct user and login
+    let second = log_in(&anon, &provider, "subject-2", "Alice");
+    assert_ne!(second.user.id, first.user.id);
+    assert_eq!(second.user.login, "Alice-2@fake");
+
+    let third = log_in(&anon, &provider, "subject-3", "alice");
+    assert_eq!(third.user.login, "alice-3@fake");
+}
+
+#[test]
+fn login_links_identity_to_logged_in_user() {
+    let provider = FakeOidcProvider::start();
+    let (_, anon, user) = TestApp::init()
+        .with_config(|config| config.oidc_providers = vec![provider.config("fake")])
+        .with_user();
+    let user_id = user.as_model().id;
+
+    let json = log_in(&user, &provider, "subject-1", "foo");
+    assert_eq!(json.user.id, user_id);
+
+    let json: IdentitiesResponse = user.get("/api/v1/me/identities").good();
+    assert_eq!(json.identities.len(), 1);
+    assert_eq!(json.identities[0].provider, "fake");
+    assert_eq!(json.identities[0].login, "foo");
+
+    // Logging in anonymously with the identity logs in to the linked user
+    let json = log_in(&anon, &provider, "subject-1", "foo");
+    assert_eq!(json.user.id, user_id);
+
+    // The last identity of a user can't be unlinked
+    let response = user.delete::<()>("/api/v1/me/identities/fake");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn login_rejects_invalid_state() {
+    let provider = FakeOidcProvider::start();
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.oidc_providers = vec![provider.config("fake")])
+        .empty();
+
+    let response = anon.get::<AuthResponse>("/api/private/session/fake/begin");
+    let cookie = response.session_cookie();
+    let json = response.good();
+    let (code, _) = provider.log_in(&json.url, "subject-1", "alice");
+
+    let mut request = anon.get_request("/api/private/session/fake/authorize");
+    request.header(header::COOKIE, &cookie);
+    request.with_query(&format!("code={code}&state=bogus"));
+    let response = anon.run::<()>(request);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "invalid state parameter" }] })
+    );
+}
@@ -0,0 +1,66 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::schema::{advisories, version_advisories, versions};
+use cargo_registry::views::EncodableAdvisory;
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct Advisories {
+    advisories: Vec<EncodableAdvisory>,
+}
+
+#[test]
+fn advisories_are_listed_and_linked_to_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_advisories", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+        CrateBuilder::new("bar_advisories", user.id).expect_build(conn);
+
+        diesel::insert_into(advisories::table)
+            .values((
+                advisories::id.eq("RUSTSEC-2022-0001"),
+                advisories::crate_name.eq(&krate.name),
+                advisories::title.eq("Everything is broken"),
+                advisories::description.eq("Really, everything."),
+                advisories::date.eq(NaiveDate::from_ymd(2022, 1, 15)),
+                advisories::patched.eq(vec![">= 1.1.0"]),
+            ))
+            .execute(conn)
+            .unwrap();
+
+        let version_id: i32 = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.0.0"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        diesel::insert_into(version_advisories::table)
+            .values((
+                version_advisories::version_id.eq(version_id),
+                version_advisories::advisory_id.eq("RUSTSEC-2022-0001"),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json: Advisories = anon.get("/api/v1/crates/foo_advisories/advisories").good();
+    assert_eq!(json.advisories.len(), 1);
+    assert_eq!(json.advisories[0].id, "RUSTSEC-2022-0001");
+    assert_eq!(json.advisories[0].patched, vec![">= 1.1.0"]);
+    assert_eq!(json.advisories[0].versions, vec!["1.0.0"]);
+
+    let json: Advisories = anon.get("/api/v1/crates/bar_advisories/advisories").good();
+    assert_eq!(json.advisories.len(), 0);
+
+    let json = anon.show_version("foo_advisories", "1.0.0");
+    assert_eq!(json.version.advisories, vec!["RUSTSEC-2022-0001"]);
+
+    let json = anon.show_version("foo_advisories", "1.1.0");
+    assert!(json.version.advisories.is_empty());
+}
@@ -1,7 +1,11 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{MockAnonymousUser, RequestHelper, TestApp};
-use cargo_registry::views::EncodableVersionDownload;
-use chrono::{Duration, Utc};
+use cargo_registry::views::{
+    EncodableAgentDownloads, EncodableMonthlyDownload, EncodableVersionDownload,
+};
+use chrono::{Duration, NaiveDate, Utc};
+use conduit::{header, Method};
+use diesel::prelude::*;
 use http::StatusCode;
 
 #[derive(Deserialize)]
@@ -9,6 +13,11 @@ struct Downloads {
     version_downloads: Vec<EncodableVersionDownload>,
 }
 
+#[derive(Deserialize)]
+struct MonthlyDownloads {
+    monthly_downloads: Vec<EncodableMonthlyDownload>,
+}
+
 fn persist_downloads_count(app: &TestApp) {
     app.as_inner()
         .downloads_counter
@@ -177,3 +186,119 @@ fn download_caches_version_id() {
     // Check download count against the new name, rather than rename it back to the original value
     assert_dl_count(&anon, "other/1.0.0", None, 2);
 }
+
+#[test]
+fn monthly_downloads() {
+    use cargo_registry::models::{CrateVersions, Version};
+    use cargo_registry::schema::version_downloads_monthly;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_monthly", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+        let versions = krate.all_versions().load::<Version>(conn).unwrap();
+
+        let archived = versions
+            .iter()
+            .map(|version| {
+                (
+                    version_downloads_monthly::version_id.eq(version.id),
+                    version_downloads_monthly::month.eq(NaiveDate::from_ymd(2020, 1, 1)),
+                    version_downloads_monthly::downloads.eq(10),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(version_downloads_monthly::table)
+            .values(&archived)
+            .execute(conn)
+            .unwrap();
+    });
+
+    let download = anon.get::<()>("/api/v1/crates/foo_monthly/1.0.0/download");
+    assert_eq!(download.status(), StatusCode::FOUND);
+    persist_downloads_count(&app);
+
+    let this_month = Utc::today().format("%Y-%m").to_string();
+    let months = |url: &str| {
+        let downloads: MonthlyDownloads = anon.get(url).good();
+        downloads
+            .monthly_downloads
+            .into_iter()
+            .map(|download| (download.month, download.downloads))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        months("/api/v1/crates/foo_monthly/monthly_downloads"),
+        vec![("2020-01".to_string(), 20), (this_month.clone(), 1)]
+    );
+    assert_eq!(
+        months("/api/v1/crates/foo_monthly/1.0.0/monthly_downloads"),
+        vec![("2020-01".to_string(), 10), (this_month, 1)]
+    );
+    assert_eq!(
+        months("/api/v1/crates/foo_monthly/1.1.0/monthly_downloads"),
+        vec![("2020-01".to_string(), 10)]
+    );
+}
+
+#[test]
+fn downloads_by_user_agent() {
+    #[derive(Deserialize)]
+    struct Meta {
+        user_agents: Vec<EncodableAgentDownloads>,
+    }
+
+    #[derive(Deserialize)]
+    struct DownloadsWithMeta {
+        meta: Meta,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_agents", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let download = |user_agent: &str| {
+        let mut request =
+            anon.request_builder(Method::GET, "/api/v1/crates/foo_agents/1.0.0/download");
+        request.header(header::USER_AGENT, user_agent);
+        let response = anon.run::<()>(request);
+        assert_eq!(response.status(), StatusCode::FOUND);
+    };
+
+    download("cargo 1.62.0 (a748cf5a3 2022-06-08)");
+    download("cargo 1.62.1 (a748cf5a3 2022-06-08)");
+    download("cargo 1.40.0 (bc8e4c8be 2019-11-22)");
+    download("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)");
+    persist_downloads_count(&app);
+
+    let user_agents = |url: &str| {
+        let downloads: DownloadsWithMeta = anon.get(url).good();
+        downloads
+            .meta
+            .user_agents
+            .into_iter()
+            .map(|agent| (agent.agent, agent.downloads))
+            .collect::<Vec<_>>()
+    };
+
+    let expected = vec![
+        ("cargo/1.62".to_string(), 2),
+        ("bot".to_string(), 1),
+        ("cargo/1.40".to_string(), 1),
+    ];
+    assert_eq!(
+        user_agents("/api/v1/crates/foo_agents/1.0.0/downloads"),
+        expected
+    );
+    assert_eq!(user_agents("/api/v1/crates/foo_agents/downloads"), expected);
+}
@@ -1,3 +1,4 @@
+mod advisories;
 mod dependencies;
 mod downloads;
 mod following;
@@ -8,4 +9,5 @@ mod search;
 mod show;
 mod summary;
 mod versions;
+mod webhooks;
 mod yanking;
@@ -5,7 +5,7 @@ use cargo_registry::controllers::krate::publish::{
     missing_metadata_error_message, MISSING_RIGHTS_ERROR_MESSAGE, WILDCARD_ERROR_MESSAGE,
 };
 use cargo_registry::models::krate::MAX_NAME_LENGTH;
-use cargo_registry::schema::{api_tokens, emails, versions_published_by};
+use cargo_registry::schema::{api_tokens, crates, emails, versions_published_by};
 use cargo_registry::views::GoodCrate;
 use diesel::{delete, update, ExpressionMethods, QueryDsl, RunQueryDsl};
 use flate2::write::GzEncoder;
@@ -598,6 +598,24 @@ fn new_krate_with_readme() {
     assert_eq!(json.krate.max_version, "1.0.0");
 }
 
+#[test]
+fn new_krate_stores_readme_extract() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_readme")
+        .readme("# Foo\n\nA *fast* `foo` implementation.\n\n```rust\nfoo::run();\n```\n");
+    token.enqueue_publish(crate_to_publish).good();
+
+    let readme: Option<String> = app.db(|conn| {
+        crates::table
+            .filter(crates::name.eq("foo_readme"))
+            .select(crates::readme)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(readme.as_deref(), Some("Foo\nA fast foo implementation."));
+}
+
 #[test]
 fn new_krate_without_any_email_fails() {
     let (app, _, _, token) = TestApp::init().with_token();
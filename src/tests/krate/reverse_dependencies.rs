@@ -215,3 +215,81 @@ fn reverse_dependencies_query_supports_u64_version_number_parts() {
     assert_eq!(deps.versions[0].krate, "c2");
     assert_eq!(deps.versions[0].num, large_but_valid_version_number);
 }
+
+#[test]
+fn reverse_dependencies_filters() {
+    use cargo_registry::schema::{crates, dependencies, versions};
+    use diesel::prelude::*;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id)
+            .version("1.0.0")
+            .version("2.0.0")
+            .expect_build(conn);
+        for (name, downloads) in [("a", 30), ("b", 20), ("c", 10)] {
+            CrateBuilder::new(name, user.id)
+                .version(VersionBuilder::new("0.1.0").dependency(&c1, None))
+                .downloads(downloads)
+                .expect_build(conn);
+        }
+
+        // (crate, req, kind, optional)
+        for (name, req, kind, optional) in [
+            ("a", "^1", 0, false),
+            ("b", "^2", 2, false),
+            ("c", "^1", 0, true),
+        ] {
+            let crate_id = crates::table
+                .filter(crates::name.eq(name))
+                .select(crates::id);
+            let version_ids = versions::table
+                .filter(versions::crate_id.eq_any(crate_id))
+                .select(versions::id);
+            diesel::update(
+                dependencies::table.filter(dependencies::version_id.eq_any(version_ids)),
+            )
+            .set((
+                dependencies::req.eq(req),
+                dependencies::kind.eq(kind),
+                dependencies::optional.eq(optional),
+            ))
+            .execute(conn)
+            .unwrap();
+        }
+    });
+
+    let dependents = |query: &str| -> Vec<String> {
+        let url = format!("/api/v1/crates/c1/reverse_dependencies?{query}");
+        let deps: RevDeps = anon.get(&url).good();
+        assert_eq!(deps.meta.total as usize, deps.dependencies.len());
+        deps.dependencies
+            .iter()
+            .map(|dep| {
+                let version = deps.versions.iter().find(|v| v.id == dep.version_id);
+                version.unwrap().krate.clone()
+            })
+            .collect()
+    };
+
+    assert_eq!(dependents(""), vec!["a", "b", "c"]);
+    assert_eq!(dependents("kind=dev"), vec!["b"]);
+    assert_eq!(dependents("optional=true"), vec!["c"]);
+    assert_eq!(dependents("matches_latest=false"), vec!["a", "c"]);
+    assert_eq!(dependents("range=^1&optional=false"), vec!["a"]);
+    assert_eq!(dependents("range=^2&per_page=1"), vec!["b"]);
+
+    let response = anon.get::<()>("/api/v1/crates/c1/reverse_dependencies?kind=other");
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+
+    let response = anon.get::<()>("/api/v1/crates/c1/reverse_dependencies?format=csv&kind=normal");
+    assert_eq!(response.content_type(), "text/csv; charset=utf-8");
+    assert_eq!(
+        response.into_text(),
+        "crate,version,req,kind,optional,target,downloads\n\
+         a,0.1.0,^1,normal,false,,30\n\
+         c,0.1.0,^1,normal,true,,10\n"
+    );
+}
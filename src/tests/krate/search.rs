@@ -3,6 +3,7 @@ use crate::util::{RequestHelper, TestApp};
 use crate::{new_category, new_user};
 use cargo_registry::models::Category;
 use cargo_registry::schema::crates;
+use cargo_registry::views::EncodableCrate;
 use diesel::{dsl::*, prelude::*, update};
 use http::StatusCode;
 use ipnetwork::IpNetwork;
@@ -119,6 +120,191 @@ fn index_queries() {
     assert_eq!(anon.search("q=k\u{0}w1").meta.total, 3);
 }
 
+#[test]
+fn index_new_filters() {
+    use cargo_registry::schema::versions;
+    use chrono::{Duration, NaiveDate};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let old = NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
+        CrateBuilder::new("filters_mit", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT OR Apache-2.0")))
+            .downloads(100)
+            .updated_at(old)
+            .expect_build(conn);
+        CrateBuilder::new("filters_gpl", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT"))
+                    .created_at(old),
+            )
+            .version(VersionBuilder::new("2.0.0").license(Some("GPL-3.0")))
+            .downloads(10)
+            .expect_build(conn);
+        let yanked = CrateBuilder::new("filters_yanked", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("Apache-2.0/MIT")))
+            .version(
+                VersionBuilder::new("2.0.0")
+                    .created_at(old + Duration::days(1))
+                    .yanked(true),
+            )
+            .expect_build(conn);
+
+        update(crates::table.find(yanked.id))
+            .set(crates::repository.eq("https://github.com/rust-lang/crates.io"))
+            .execute(conn)
+            .unwrap();
+        update(versions::table.filter(versions::crate_id.eq(yanked.id)))
+            .filter(versions::num.eq("1.0.0"))
+            .set(versions::created_at.eq(old))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let names = |query: &str| {
+        let json = anon.search(query);
+        assert_eq!(json.meta.total as usize, json.crates.len());
+        json.crates
+            .into_iter()
+            .map(|krate| krate.name)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names("license=MIT"), vec!["filters_mit", "filters_yanked"]);
+    assert_eq!(
+        names("license=GPL-3.0 OR BSD-3-Clause"),
+        vec!["filters_gpl"]
+    );
+    assert_eq!(
+        names("license=Apache-2.0"),
+        vec!["filters_mit", "filters_yanked"]
+    );
+    assert_eq!(
+        names("min_downloads=10"),
+        vec!["filters_gpl", "filters_mit"]
+    );
+    assert_eq!(names("updated_before=2020-01-01"), vec!["filters_mit"]);
+    assert_eq!(
+        names("updated_after=2020-01-02"),
+        vec!["filters_gpl", "filters_yanked"]
+    );
+    assert_eq!(names("has_repository=yes"), vec!["filters_yanked"]);
+    assert_eq!(
+        names("latest_not_yanked=yes"),
+        vec!["filters_gpl", "filters_mit"]
+    );
+    assert_eq!(
+        names("latest_not_yanked=yes&license=MIT"),
+        vec!["filters_mit"]
+    );
+
+    let response = anon.get_with_query::<()>("/api/v1/crates", "license=(MIT");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = anon.get_with_query::<()>("/api/v1/crates", "updated_after=yesterday");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn filtered_totals_with_seek_based_pagination() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("seek_kw_1", user.id)
+            .keyword("kw1")
+            .expect_build(conn);
+        CrateBuilder::new("seek_kw_2", user.id)
+            .keyword("kw1")
+            .expect_build(conn);
+        CrateBuilder::new("seek_other", user.id).expect_build(conn);
+    });
+
+    let json = anon.search("keyword=kw1&per_page=1");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "seek_kw_1");
+    let next_page = json.meta.next_page.unwrap();
+    assert!(next_page.contains("seek="));
+
+    let json = anon.search(next_page.trim_start_matches('?'));
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "seek_kw_2");
+}
+
+#[test]
+fn index_facets() {
+    #[derive(Deserialize)]
+    struct Facet {
+        value: String,
+        count: i64,
+    }
+
+    #[derive(Deserialize)]
+    struct Facets {
+        categories: Vec<Facet>,
+        keywords: Vec<Facet>,
+    }
+
+    #[derive(Deserialize)]
+    struct Meta {
+        facets: Option<Facets>,
+    }
+
+    #[derive(Deserialize)]
+    struct FacetsResponse {
+        meta: Meta,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+        CrateBuilder::new("facets_1", user.id)
+            .keyword("kw1")
+            .keyword("kw2")
+            .category("cat1")
+            .expect_build(conn);
+        CrateBuilder::new("facets_2", user.id)
+            .keyword("kw1")
+            .expect_build(conn);
+        CrateBuilder::new("other", user.id)
+            .keyword("kw3")
+            .category("cat1")
+            .expect_build(conn);
+    });
+
+    let facets = |query: &str| {
+        let response: FacetsResponse = anon.get_with_query("/api/v1/crates", query).good();
+        let facets = response.meta.facets.unwrap();
+        let encode = |facet: Vec<Facet>| {
+            facet
+                .into_iter()
+                .map(|facet| (facet.value, facet.count))
+                .collect::<Vec<_>>()
+        };
+        (encode(facets.categories), encode(facets.keywords))
+    };
+
+    let (categories, keywords) = facets("q=facets&include_facets=yes");
+    assert_eq!(categories, vec![("cat1".to_string(), 1)]);
+    assert_eq!(
+        keywords,
+        vec![("kw1".to_string(), 2), ("kw2".to_string(), 1)]
+    );
+
+    let (categories, keywords) = facets("include_facets=yes");
+    assert_eq!(categories, vec![("cat1".to_string(), 2)]);
+    assert_eq!(keywords.len(), 3);
+
+    let response: FacetsResponse = anon.get("/api/v1/crates").good();
+    assert!(response.meta.facets.is_none());
+}
+
 #[test]
 fn search_includes_crates_where_name_is_stopword() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -473,6 +659,191 @@ fn loose_search_order() {
     assert_eq!(search_temp.crates.len(), 3);
 }
 
+#[test]
+fn typo_tolerant_search() {
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i32,
+        did_you_mean: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct SearchResponse {
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("tokio", user.id)
+            .keyword("asynchronous")
+            .downloads(100)
+            .expect_build(conn);
+        CrateBuilder::new("serde_json", user.id)
+            .downloads(50)
+            .expect_build(conn);
+        for i in 0..5 {
+            CrateBuilder::new(&format!("parser_{i}"), user.id)
+                .description("A parser")
+                .expect_build(conn);
+        }
+    });
+
+    let search =
+        |query: &str| -> SearchResponse { anon.get_with_query("/api/v1/crates", query).good() };
+
+    let json = search("q=tokoi");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "tokio");
+    assert_eq!(json.meta.did_you_mean.as_deref(), Some("tokio"));
+
+    let json = search("q=serd_json");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "serde_json");
+
+    // Keywords are matched too
+    let json = search("q=asynchronus");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "tokio");
+    assert_eq!(json.meta.did_you_mean.as_deref(), Some("asynchronous"));
+
+    // No suggestion when there are enough exact hits
+    let json = search("q=parser");
+    assert_eq!(json.meta.total, 5);
+    assert_eq!(json.meta.did_you_mean, None);
+
+    // Short queries are not matched fuzzily
+    let json = search("q=tko");
+    assert_eq!(json.meta.total, 0);
+    assert_eq!(json.meta.did_you_mean, None);
+}
+
+#[test]
+fn relevance_score_weights() {
+    #[derive(Deserialize)]
+    struct Score {
+        name: String,
+        text: f64,
+        recent_downloads: f64,
+        score: f64,
+    }
+
+    #[derive(Deserialize)]
+    struct Meta {
+        scores: Option<Vec<Score>>,
+    }
+
+    #[derive(Deserialize)]
+    struct SearchResponse {
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| {
+            config.search_ranking_weights.recent_downloads = 1.0;
+            config.gh_admin_user_ids.insert(ADMIN_GH_ID);
+        })
+        .with_user();
+    let admin = user.as_model();
+
+    app.db(|conn| {
+        make_admin(conn, admin.id);
+        CrateBuilder::new("obscure", admin.id)
+            .description("widget widget widget widget")
+            .expect_build(conn);
+        CrateBuilder::new("popular", admin.id)
+            .description("A widget")
+            .recent_downloads(1000)
+            .expect_build(conn);
+    });
+
+    let json: SearchResponse = user
+        .get_with_query("/api/v1/crates", "q=widget&include_scores=yes")
+        .good();
+    let names = json.crates.iter().map(|c| &*c.name).collect::<Vec<_>>();
+    assert_eq!(names, ["popular", "obscure"]);
+
+    let scores = json.meta.scores.unwrap();
+    assert_eq!(scores.len(), 2);
+    assert_eq!(scores[0].name, "popular");
+    assert_eq!(scores[1].name, "obscure");
+    assert!(scores[0].text < scores[1].text);
+    assert!(scores[0].recent_downloads > 0.0);
+    assert_eq!(scores[1].recent_downloads, 0.0);
+    assert!(scores[0].score > scores[1].score);
+
+    // Scores are only included when requested
+    let json: SearchResponse = user.get_with_query("/api/v1/crates", "q=widget").good();
+    assert!(json.meta.scores.is_none());
+}
+
+#[test]
+fn relevance_scores_require_admin() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.gh_admin_user_ids.insert(ADMIN_GH_ID);
+        })
+        .with_user();
+    let user_model = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("widget", user_model.id).expect_build(conn);
+    });
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "q=widget&include_scores=yes")
+        .into_json();
+    assert_eq!(json["meta"]["scores"], serde_json::Value::Null);
+
+    let json = user
+        .get_with_query::<()>("/api/v1/crates", "q=widget&include_scores=yes")
+        .into_json();
+    assert_eq!(json["meta"]["scores"], serde_json::Value::Null);
+}
+
+/// GitHub user ID of the administrator in the tests, out of the range used by `new_user`.
+const ADMIN_GH_ID: i32 = 1_000_000_000;
+
+fn make_admin(conn: &PgConnection, user_id: i32) {
+    use cargo_registry::schema::users;
+
+    update(users::table.find(user_id))
+        .set(users::gh_id.eq(ADMIN_GH_ID))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+fn readme_snippets() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("runtime", user.id)
+            .readme("An <b>event-driven</b> runtime for writing reliable network applications.")
+            .expect_build(conn);
+        CrateBuilder::new("network", user.id)
+            .description("Networking")
+            .expect_build(conn);
+    });
+
+    let json = anon.search("q=network");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "network");
+    assert_eq!(json.crates[0].readme_snippet, None);
+    assert_eq!(json.crates[1].name, "runtime");
+    let snippet = json.crates[1].readme_snippet.as_deref().unwrap();
+    assert!(snippet.contains("<mark>network</mark>"), "{snippet}");
+    // The README is not trusted HTML
+    assert!(!snippet.contains("<b>"), "{snippet}");
+
+    let json = anon.search("sort=alpha");
+    assert!(json.crates.iter().all(|c| c.readme_snippet.is_none()));
+}
+
 #[test]
 fn index_include_yanked() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -699,31 +1070,31 @@ fn pagination_links_included_if_applicable() {
         CrateBuilder::new("pagination_links_3", user.id).expect_build(conn);
     });
 
-    // This uses a filter (`letter=p`) to disable seek-based pagination, as seek-based pagination
-    // does not return page numbers. If the test fails after expanding the scope of seek-based
-    // pagination replace the filter with something else still using pages.
+    // This uses a custom sort (`sort=downloads`) to disable seek-based pagination, as seek-based
+    // pagination does not return page numbers. If the test fails after expanding the scope of
+    // seek-based pagination replace the sort with something else still using pages.
 
-    let page1 = anon.search("letter=p&per_page=1");
-    let page2 = anon.search("letter=p&page=2&per_page=1");
-    let page3 = anon.search("letter=p&page=3&per_page=1");
-    let page4 = anon.search("letter=p&page=4&per_page=1");
+    let page1 = anon.search("sort=downloads&per_page=1");
+    let page2 = anon.search("sort=downloads&page=2&per_page=1");
+    let page3 = anon.search("sort=downloads&page=3&per_page=1");
+    let page4 = anon.search("sort=downloads&page=4&per_page=1");
 
     assert_eq!(
-        Some("?letter=p&per_page=1&page=2".to_string()),
+        Some("?sort=downloads&per_page=1&page=2".to_string()),
         page1.meta.next_page
     );
     assert_eq!(None, page1.meta.prev_page);
     assert_eq!(
-        Some("?letter=p&page=3&per_page=1".to_string()),
+        Some("?sort=downloads&page=3&per_page=1".to_string()),
         page2.meta.next_page
     );
     assert_eq!(
-        Some("?letter=p&page=1&per_page=1".to_string()),
+        Some("?sort=downloads&page=1&per_page=1".to_string()),
         page2.meta.prev_page
     );
     assert_eq!(None, page4.meta.next_page);
     assert_eq!(
-        Some("?letter=p&page=2&per_page=1".to_string()),
+        Some("?sort=downloads&page=2&per_page=1".to_string()),
         page3.meta.prev_page
     );
 }
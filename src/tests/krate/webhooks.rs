@@ -0,0 +1,230 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::models::{Crate, NewCrateWebhook};
+use cargo_registry::schema::webhook_deliveries;
+use cargo_registry::views::{EncodableWebhook, EncodableWebhookDelivery};
+use diesel::prelude::*;
+use http::StatusCode;
+
+#[derive(Deserialize)]
+struct Webhooks {
+    webhooks: Vec<EncodableWebhook>,
+}
+
+#[derive(Deserialize)]
+struct Webhook {
+    webhook: EncodableWebhook,
+}
+
+#[derive(Deserialize)]
+struct Deliveries {
+    deliveries: Vec<EncodableWebhookDelivery>,
+}
+
+static NEW_WEBHOOK: &[u8] =
+    br#"{ "webhook": { "url": "https://93.184.216.34/hook", "secret": "s3cr3t" } }"#;
+
+#[test]
+fn owners_can_manage_webhooks() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo_webhooks", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo_webhooks/webhooks";
+    let json: Webhooks = user.get(url).good();
+    assert!(json.webhooks.is_empty());
+
+    let response = user.put::<()>(url, NEW_WEBHOOK);
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.into_json();
+    assert_eq!(json["webhook"]["url"], "https://93.184.216.34/hook");
+    assert!(json["webhook"].get("secret").is_none());
+
+    let json: Webhooks = user.get(url).good();
+    assert_eq!(json.webhooks.len(), 1);
+    let id = json.webhooks[0].id;
+
+    let json: Deliveries = user
+        .get(&format!(
+            "/api/v1/crates/foo_webhooks/webhooks/{id}/deliveries"
+        ))
+        .good();
+    assert!(json.deliveries.is_empty());
+
+    let response = user.delete::<()>(&format!("/api/v1/crates/foo_webhooks/webhooks/{id}"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: Webhooks = user.get(url).good();
+    assert!(json.webhooks.is_empty());
+}
+
+#[test]
+fn invalid_webhooks_are_rejected() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo_webhooks", user.as_model().id).expect_build(conn));
+
+    let url = "/api/v1/crates/foo_webhooks/webhooks";
+    let body = br#"{ "webhook": { "url": "ftp://example.com", "secret": "s3cr3t" } }"#;
+    let response = user.put::<()>(url, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = br#"{ "webhook": { "url": "http://93.184.216.34/hook", "secret": "s3cr3t" } }"#;
+    let response = user.put::<()>(url, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    for internal in ["127.0.0.1", "10.0.0.1", "169.254.169.254", "[::1]"] {
+        let body =
+            json!({ "webhook": { "url": format!("https://{internal}/hook"), "secret": "s3cr3t" } });
+        let response = user.put::<()>(url, body.to_string().as_bytes());
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    let body = br#"{ "webhook": { "url": "https://93.184.216.34/hook", "secret": "" } }"#;
+    let response = user.put::<()>(url, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: Webhook = user.put(url, NEW_WEBHOOK).good();
+    assert_eq!(json.webhook.url, "https://93.184.216.34/hook");
+}
+
+#[test]
+fn non_owners_cannot_manage_webhooks() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| CrateBuilder::new("foo_webhooks", user.as_model().id).expect_build(conn));
+    let other = app.db_new_user("other");
+
+    let url = "/api/v1/crates/foo_webhooks/webhooks";
+    let response = other.put::<()>(url, NEW_WEBHOOK);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "only owners have permission to manage webhooks" }] })
+    );
+
+    let response = anon.get::<()>(url);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let json: Webhooks = user.get(url).good();
+    assert!(json.webhooks.is_empty());
+}
+
+#[test]
+fn crate_events_enqueue_deliveries() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    token.enqueue_publish(PublishBuilder::new("fyk_max")).good();
+
+    // The API refuses internal addresses, so the webhook is inserted directly. Its
+    // deliveries are recorded as failed without sending a request.
+    let krate = app.db(|conn| {
+        let krate: Crate = Crate::by_name("fyk_max").first(conn).unwrap();
+        NewCrateWebhook {
+            crate_id: krate.id,
+            url: "https://127.0.0.1/hook",
+            secret: "s3cr3t",
+            created_by: user.as_model().id,
+        }
+        .create(conn)
+        .unwrap();
+        krate
+    });
+
+    token
+        .delete::<OkBool>("/api/v1/crates/fyk_max/1.0.0/yank")
+        .good();
+    app.run_pending_background_jobs();
+    token
+        .enqueue_publish(PublishBuilder::new("fyk_max").version("2.0.0"))
+        .good();
+    token
+        .put::<OkBool>("/api/v1/crates/fyk_max/1.0.0/unyank", &[])
+        .good();
+    app.run_pending_background_jobs();
+
+    let other = app.db_new_user("other");
+    token.add_named_owner("fyk_max", "other").good();
+    let body = json!({
+        "crate_owner_invite": {
+            "invited_by_username": "",
+            "crate_name": "fyk_max",
+            "crate_id": krate.id,
+            "created_at": "",
+            "accepted": true
+        }
+    });
+    let url = format!("/api/v1/me/crate_owner_invitations/{}", krate.id);
+    let response = other.put::<()>(&url, body.to_string().as_bytes());
+    assert_eq!(response.status(), StatusCode::OK);
+    token.remove_named_owner("fyk_max", "other").good();
+    app.run_pending_background_jobs();
+
+    let deliveries: Vec<(String, i32, Option<String>)> = app.db(|conn| {
+        webhook_deliveries::table
+            .select((
+                webhook_deliveries::event,
+                webhook_deliveries::attempts,
+                webhook_deliveries::error,
+            ))
+            .order(webhook_deliveries::id)
+            .load(conn)
+            .unwrap()
+    });
+    let events = deliveries
+        .iter()
+        .map(|(event, _, _)| event.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        ["yank", "publish", "unyank", "owner_add", "owner_remove"]
+    );
+    for (_, attempts, error) in deliveries {
+        assert_eq!(attempts, 1);
+        assert_eq!(
+            error.unwrap(),
+            "the host `127.0.0.1` resolves to a non-public address"
+        );
+    }
+}
+
+#[test]
+fn accepted_transfers_enqueue_deliveries() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let krate = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_transfer_hook", user.as_model().id).expect_build(conn);
+        NewCrateWebhook {
+            crate_id: krate.id,
+            url: "https://127.0.0.1/hook",
+            secret: "s3cr3t",
+            created_by: user.as_model().id,
+        }
+        .create(conn)
+        .unwrap();
+        krate
+    });
+    let new_owner = app.db_new_user("new-owner");
+
+    let body = json!({ "crates": ["foo_transfer_hook"], "to": "new-owner" });
+    token
+        .put::<OkBool>("/api/v1/me/crate_transfers", body.to_string().as_bytes())
+        .good();
+    let body = json!({
+        "crate_owner_invite": {
+            "invited_by_username": "",
+            "crate_name": "foo_transfer_hook",
+            "crate_id": krate.id,
+            "created_at": "",
+            "accepted": true
+        }
+    });
+    let url = format!("/api/v1/me/crate_owner_invitations/{}", krate.id);
+    let response = new_owner.put::<()>(&url, body.to_string().as_bytes());
+    assert_eq!(response.status(), StatusCode::OK);
+    app.run_pending_background_jobs();
+
+    let events: Vec<String> = app.db(|conn| {
+        webhook_deliveries::table
+            .select(webhook_deliveries::event)
+            .order(webhook_deliveries::id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(events, ["owner_add", "owner_remove"]);
+}
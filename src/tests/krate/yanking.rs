@@ -1,6 +1,9 @@
-use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use crate::OkBool;
+use cargo_registry::schema::{crates, dependencies, versions};
+use cargo_registry::views::EncodableDependent;
+use diesel::prelude::*;
 use http::StatusCode;
 
 impl crate::util::MockTokenUser {
@@ -246,3 +249,94 @@ fn unyank_records_an_audit_action() {
     assert_eq!(action.action, "unyank");
     assert_eq!(action.user.id, token.as_model().user_id);
 }
+
+#[test]
+fn publish_and_yank_notify_owners() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token.enqueue_publish(PublishBuilder::new("fyk")).good();
+    token.yank("fyk", "1.0.0").good();
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(mails.len(), 2);
+    assert_eq!(mails[0].subject, "Crate fyk 1.0.0 was published");
+    assert_eq!(mails[1].subject, "Crate fyk 1.0.0 was yanked");
+    assert!(mails[1].body.contains("By user: foo"));
+    assert!(mails[1].body.contains("the API token \"bar\""));
+    assert!(mails[1].body.contains("User agent: conduit-test"));
+}
+
+#[test]
+fn yank_respects_email_notification_preference() {
+    use cargo_registry::schema::crate_owners;
+    use diesel::prelude::*;
+
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    token.enqueue_publish(PublishBuilder::new("fyk")).good();
+    app.db(|conn| {
+        diesel::update(crate_owners::table)
+            .set(crate_owners::email_notifications.eq(false))
+            .execute(conn)
+            .unwrap();
+    });
+    token.yank("fyk", "1.0.0").good();
+
+    let mails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(mails.len(), 1);
+    assert_eq!(mails[0].subject, "Crate fyk 1.0.0 was published");
+}
+
+#[derive(Deserialize)]
+struct YankImpact {
+    dependents: Vec<EncodableDependent>,
+}
+
+#[test]
+fn yank_impact_lists_dependents_without_other_matching_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("impact", user.id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.0.1").yanked(true))
+            .version("1.1.0")
+            .expect_build(conn);
+        for (name, downloads) in [("pinned", 10), ("caret", 20), ("popular", 30)] {
+            CrateBuilder::new(name, user.id)
+                .version(VersionBuilder::new("0.1.0").dependency(&krate, None))
+                .downloads(downloads)
+                .expect_build(conn);
+        }
+
+        for (name, req) in [("pinned", "=1.1.0"), ("caret", "^1.0"), ("popular", "~1.1")] {
+            let crate_id = crates::table
+                .filter(crates::name.eq(name))
+                .select(crates::id);
+            let version_ids = versions::table
+                .filter(versions::crate_id.eq_any(crate_id))
+                .select(versions::id);
+            diesel::update(
+                dependencies::table.filter(dependencies::version_id.eq_any(version_ids)),
+            )
+            .set(dependencies::req.eq(req))
+            .execute(conn)
+            .unwrap();
+        }
+    });
+
+    let json: YankImpact = anon.get("/api/v1/crates/impact/1.1.0/yank_impact").good();
+    let dependents = json
+        .dependents
+        .iter()
+        .map(|dep| (dep.krate.as_str(), dep.version.as_str(), dep.req.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dependents,
+        vec![("popular", "0.1.0", "~1.1"), ("pinned", "0.1.0", "=1.1.0")]
+    );
+
+    let json: YankImpact = anon.get("/api/v1/crates/impact/1.0.0/yank_impact").good();
+    assert!(json.dependents.is_empty());
+}
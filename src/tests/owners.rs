@@ -3,10 +3,10 @@ use crate::{
     builders::{CrateBuilder, PublishBuilder},
     new_team,
     util::{MockAnonymousUser, MockCookieUser, MockTokenUser, RequestHelper, Response},
-    TestApp,
+    OkBool, TestApp,
 };
 use cargo_registry::{
-    models::Crate,
+    models::{Crate, CrateOwnerAction, CrateOwnerActionKind, OwnerRole},
     views::{
         EncodableCrateOwnerInvitation, EncodableCrateOwnerInvitationV1, EncodableOwner,
         EncodablePublicUser, InvitationResponse,
@@ -170,6 +170,218 @@ fn create_and_add_owner(
     user
 }
 
+#[test]
+fn owner_roles_limit_rights() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_roles").version("1.0.0");
+    token.enqueue_publish(crate_to_publish).good();
+    let krate: Crate = app.db(|conn| Crate::by_name("foo_roles").first(conn).unwrap());
+
+    let bot = app.db_new_user("bot");
+    token
+        .add_named_owner_with_role("foo_roles", "bot", "yank-only")
+        .good();
+    bot.accept_ownership_invitation("foo_roles", krate.id);
+    let bot_token = bot.db_new_token("bot_token");
+
+    let json: UserResponse = token.get("/api/v1/crates/foo_roles/owners").good();
+    let mut roles = json
+        .users
+        .into_iter()
+        .map(|owner| (owner.login, owner.role))
+        .collect::<Vec<_>>();
+    roles.sort();
+    assert_eq!(
+        roles,
+        [
+            ("bot".to_string(), Some(OwnerRole::YankOnly)),
+            ("foo".to_string(), Some(OwnerRole::Admin)),
+        ]
+    );
+
+    // Yank-only owners can only yank
+    bot_token
+        .delete::<OkBool>("/api/v1/crates/foo_roles/1.0.0/yank")
+        .good();
+    let crate_to_publish = PublishBuilder::new("foo_roles").version("2.0.0");
+    let response = bot_token.enqueue_publish(crate_to_publish);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "yank-only owners don't have permission to publish new versions" }] })
+    );
+    let response = bot_token.remove_named_owner("foo_roles", "foo");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "only owners with the admin role have permission to modify owners" }] })
+    );
+
+    // Publishers can also publish
+    token
+        .add_named_owner_with_role("foo_roles", "bot", "publisher")
+        .good();
+    let crate_to_publish = PublishBuilder::new("foo_roles").version("2.0.0");
+    bot_token.enqueue_publish(crate_to_publish).good();
+
+    // The last individual admin can't give up their role
+    let response = token.add_named_owner_with_role("foo_roles", "foo", "publisher");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "cannot take the admin role away from all individual owners of a crate" }] })
+    );
+}
+
+#[test]
+fn transfer_crates_to_user() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    let krates = app.db(|conn| {
+        vec![
+            CrateBuilder::new("foo_transfer_1", user.as_model().id).expect_build(conn),
+            CrateBuilder::new("foo_transfer_2", user.as_model().id).expect_build(conn),
+        ]
+    });
+    let new_owner = app.db_new_user("new-owner");
+
+    // Only admins of the crates can transfer them
+    let body = json!({ "crates": ["foo_transfer_1", "foo_transfer_2"], "to": "foo" });
+    let response = new_owner.put::<()>("/api/v1/me/crate_transfers", body.to_string().as_bytes());
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "only owners with the admin role have permission to transfer crate foo_transfer_1" }] })
+    );
+
+    // Nothing is transferred, and no email is sent, if any of the crates can't be transferred
+    app.db(|conn| {
+        CrateBuilder::new("foo_transfer_other", new_owner.as_model().id).expect_build(conn);
+    });
+    let body = json!({
+        "crates": ["foo_transfer_1", "foo_transfer_2", "foo_transfer_other"],
+        "to": "new-owner",
+    });
+    let response = token.put::<()>("/api/v1/me/crate_transfers", body.to_string().as_bytes());
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "only owners with the admin role have permission to transfer crate foo_transfer_other" }] })
+    );
+    assert!(new_owner
+        .list_invitations()
+        .crate_owner_invitations
+        .is_empty());
+    let transfer_emails = || {
+        let emails = app.as_inner().emails.mails_in_memory().unwrap();
+        emails
+            .into_iter()
+            .filter(|email| email.subject == "Crate ownership transfer")
+            .count()
+    };
+    assert_eq!(transfer_emails(), 0);
+
+    let body = json!({ "crates": ["foo_transfer_1", "foo_transfer_2"], "to": "new-owner" });
+    token
+        .put::<OkBool>("/api/v1/me/crate_transfers", body.to_string().as_bytes())
+        .good();
+    assert_eq!(transfer_emails(), 2);
+
+    for krate in &krates {
+        // The previous owner stays until the transfer is accepted
+        let owners = app.db(|conn| krate.owners(conn).unwrap());
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].login(), "foo");
+
+        new_owner.accept_ownership_invitation(&krate.name, krate.id);
+
+        let owners = app.db(|conn| krate.owners_with_roles(conn).unwrap());
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].0.login(), "new-owner");
+        assert_eq!(owners[0].1, OwnerRole::Admin);
+
+        let actions = app.db(|conn| CrateOwnerAction::by_crate(conn, krate.id).unwrap());
+        let actions = actions
+            .iter()
+            .map(|action| (action.user_id, action.action, action.owner_id))
+            .collect::<Vec<_>>();
+        let (old_id, new_id) = (user.as_model().id, new_owner.as_model().id);
+        assert_eq!(
+            actions,
+            [
+                (old_id, CrateOwnerActionKind::TransferRequested, new_id),
+                (new_id, CrateOwnerActionKind::Add, new_id),
+                (new_id, CrateOwnerActionKind::Remove, old_id),
+            ]
+        );
+    }
+}
+
+#[test]
+fn invite_owner_by_email() {
+    use cargo_registry::schema::emails;
+
+    let (app, _, _, token) = TestApp::init().with_token();
+    let krate = app.db(|conn| {
+        let user = token.as_model();
+        CrateBuilder::new("foo_email_invite", user.user_id).expect_build(conn)
+    });
+
+    token
+        .add_named_owner("foo_email_invite", "New-Hire@example.com")
+        .good();
+    let emails = app.as_inner().emails.mails_in_memory().unwrap();
+    assert_eq!(emails.last().unwrap().to, "New-Hire@example.com");
+
+    // The invitation is pending until someone verifies the email address
+    let new_hire = app.db_new_user("new-hire");
+    assert!(new_hire
+        .list_invitations()
+        .crate_owner_invitations
+        .is_empty());
+    let email_token = app.db(|conn| {
+        diesel::update(emails::table.filter(emails::user_id.eq(new_hire.as_model().id)))
+            .set((
+                emails::email.eq("new-hire@example.com"),
+                emails::verified.eq(false),
+            ))
+            .returning(emails::token)
+            .get_result::<String>(conn)
+            .unwrap()
+    });
+    new_hire
+        .put::<OkBool>(&format!("/api/v1/confirm/{email_token}"), &[])
+        .good();
+
+    let invitations = new_hire.list_invitations().crate_owner_invitations;
+    assert_eq!(invitations.len(), 1);
+    assert_eq!(invitations[0].crate_name, "foo_email_invite");
+    new_hire.accept_ownership_invitation("foo_email_invite", krate.id);
+    assert_eq!(app.db(|conn| krate.owners(conn).unwrap()).len(), 2);
+}
+
+#[test]
+fn email_invitations_are_validated_and_capped() {
+    let (app, _, _, token) = TestApp::init().with_token();
+    app.db(|conn| {
+        let user = token.as_model();
+        CrateBuilder::new("foo_email_cap", user.user_id).expect_build(conn)
+    });
+
+    // Logins of users created through an identity provider aren't email addresses
+    let response = token.add_named_owner("foo_email_cap", "alice@keycloak");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "could not find user with login `alice@keycloak`" }] })
+    );
+
+    for i in 0..10 {
+        token
+            .add_named_owner("foo_email_cap", &format!("hire-{i}@example.com"))
+            .good();
+    }
+    let response = token.add_named_owner("foo_email_cap", "hire-10@example.com");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "this crate has too many pending invitations by email, please wait for some of them to be accepted or to expire" }] })
+    );
+}
+
 // Ensures that so long as at least one owner remains associated with the crate,
 // a user can still remove their own login as an owner
 #[test]
@@ -1,13 +1,50 @@
 use crate::{
     add_team_to_crate,
     builders::{CrateBuilder, PublishBuilder},
-    new_team, OwnerTeamsResponse, RequestHelper, TestApp,
+    new_team, OkBool, OwnerTeamsResponse, RequestHelper, TestApp,
 };
-use cargo_registry::models::{Crate, NewTeam};
+use cargo_registry::models::{CachedTeamMembership, Crate, NewTeam, TeamRole};
+use cargo_registry::views::{EncodableTeam, EncodableTeamMember};
 
 use conduit::StatusCode;
 use diesel::*;
 
+#[derive(Deserialize)]
+struct TeamResponse {
+    team: EncodableTeam,
+}
+
+#[derive(Deserialize)]
+struct TeamMembersResponse {
+    members: Vec<EncodableTeamMember>,
+}
+
+impl crate::util::MockCookieUser {
+    /// Create a registry-native team.
+    fn create_team(&self, name: &str) -> crate::util::Response<TeamResponse> {
+        let body = json!({ "team": { "name": name } });
+        self.put("/api/v1/teams", body.to_string().as_bytes())
+    }
+
+    /// Add a user to a registry-native team, or change their role.
+    fn add_team_member(
+        &self,
+        team: &str,
+        login: &str,
+        role: &str,
+    ) -> crate::util::Response<OkBool> {
+        let url = format!("/api/v1/teams/{team}/members/{login}");
+        let body = json!({ "role": role });
+        self.put(&url, body.to_string().as_bytes())
+    }
+
+    /// Remove a user from a registry-native team.
+    fn remove_team_member(&self, team: &str, login: &str) -> crate::util::Response<OkBool> {
+        let url = format!("/api/v1/teams/{team}/members/{login}");
+        self.delete(&url)
+    }
+}
+
 impl crate::util::MockAnonymousUser {
     /// List the team owners of the specified crate.
     fn crate_owner_teams(&self, krate_name: &str) -> crate::util::Response<OwnerTeamsResponse> {
@@ -29,7 +66,7 @@ fn not_github() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.into_json(),
-        json!({ "errors": [{ "detail": "unknown organization handler, only 'github:org:team' is supported" }] })
+        json!({ "errors": [{ "detail": "unknown organization handler, only 'github:org:team' and 'registry:team' are supported" }] })
     );
 }
 
@@ -340,6 +377,78 @@ fn publish_owned() {
     user_on_one_team.enqueue_publish(crate_to_publish).good();
 }
 
+#[test]
+fn team_memberships_are_cached() {
+    use cargo_registry::schema::{cached_team_memberships, teams};
+    use chrono::Utc;
+
+    let (app, _) = TestApp::full().empty();
+    let user_on_both_teams = app.db_new_user("user-all-teams");
+    let token_on_both_teams = user_on_both_teams.db_new_token("arbitrary token name");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_team_cached", user_on_both_teams.as_model().id).expect_build(conn);
+    });
+
+    token_on_both_teams
+        .add_named_owner("foo_team_cached", "github:test-org:all")
+        .good();
+    let team_id = app.db(|conn| {
+        teams::table
+            .filter(teams::login.eq("github:test-org:all"))
+            .select(teams::id)
+            .first::<i32>(conn)
+            .unwrap()
+    });
+
+    // The result of asking GitHub is recorded
+    let user_on_one_team = app.db_new_user("user-one-team");
+    let crate_to_publish = PublishBuilder::new("foo_team_cached").version("2.0.0");
+    user_on_one_team.enqueue_publish(crate_to_publish).good();
+    let cached = app.db(|conn| {
+        CachedTeamMembership::find(conn, team_id, user_on_one_team.as_model().id).unwrap()
+    });
+    assert!(cached.unwrap().is_member);
+
+    // A recent result is used instead of asking GitHub again, and marked as used
+    let user_id = user_on_one_team.as_model().id;
+    let long_ago = (Utc::now() - chrono::Duration::days(30)).naive_utc();
+    app.db(|conn| {
+        CachedTeamMembership::record(conn, team_id, user_id, false).unwrap();
+        diesel::update(cached_team_memberships::table.find((team_id, user_id)))
+            .set(cached_team_memberships::last_used_at.eq(long_ago))
+            .execute(conn)
+            .unwrap();
+    });
+    let crate_to_publish = PublishBuilder::new("foo_team_cached").version("3.0.0");
+    let response = user_on_one_team.enqueue_publish(crate_to_publish);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing." }] })
+    );
+
+    // Only memberships that weren't used recently are pruned
+    app.db(|conn| {
+        let cached = CachedTeamMembership::find(conn, team_id, user_id).unwrap();
+        assert!(cached.unwrap().last_used_at > long_ago);
+
+        let cutoff = (Utc::now() - chrono::Duration::days(1)).naive_utc();
+        assert_eq!(
+            CachedTeamMembership::delete_unused_since(conn, cutoff).unwrap(),
+            0
+        );
+        diesel::update(cached_team_memberships::table.find((team_id, user_id)))
+            .set(cached_team_memberships::last_used_at.eq(long_ago))
+            .execute(conn)
+            .unwrap();
+        assert_eq!(
+            CachedTeamMembership::delete_unused_since(conn, cutoff).unwrap(),
+            1
+        );
+    });
+}
+
 // Test trying to change owners (when only on an owning team)
 #[test]
 fn add_owners_as_org_owner() {
@@ -431,3 +540,148 @@ fn crates_by_team_id_not_including_deleted_owners() {
     let json = anon.search(&format!("team_id={}", team.id));
     assert_eq!(json.crates.len(), 0);
 }
+
+#[test]
+fn native_team_members_get_publish_rights() {
+    let (app, anon) = TestApp::full().empty();
+    let owner = app.db_new_user("owner");
+    let owner_token = owner.db_new_token("arbitrary token name");
+
+    let json = owner.create_team("core").good();
+    assert_eq!(json.team.login, "registry:core");
+    assert_none!(json.team.url);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_native_team", owner.as_model().id).expect_build(conn);
+    });
+
+    let member = app.db_new_user("member");
+    let member_token = member.db_new_token("arbitrary token name");
+    owner
+        .add_team_member("registry:core", "member", "member")
+        .good();
+
+    let json: TeamMembersResponse = anon.get("/api/v1/teams/registry:core/members").good();
+    assert_eq!(
+        json.members,
+        vec![
+            EncodableTeamMember {
+                login: "owner".into(),
+                role: TeamRole::Maintainer,
+            },
+            EncodableTeamMember {
+                login: "member".into(),
+                role: TeamRole::Member,
+            },
+        ]
+    );
+
+    owner_token
+        .add_named_owner("foo_native_team", "registry:core")
+        .good();
+
+    let crate_to_publish = PublishBuilder::new("foo_native_team").version("2.0.0");
+    member_token.enqueue_publish(crate_to_publish).good();
+
+    let response = member_token.add_named_owner("foo_native_team", "arbitrary_username");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "team members don't have permission to modify owners" }] })
+    );
+
+    // Members can leave the team, which revokes their rights
+    member.remove_team_member("registry:core", "member").good();
+    let crate_to_publish = PublishBuilder::new("foo_native_team").version("3.0.0");
+    let response = member_token.enqueue_publish(crate_to_publish);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing." }] })
+    );
+}
+
+#[test]
+fn native_team_members_are_managed_by_maintainers() {
+    let (app, _) = TestApp::init().empty();
+    let owner = app.db_new_user("owner");
+    let member = app.db_new_user("member");
+    let other = app.db_new_user("other");
+    let other_token = other.db_new_token("arbitrary token name");
+
+    owner.create_team("core").good();
+    owner
+        .add_team_member("registry:core", "member", "member")
+        .good();
+
+    // Only maintainers can manage the members
+    let response = member.add_team_member("registry:core", "other", "member");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = member.remove_team_member("registry:core", "owner");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // A team always keeps a maintainer
+    let response = owner.remove_team_member("registry:core", "owner");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    owner
+        .add_team_member("registry:core", "member", "maintainer")
+        .good();
+    owner.remove_team_member("registry:core", "owner").good();
+
+    // Team names are unique
+    let response = other.create_team("core");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "team registry:core already exists" }] })
+    );
+    let response = other.create_team("Core");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Only members can add the team as an owner of their crates
+    app.db(|conn| {
+        CrateBuilder::new("foo_native_team", other.as_model().id).expect_build(conn);
+    });
+    let response = other_token.add_named_owner("foo_native_team", "registry:core");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "only members of a team can add it as an owner" }] })
+    );
+    let response = other_token.add_named_owner("foo_native_team", "registry:missing");
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "could not find the team registry:missing" }] })
+    );
+}
+
+#[test]
+fn transfer_crate_between_native_teams() {
+    let (app, anon) = TestApp::init().empty();
+    let owner = app.db_new_user("owner");
+    let owner_token = owner.db_new_token("arbitrary token name");
+
+    owner.create_team("old").good();
+    owner.create_team("new").good();
+    app.db(|conn| {
+        CrateBuilder::new("foo_team_transfer", owner.as_model().id).expect_build(conn);
+    });
+    owner_token
+        .add_named_owner("foo_team_transfer", "registry:old")
+        .good();
+
+    let body =
+        json!({ "crates": ["foo_team_transfer"], "from": "registry:old", "to": "registry:new" });
+    owner
+        .put::<OkBool>("/api/v1/me/crate_transfers", body.to_string().as_bytes())
+        .good();
+
+    let json = anon.crate_owner_teams("foo_team_transfer").good();
+    assert_eq!(json.teams.len(), 1);
+    assert_eq!(json.teams[0].login, "registry:new");
+
+    // The last individual admin can't be replaced by a team
+    let body = json!({ "crates": ["foo_team_transfer"], "to": "registry:old" });
+    let response = owner.put::<()>("/api/v1/me/crate_transfers", body.to_string().as_bytes());
+    assert_eq!(
+        response.into_json(),
+        json!({ "errors": [{ "detail": "cannot transfer crate foo_team_transfer to a team, as it would be left without an individual owner with the admin role" }] })
+    );
+}
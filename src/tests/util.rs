@@ -36,11 +36,13 @@ use std::collections::HashMap;
 mod chaosproxy;
 mod fresh_schema;
 mod github;
+mod oidc;
 mod response;
 mod test_app;
 
 pub(crate) use chaosproxy::ChaosProxy;
 pub(crate) use fresh_schema::FreshSchema;
+pub(crate) use oidc::FakeOidcProvider;
 pub use response::Response;
 pub use test_app::{TestApp, TestDatabase};
 
@@ -312,6 +314,19 @@ impl MockTokenUser {
         self.add_named_owners(krate_name, &[owner])
     }
 
+    /// Add a single owner to the specified crate with the given role, or change the role of an
+    /// existing owner.
+    pub fn add_named_owner_with_role(
+        &self,
+        krate_name: &str,
+        owner: &str,
+        role: &str,
+    ) -> Response<OkBool> {
+        let url = format!("/api/v1/crates/{krate_name}/owners");
+        let body = json!({ "owners": [owner], "role": role }).to_string();
+        self.put(&url, body.as_bytes())
+    }
+
     /// Remove from the specified crate the specified owners.
     pub fn remove_named_owners(&self, krate_name: &str, owners: &[&str]) -> Response<OkBool> {
         self.modify_owners(krate_name, owners, Self::delete_with_body)
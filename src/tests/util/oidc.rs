@@ -0,0 +1,207 @@
+//! A fake OpenID Connect provider, serving the discovery document, keys and token endpoint used by
+//! `OidcProvider` from a local port.
+
+use cargo_registry::identity::OidcProviderConfig;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const CLIENT_ID: &str = "crates-io-test";
+const KEY_ID: &str = "test-key";
+const SIGNING_KEY: &[u8] = include_bytes!("oidc-test-key.pem");
+/// Modulus of `SIGNING_KEY`, base64url encoded.
+const SIGNING_KEY_MODULUS: &str = "we7Nt2QeDwXaOGqwJfqhZozx8R8kl5S_5ii0gWGTTJJt9FkBKojVCgoH3C8bYvI2Oh1gEVPrYrX8q-qpff6DrQCqtVk6mR-zuzM0E3A0ULqybhDKPlmSEjA_IWr49Sc5-RQeMONzBTygTEtrCTRt0Y_arBxF8zeEYQjUm3rh-xoSnN_bMuwli-M6aUT0MO3VdEyXON-vUzlrLqyLtWrP0cpLL2Rgqm8ZTmPO3tOSSx1c3C2mlYYJmx93HH0bgcummGxsWjNUMj3RvIl8qPkK1M3sEhP-WbbxnrRl-fVZsIQgn8UCdoMm0YZp6nH7yt8Rmc7ZudmsruzSzEmBxF7RJw";
+
+type Logins = Arc<Mutex<HashMap<String, PendingLogin>>>;
+
+pub(crate) struct FakeOidcProvider {
+    issuer: String,
+    logins: Logins,
+}
+
+/// A login waiting for its code to be exchanged at the token endpoint.
+struct PendingLogin {
+    code_challenge: String,
+    claims: Value,
+}
+
+impl FakeOidcProvider {
+    pub(crate) fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+        let logins = Logins::default();
+
+        let server = Server {
+            issuer: issuer.clone(),
+            logins: logins.clone(),
+        };
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                server.handle(stream.unwrap());
+            }
+        });
+
+        Self { issuer, logins }
+    }
+
+    /// Returns the configuration of a provider named `name` logging in with this server.
+    pub(crate) fn config(&self, name: &str) -> OidcProviderConfig {
+        OidcProviderConfig {
+            name: name.into(),
+            display_name: format!("Fake {name}"),
+            issuer_url: self.issuer.clone(),
+            client_id: CLIENT_ID.into(),
+            client_secret: None,
+            redirect_url: "http://localhost:4200/authorize".into(),
+            scopes: vec!["profile".into(), "email".into()],
+        }
+    }
+
+    /// Simulates the user `subject` logging in after being redirected to the authorization `url`,
+    /// and returns the `code` and `state` the provider redirects back with.
+    pub(crate) fn log_in(&self, url: &str, subject: &str, username: &str) -> (String, String) {
+        let url = Url::parse(url).unwrap();
+        assert!(url.as_str().starts_with(&self.issuer));
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(params["client_id"], CLIENT_ID);
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["scope"], "openid profile email");
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let claims = json!({
+            "iss": self.issuer,
+            "aud": CLIENT_ID,
+            "sub": subject,
+            "iat": now,
+            "exp": now + 5 * 60,
+            "nonce": params["nonce"],
+            "preferred_username": username,
+            "name": format!("{username} at the fake provider"),
+            "email": format!("{username}@example.com"),
+            "email_verified": true,
+        });
+
+        let code = format!("code-{subject}");
+        let login = PendingLogin {
+            code_challenge: params["code_challenge"].clone(),
+            claims,
+        };
+        self.logins.lock().unwrap().insert(code.clone(), login);
+        (code, params["state"].clone())
+    }
+}
+
+struct Server {
+    issuer: String,
+    logins: Logins,
+}
+
+impl Server {
+    fn handle(&self, mut stream: TcpStream) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let path = request_line.split(' ').nth(1).unwrap_or_default();
+        let (status, body) = match path {
+            "/.well-known/openid-configuration" => (
+                "200 OK",
+                json!({
+                    "issuer": self.issuer,
+                    "authorization_endpoint": format!("{}/authorize", self.issuer),
+                    "token_endpoint": format!("{}/token", self.issuer),
+                    "jwks_uri": format!("{}/jwks", self.issuer),
+                }),
+            ),
+            "/jwks" => (
+                "200 OK",
+                json!({ "keys": [{
+                    "kty": "RSA",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "kid": KEY_ID,
+                    "n": SIGNING_KEY_MODULUS,
+                    "e": "AQAB",
+                }] }),
+            ),
+            "/token" => self.token(&body),
+            _ => ("404 Not Found", json!({})),
+        };
+
+        let body = body.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+    }
+
+    /// Exchanges a code for an ID token, after checking the PKCE code verifier.
+    fn token(&self, body: &[u8]) -> (&'static str, Value) {
+        let form: HashMap<_, _> = url::form_urlencoded::parse(body).into_owned().collect();
+        let invalid_grant = ("400 Bad Request", json!({ "error": "invalid_grant" }));
+
+        let login = match form.get("code") {
+            Some(code) => self.logins.lock().unwrap().remove(code),
+            None => None,
+        };
+        let login = match login {
+            Some(login) => login,
+            None => return invalid_grant,
+        };
+
+        let verifier = form
+            .get("code_verifier")
+            .map(String::as_str)
+            .unwrap_or_default();
+        let challenge = base64::encode_config(Sha256::digest(verifier), base64::URL_SAFE_NO_PAD);
+        if form.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+            || challenge != login.code_challenge
+        {
+            return invalid_grant;
+        }
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some(KEY_ID.into());
+        let key = EncodingKey::from_rsa_pem(SIGNING_KEY).unwrap();
+        let id_token = jsonwebtoken::encode(&header, &login.claims, &key).unwrap();
+
+        (
+            "200 OK",
+            json!({
+                "access_token": "fake-access-token",
+                "token_type": "Bearer",
+                "id_token": id_token,
+            }),
+        )
+    }
+}
@@ -5,6 +5,7 @@ use std::marker::PhantomData;
 use conduit::{Body, HandlerResult};
 
 use conduit::{header, StatusCode};
+use cookie::Cookie;
 
 /// A type providing helper methods for working with responses
 #[must_use]
@@ -42,10 +43,41 @@ impl<T> Response<T> {
         json(&mut self.response)
     }
 
+    /// Consume the response body and return it as text, for responses that
+    /// are not JSON
+    #[track_caller]
+    pub fn into_text(mut self) -> String {
+        String::from_utf8(take_body(&mut self.response).into_owned()).unwrap()
+    }
+
+    /// Returns the value of the `Content-Type` header
+    #[track_caller]
+    pub fn content_type(&self) -> &str {
+        self.response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .expect("Missing content-type header")
+            .to_str()
+            .unwrap()
+    }
+
     pub fn status(&self) -> StatusCode {
         self.response.status()
     }
 
+    /// Returns the session cookie set by the response, as the value of a `Cookie` header
+    #[track_caller]
+    pub fn session_cookie(&self) -> String {
+        self.response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| Cookie::parse(value.to_str().ok()?).ok())
+            .find(|cookie| cookie.name() == "cargo_session")
+            .map(|cookie| cookie.stripped().to_string())
+            .expect("Missing session cookie")
+    }
+
     #[track_caller]
     pub fn assert_redirect_ends_with(&self, target: &str) -> &Self {
         assert!(self
@@ -78,15 +110,7 @@ fn json<T>(r: &mut AppResponse) -> T
 where
     for<'de> T: serde::Deserialize<'de>,
 {
-    use conduit::Body::*;
-
-    let mut body = Body::empty();
-    std::mem::swap(r.body_mut(), &mut body);
-    let body: std::borrow::Cow<'static, [u8]> = match body {
-        Static(slice) => slice.into(),
-        Owned(vec) => vec.into(),
-        File(_) => unimplemented!(),
-    };
+    let body = take_body(r);
 
     assert_eq!(
         r.headers()
@@ -110,3 +134,15 @@ where
         Err(e) => panic!("failed to decode: {:?}", e),
     }
 }
+
+fn take_body(r: &mut AppResponse) -> std::borrow::Cow<'static, [u8]> {
+    use conduit::Body::*;
+
+    let mut body = Body::empty();
+    std::mem::swap(r.body_mut(), &mut body);
+    match body {
+        Static(slice) => slice.into(),
+        Owned(vec) => vec.into(),
+        File(_) => unimplemented!(),
+    }
+}
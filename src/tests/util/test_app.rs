@@ -336,9 +336,13 @@ fn simple_config() -> config::Server {
         gh_client_id: dotenv::var("GH_CLIENT_ID").unwrap_or_default(),
         gh_client_secret: dotenv::var("GH_CLIENT_SECRET").unwrap_or_default(),
         gh_base_url: "http://api.github.com".to_string(),
+        oidc_providers: vec![],
         max_upload_size: 3000,
         max_unpack_size: 2000,
         publish_rate_limit: Default::default(),
+        search_ranking_weights: Default::default(),
+        gh_admin_user_ids: HashSet::new(),
+        team_membership_cache: Default::default(),
         blocked_traffic: Default::default(),
         max_allowed_page_offset: 200,
         page_offset_ua_blocklist: vec![],
@@ -347,6 +351,7 @@ fn simple_config() -> config::Server {
         domain_name: "crates.io".into(),
         allowed_origins: Vec::new(),
         downloads_persist_interval_ms: 1000,
+        downloads_journal_path: None,
         ownership_invitations_expiration_days: 30,
         metrics_authorization_token: None,
         use_test_database_pool: true,
@@ -355,6 +360,8 @@ fn simple_config() -> config::Server {
         blocked_routes: HashSet::new(),
         version_id_cache_size: 10000,
         version_id_cache_ttl: Duration::from_secs(5 * 60),
+        autocomplete_cache_size: 10000,
+        autocomplete_cache_ttl: Duration::from_secs(10 * 60),
     }
 }
 
@@ -156,6 +156,9 @@ fn version_size() {
         .find(|v| v.num == "1.0.0")
         .expect("Could not find v1.0.0");
     assert_eq!(version1.crate_size, Some(35));
+    assert_eq!(version1.unpacked_size, Some(0));
+    assert_eq!(version1.file_count, Some(0));
+    assert_none!(&version1.largest_file_path);
 
     let version2 = crate_json
         .versions
@@ -165,4 +168,199 @@ fn version_size() {
         .find(|v| v.num == "2.0.0")
         .expect("Could not find v2.0.0");
     assert_eq!(version2.crate_size, Some(91));
+    assert_eq!(version2.unpacked_size, Some(1));
+    assert_eq!(version2.file_count, Some(1));
+    assert_some_eq!(&version2.largest_file_path, "big");
+    assert_eq!(version2.largest_file_size, Some(1));
+}
+
+#[test]
+fn sbom() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        let dep = CrateBuilder::new("bar_sbom", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+        CrateBuilder::new("foo_sbom", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT"))
+                    .dependency(&dep, None),
+            )
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_sbom/1.0.0/sbom";
+    let response = anon.get::<()>(url);
+    assert_eq!(response.content_type(), "application/vnd.cyclonedx+json");
+    let json: Value = serde_json::from_str(&response.into_text()).unwrap();
+    assert_eq!(json["bomFormat"], "CycloneDX");
+    assert_eq!(
+        json["metadata"]["component"]["purl"],
+        "pkg:cargo/foo_sbom@1.0.0"
+    );
+    assert_eq!(json["components"][0]["purl"], "pkg:cargo/bar_sbom@1.1.0");
+
+    let response = anon.get::<()>(&format!("{url}?format=spdx"));
+    assert_eq!(response.content_type(), "application/spdx+json");
+    let json: Value = serde_json::from_str(&response.into_text()).unwrap();
+    assert_eq!(json["spdxVersion"], "SPDX-2.3");
+    assert_eq!(json["packages"][1]["versionInfo"], "1.1.0");
+
+    let response = anon.get::<()>(&format!("{url}?format=xml"));
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn files() {
+    use cargo_registry::schema::background_jobs;
+
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_files", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    // The files are not cached yet, so the caching job is enqueued only once
+    let url = "/api/v1/crates/foo_files/1.0.0/files";
+    let response = anon.get::<()>(url);
+    assert_eq!(response.status(), http::StatusCode::ACCEPTED);
+    let response = anon.get::<()>(&format!("{url}/src/lib.rs"));
+    assert_eq!(response.status(), http::StatusCode::ACCEPTED);
+    let jobs: i64 = app.db(|conn| {
+        background_jobs::table
+            .filter(background_jobs::job_type.eq("cache_crate_files"))
+            .count()
+            .get_result(conn)
+            .unwrap()
+    });
+    assert_eq!(jobs, 1);
+
+    app.run_pending_background_jobs();
+
+    let json = anon.get::<()>(url).into_json();
+    assert_eq!(
+        json["files"],
+        json!([
+            {
+                "path": "Cargo.toml",
+                "size": 29,
+                "mode": 420,
+                "checksum": "7b6be75914a393fcc9a26e0a1b29da8f21d80c73a42002f354640f1e9cf3a6f7",
+            },
+            {
+                "path": "src/lib.rs",
+                "size": 16,
+                "mode": 420,
+                "checksum": "8f46e21779e9807d1c3d9828c172b1cac2f2b98924494a59d1512ca82ea42fb8",
+            },
+        ])
+    );
+
+    let response = anon.get::<()>(&format!("{url}/src/lib.rs"));
+    assert_eq!(response.content_type(), "text/plain; charset=utf-8");
+    assert_eq!(response.into_text(), "pub fn foo() {}\n");
+
+    anon.get::<()>(&format!("{url}/src/main.rs"))
+        .assert_not_found();
+}
+
+#[test]
+fn diff() {
+    let (app, anon, user) = TestApp::with_proxy().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_diff", user.id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+    });
+
+    // Only the changed files are downloaded from the cache
+    let url = "/api/v1/crates/foo_diff/diff/1.0.0/1.1.0";
+    let json = anon.get::<()>(url).into_json();
+    assert_eq!(
+        json["files"],
+        json!([
+            { "path": "src/lib.rs", "status": "modified", "additions": 1, "deletions": 1, "binary": false },
+            { "path": "src/new.rs", "status": "added", "additions": 1, "deletions": 0, "binary": false },
+        ])
+    );
+
+    let json = anon
+        .get_with_query::<()>(url, "path=src/lib.rs")
+        .into_json();
+    assert_eq!(
+        json["patch"],
+        "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n"
+    );
+
+    // Unchanged files have an empty patch, and are not downloaded
+    let json = anon
+        .get_with_query::<()>(url, "path=Cargo.toml")
+        .into_json();
+    assert_eq!(json["patch"], "");
+
+    anon.get_with_query::<()>(url, "path=src/missing.rs")
+        .assert_not_found();
+}
+
+#[test]
+fn resolve() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        let baz = CrateBuilder::new("baz_resolve", user.id)
+            .version("0.1.0")
+            .expect_build(conn);
+        let bar = CrateBuilder::new("bar_resolve", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&baz, None))
+            .expect_build(conn);
+        let winapi = CrateBuilder::new("winapi_resolve", user.id)
+            .version("0.3.0")
+            .expect_build(conn);
+        CrateBuilder::new("foo_resolve", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency(&bar, None)
+                    .dependency(&winapi, Some("cfg(windows)")),
+            )
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_resolve/1.0.0/resolve";
+    let json = anon.get::<()>(url).into_json();
+    let packages = json["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            format!(
+                "{} {}",
+                p["name"].as_str().unwrap(),
+                p["version"].as_str().unwrap()
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        packages,
+        vec![
+            "bar_resolve 1.0.0",
+            "baz_resolve 0.1.0",
+            "foo_resolve 1.0.0",
+            "winapi_resolve 0.3.0"
+        ]
+    );
+
+    let json = anon
+        .get::<()>(&format!("{url}?target=x86_64-unknown-linux-gnu"))
+        .into_json();
+    assert_eq!(json["packages"].as_array().unwrap().len(), 3);
+
+    let response = anon.get::<()>(&format!("{url}?target=not-a-target"));
+    assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
 }
@@ -1,5 +1,6 @@
 use anyhow::Result;
-use reqwest::{blocking::Client, header};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::{blocking::Client, header, StatusCode};
 
 use crate::util::errors::{internal, AppResult};
 
@@ -13,6 +14,22 @@ use crate::models::Crate;
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
 const CACHE_CONTROL_INDEX: &str = "public,max-age=600";
+const CONTENT_TYPE_CRATE_FILE: &str = "application/octet-stream";
+
+/// Characters that are percent-encoded in the segments of storage paths.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'+')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
 
 #[derive(Clone, Debug)]
 pub enum Uploader {
@@ -87,6 +104,26 @@ impl Uploader {
         format!("readmes/{name}/{name}-{version}.html")
     }
 
+    /// Returns the internal path of the cached listing of the files inside an
+    /// uploaded crate's version archive.
+    fn crate_files_listing_path(name: &str, version: &str) -> String {
+        format!("crate-files/{name}/{name}-{version}.json")
+    }
+
+    /// Returns the internal path of a cached file extracted from an uploaded
+    /// crate's version archive.
+    ///
+    /// Each segment of the path inside the archive is percent-encoded, since
+    /// it may contain characters like `?` or `#` that can't appear in URLs.
+    fn crate_file_path(name: &str, version: &str, path: &str) -> String {
+        let path = path
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("crate-files/{name}/{name}-{version}/{path}")
+    }
+
     /// Returns the internal path of an uploaded crate's index file.
     fn index_path(name: &str) -> String {
         cargo_registry_index::Repository::relative_index_file_for_url(name)
@@ -155,6 +192,27 @@ impl Uploader {
         }
     }
 
+    /// Downloads a file using the configured uploader (either `S3`, `Local`).
+    ///
+    /// Returns `None` if the file does not exist.
+    pub fn download(&self, client: &Client, path: &str) -> Result<Option<Vec<u8>>> {
+        match *self {
+            Uploader::S3 { ref bucket, .. } => match bucket.get(client, path) {
+                Ok(response) => Ok(Some(response.bytes()?.to_vec())),
+                Err(e) if e.status() == Some(StatusCode::NOT_FOUND) => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+            Uploader::Local => {
+                let filename = Self::local_uploads_path(path, UploadBucket::Default);
+                match fs::read(&filename) {
+                    Ok(content) => Ok(Some(content)),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                    Err(e) => Err(e.into()),
+                }
+            }
+        }
+    }
+
     /// Deletes a file using the configured uploader (either `S3`, `Local`).
     pub fn delete(&self, client: &Client, path: &str, upload_bucket: UploadBucket) -> Result<()> {
         match *self {
@@ -234,6 +292,88 @@ impl Uploader {
         Ok(())
     }
 
+    pub(crate) fn download_crate(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let path = Uploader::crate_path(crate_name, vers);
+        self.download(http_client, &path)
+    }
+
+    pub(crate) fn download_crate_files_listing(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let path = Uploader::crate_files_listing_path(crate_name, vers);
+        self.download(http_client, &path)
+    }
+
+    pub(crate) fn download_crate_file(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        file_path: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let path = Uploader::crate_file_path(crate_name, vers, file_path);
+        self.download(http_client, &path)
+    }
+
+    pub(crate) fn upload_crate_files_listing(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        listing: String,
+    ) -> Result<()> {
+        let path = Uploader::crate_files_listing_path(crate_name, vers);
+        let content = Cursor::new(listing);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static(CACHE_CONTROL_IMMUTABLE),
+        );
+        self.upload(
+            http_client,
+            &path,
+            content,
+            "application/json",
+            extra_headers,
+            UploadBucket::Default,
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn upload_crate_file(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        file_path: &str,
+        content: Vec<u8>,
+    ) -> Result<()> {
+        let path = Uploader::crate_file_path(crate_name, vers, file_path);
+        let content = Cursor::new(content);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(
+            header::CACHE_CONTROL,
+            header::HeaderValue::from_static(CACHE_CONTROL_IMMUTABLE),
+        );
+        self.upload(
+            http_client,
+            &path,
+            content,
+            CONTENT_TYPE_CRATE_FILE,
+            extra_headers,
+            UploadBucket::Default,
+        )?;
+        Ok(())
+    }
+
     pub(crate) fn upload_index(
         &self,
         http_client: &Client,
@@ -277,3 +417,20 @@ impl Uploader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_file_path_encodes_segments() {
+        assert_eq!(
+            Uploader::crate_file_path("foo", "1.0.0", "src/lib.rs"),
+            "crate-files/foo/foo-1.0.0/src/lib.rs"
+        );
+        assert_eq!(
+            Uploader::crate_file_path("foo", "1.0.0", "tests/a b?#%.rs"),
+            "crate-files/foo/foo-1.0.0/tests/a%20b%3F%23%25.rs"
+        );
+    }
+}
@@ -1,12 +1,13 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use std::collections::HashMap;
 use url::Url;
 
 use crate::github;
 use crate::models::{
-    Badge, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    Advisory, AgentDownloads, Badge, Category, Crate, CrateOwnerInvitation, CrateWebhook,
+    CreatedApiToken, Dependency, DependencyKind, Keyword, LinkedIdentity, MonthlyDownloads, Owner,
+    OwnerRole, ReverseDependency, Team, TeamRole, TopVersions, User, Version, VersionDownload,
+    VersionOwnerAction, WebhookDelivery,
 };
 use crate::util::rfc3339;
 
@@ -14,6 +15,44 @@ use crate::util::rfc3339;
 /// and are possibly of malicious intent e.g. ad tracking networks, etc.
 const DOCUMENTATION_BLOCKLIST: &[&str] = &["rust-ci.org", "rustless.org", "ironframework.io"];
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdvisory {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    /// The published versions of the crate affected by this advisory
+    pub versions: Vec<String>,
+}
+
+impl EncodableAdvisory {
+    pub fn from(advisory: Advisory, versions: Vec<String>) -> Self {
+        let Advisory {
+            id,
+            title,
+            description,
+            url,
+            date,
+            patched,
+            unaffected,
+            ..
+        } = advisory;
+        Self {
+            id,
+            title,
+            description,
+            url,
+            date,
+            patched,
+            unaffected,
+            versions,
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct EncodableBadge {
     pub badge_type: String,
@@ -164,6 +203,48 @@ impl EncodableDependency {
     }
 }
 
+/// A crate depending on another crate, described by the newest version of the
+/// dependent crate.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependent {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+    pub target: Option<String>,
+    pub downloads: i32,
+}
+
+impl EncodableDependent {
+    /// Column names used when serializing dependents as CSV.
+    pub const CSV_HEADER: [&'static str; 7] = [
+        "crate",
+        "version",
+        "req",
+        "kind",
+        "optional",
+        "target",
+        "downloads",
+    ];
+}
+
+impl From<ReverseDependency> for EncodableDependent {
+    fn from(rev_dep: ReverseDependency) -> Self {
+        let dependency = rev_dep.dependency;
+        Self {
+            krate: rev_dep.name,
+            version: rev_dep.version_num,
+            req: dependency.req,
+            kind: dependency.kind,
+            optional: dependency.optional,
+            target: dependency.target,
+            downloads: rev_dep.crate_downloads,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersionDownload {
     pub version: i32,
@@ -181,6 +262,44 @@ impl From<VersionDownload> for EncodableVersionDownload {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAgentDownloads {
+    pub agent: String,
+    pub downloads: i64,
+}
+
+impl From<AgentDownloads> for EncodableAgentDownloads {
+    fn from(downloads: AgentDownloads) -> Self {
+        Self {
+            agent: downloads.agent_class,
+            downloads: downloads.downloads,
+        }
+    }
+}
+
+/// Completions of a prefix returned by the `GET /autocomplete` route.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodableAutocomplete {
+    pub crates: Vec<String>,
+    pub keywords: Vec<String>,
+    pub categories: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableMonthlyDownload {
+    pub month: String,
+    pub downloads: i64,
+}
+
+impl From<MonthlyDownloads> for EncodableMonthlyDownload {
+    fn from(downloads: MonthlyDownloads) -> Self {
+        Self {
+            month: downloads.month,
+            downloads: downloads.downloads,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableKeyword {
     pub id: String,
@@ -232,6 +351,10 @@ pub struct EncodableCrate {
     pub repository: Option<String>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
+    /// Excerpts of the README matching the search query, with the matches wrapped in `<mark>`.
+    /// Only included in search results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub readme_snippet: Option<String>,
 }
 
 impl EncodableCrate {
@@ -297,6 +420,7 @@ impl EncodableCrate {
             documentation,
             homepage,
             exact_match,
+            readme_snippet: None,
             description,
             repository,
             links: EncodableCrateLinks {
@@ -376,6 +500,8 @@ pub struct EncodableOwner {
     pub url: Option<String>,
     pub name: Option<String>,
     pub avatar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<OwnerRole>,
 }
 
 impl From<Owner> for EncodableOwner {
@@ -396,6 +522,7 @@ impl From<Owner> for EncodableOwner {
                     url: Some(url),
                     name,
                     kind: String::from("user"),
+                    role: None,
                 }
             }
             Owner::Team(Team {
@@ -403,23 +530,26 @@ impl From<Owner> for EncodableOwner {
                 name,
                 login,
                 avatar,
+                github_id,
                 ..
             }) => {
-                let url = github::team_url(&login);
+                // Registry-native teams don't have a page on GitHub
+                let url = github_id.map(|_| github::team_url(&login));
                 Self {
                     id,
                     login,
-                    url: Some(url),
+                    url,
                     avatar,
                     name,
                     kind: String::from("team"),
+                    role: None,
                 }
             }
         }
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableTeam {
     pub id: i32,
     pub login: String,
@@ -435,20 +565,28 @@ impl From<Team> for EncodableTeam {
             name,
             login,
             avatar,
+            github_id,
             ..
         } = team;
-        let url = github::team_url(&login);
+        let url = github_id.map(|_| github::team_url(&login));
 
         EncodableTeam {
             id,
             login,
             name,
             avatar,
-            url: Some(url),
+            url,
         }
     }
 }
 
+/// A member of a registry-native team, returned by the `GET /teams/:team_id/members` route.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableTeamMember {
+    pub login: String,
+    pub role: TeamRole,
+}
+
 /// The serialization format for the `ApiToken` model with its token value.
 /// This should only be used when initially creating a new token to minimize
 /// the chance of token leaks.
@@ -567,6 +705,38 @@ impl From<User> for EncodablePublicUser {
     }
 }
 
+/// The serialization format for the `LinkedIdentity` model.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct EncodableLinkedIdentity {
+    pub provider: String,
+    pub login: String,
+    pub email: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub last_login_at: NaiveDateTime,
+}
+
+impl From<LinkedIdentity> for EncodableLinkedIdentity {
+    fn from(identity: LinkedIdentity) -> Self {
+        Self {
+            provider: identity.provider,
+            login: identity.login,
+            email: identity.email,
+            created_at: identity.created_at,
+            last_login_at: identity.last_login_at,
+        }
+    }
+}
+
+/// An identity provider users can log in with, returned by the
+/// `GET /api/private/session/providers` route.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+pub struct EncodableIdentityProvider {
+    pub name: String,
+    pub display_name: String,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableAuditAction {
     pub action: String,
@@ -595,8 +765,15 @@ pub struct EncodableVersion {
     pub license: Option<String>,
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
+    /// Total size of the files inside the `.crate` archive, once unpacked
+    pub unpacked_size: Option<i64>,
+    pub file_count: Option<i32>,
+    pub largest_file_path: Option<String>,
+    pub largest_file_size: Option<i64>,
     pub published_by: Option<EncodablePublicUser>,
     pub audit_actions: Vec<EncodableAuditAction>,
+    /// Ids of the security advisories affecting this version
+    pub advisories: Vec<String>,
 }
 
 impl EncodableVersion {
@@ -605,6 +782,7 @@ impl EncodableVersion {
         crate_name: &str,
         published_by: Option<User>,
         audit_actions: Vec<(VersionOwnerAction, User)>,
+        advisories: Vec<String>,
     ) -> Self {
         let Version {
             id,
@@ -616,6 +794,10 @@ impl EncodableVersion {
             yanked,
             license,
             crate_size,
+            unpacked_size,
+            file_count,
+            largest_file_path,
+            largest_file_size,
             ..
         } = version;
 
@@ -639,6 +821,10 @@ impl EncodableVersion {
             license,
             links,
             crate_size,
+            unpacked_size,
+            file_count,
+            largest_file_path,
+            largest_file_size,
             published_by: published_by.map(User::into),
             audit_actions: audit_actions
                 .into_iter()
@@ -648,6 +834,7 @@ impl EncodableVersion {
                     time: audit_action.time,
                 })
                 .collect(),
+            advisories,
         }
     }
 }
@@ -673,9 +860,61 @@ pub struct PublishWarnings {
     pub other: Vec<String>,
 }
 
+/// The serialization format for the `CrateWebhook` model.
+///
+/// The secret is never included in responses.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableWebhook {
+    pub id: i32,
+    pub url: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+impl From<CrateWebhook> for EncodableWebhook {
+    fn from(webhook: CrateWebhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableWebhookDelivery {
+    pub id: i64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+impl From<WebhookDelivery> for EncodableWebhookDelivery {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            event: delivery.event,
+            payload: delivery.payload,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            error: delivery.error,
+            created_at: delivery.created_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
 pub mod krate_publish;
 pub use self::krate_publish::{EncodableCrateDependency, EncodableCrateUpload};
 
+pub mod sbom;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -749,6 +988,10 @@ mod tests {
                 authors: "".to_string(),
             },
             crate_size: Some(1234),
+            unpacked_size: Some(4321),
+            file_count: Some(3),
+            largest_file_path: Some("src/lib.rs".to_string()),
+            largest_file_size: Some(4000),
             published_by: None,
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
@@ -761,6 +1004,7 @@ mod tests {
                 },
                 time: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             }],
+            advisories: vec![],
         };
         let json = serde_json::to_string(&ver).unwrap();
         assert_some!(json
@@ -801,6 +1045,7 @@ mod tests {
                 reverse_dependencies: "".to_string(),
             },
             exact_match: false,
+            readme_snippet: None,
         };
         let json = serde_json::to_string(&crt).unwrap();
         assert_some!(json
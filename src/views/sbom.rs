@@ -0,0 +1,468 @@
+//! Software bills of materials (SBOMs) describing a single version of a
+//! crate and its direct dependencies.
+//!
+//! Both [CycloneDX 1.4](https://cyclonedx.org/docs/1.4/json/) and
+//! [SPDX 2.3](https://spdx.github.io/spdx-spec/v2.3/) documents are
+//! generated in their JSON serialization. Dependencies are described by the
+//! newest non-yanked version matching their requirement at the time of the
+//! request, since the exact versions are only known to the lockfile of the
+//! consumer.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::Value;
+
+use crate::models::{Crate, Dependency, DependencyKind, Version};
+
+const TOOL_NAME: &str = "crates.io";
+
+/// A direct dependency of the described version.
+pub struct SbomDependency {
+    pub dependency: Dependency,
+    pub crate_name: String,
+    /// The newest non-yanked version matching the requirement, if any.
+    pub resolved: Option<semver::Version>,
+}
+
+impl SbomDependency {
+    fn version(&self) -> Option<String> {
+        self.resolved.as_ref().map(ToString::to_string)
+    }
+
+    fn purl(&self) -> String {
+        match &self.resolved {
+            Some(version) => purl(&self.crate_name, &version.to_string()),
+            None => format!("pkg:cargo/{}", self.crate_name),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self.dependency.kind {
+            DependencyKind::Normal => "normal",
+            DependencyKind::Build => "build",
+            DependencyKind::Dev => "dev",
+        }
+    }
+}
+
+/// The version a document is generated for.
+pub struct SbomSubject<'a> {
+    pub krate: &'a Crate,
+    pub version: &'a Version,
+    /// The domain of the registry, used for download locations and
+    /// document namespaces.
+    pub domain_name: &'a str,
+}
+
+impl SbomSubject<'_> {
+    fn purl(&self) -> String {
+        purl(&self.krate.name, &self.version.num)
+    }
+
+    fn download_url(&self) -> String {
+        format!(
+            "https://{}/api/v1/crates/{}/{}/download",
+            self.domain_name, self.krate.name, self.version.num
+        )
+    }
+}
+
+/// Builds a CycloneDX 1.4 document.
+pub fn cyclonedx(subject: &SbomSubject<'_>, dependencies: &[SbomDependency]) -> Value {
+    let SbomSubject { krate, version, .. } = subject;
+    let root_ref = subject.purl();
+
+    let mut root = json!({
+        "type": "library",
+        "bom-ref": root_ref,
+        "name": krate.name,
+        "version": version.num,
+        "purl": root_ref,
+    });
+    if let Some(description) = &krate.description {
+        root["description"] = json!(description);
+    }
+    if let Some(license) = &version.license {
+        root["licenses"] = json!([{ "expression": license }]);
+    }
+    if let Some(checksum) = &version.checksum {
+        root["hashes"] = json!([{ "alg": "SHA-256", "content": checksum }]);
+    }
+    let mut external_references = vec![json!({
+        "type": "distribution",
+        "url": subject.download_url(),
+    })];
+    let links = [
+        ("vcs", &krate.repository),
+        ("website", &krate.homepage),
+        ("documentation", &krate.documentation),
+    ];
+    for (kind, url) in links {
+        if let Some(url) = url {
+            external_references.push(json!({ "type": kind, "url": url }));
+        }
+    }
+    root["externalReferences"] = json!(external_references);
+
+    let mut refs = HashSet::new();
+    refs.insert(root_ref.clone());
+
+    let mut components = Vec::with_capacity(dependencies.len());
+    let mut depends_on = Vec::with_capacity(dependencies.len());
+    for dep in dependencies {
+        // The same crate can be depended on several times, e.g. as both a
+        // normal and a dev dependency, but references have to be unique.
+        let mut bom_ref = dep.purl();
+        let mut suffix = 1;
+        while !refs.insert(bom_ref.clone()) {
+            suffix += 1;
+            bom_ref = format!("{}#{}", dep.purl(), suffix);
+        }
+
+        let scope = match (dep.dependency.kind, dep.dependency.optional) {
+            (DependencyKind::Dev, _) => "excluded",
+            (_, true) => "optional",
+            (_, false) => "required",
+        };
+
+        let mut properties = vec![
+            json!({ "name": "cargo:requirement", "value": dep.dependency.req }),
+            json!({ "name": "cargo:kind", "value": dep.kind() }),
+        ];
+        if let Some(target) = &dep.dependency.target {
+            properties.push(json!({ "name": "cargo:target", "value": target }));
+        }
+
+        let mut component = json!({
+            "type": "library",
+            "bom-ref": bom_ref,
+            "name": dep.crate_name,
+            "purl": dep.purl(),
+            "scope": scope,
+            "properties": properties,
+        });
+        if let Some(version) = dep.version() {
+            component["version"] = json!(version);
+        }
+
+        components.push(component);
+        depends_on.push(bom_ref);
+    }
+
+    let mut graph = vec![json!({ "ref": root_ref, "dependsOn": depends_on })];
+    graph.extend(
+        depends_on
+            .iter()
+            .map(|bom_ref| json!({ "ref": bom_ref, "dependsOn": [] })),
+    );
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.4",
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp(version.created_at),
+            "tools": [{ "vendor": TOOL_NAME, "name": TOOL_NAME }],
+            "component": root,
+        },
+        "components": components,
+        "dependencies": graph,
+    })
+}
+
+/// Builds an SPDX 2.3 document.
+pub fn spdx(subject: &SbomSubject<'_>, dependencies: &[SbomDependency]) -> Value {
+    let SbomSubject {
+        krate,
+        version,
+        domain_name,
+    } = subject;
+    let root_id = spdx_id(&format!("Package-{}-{}", krate.name, version.num));
+
+    let mut root = json!({
+        "name": krate.name,
+        "SPDXID": root_id,
+        "versionInfo": version.num,
+        "downloadLocation": subject.download_url(),
+        "filesAnalyzed": false,
+        "licenseConcluded": "NOASSERTION",
+        "licenseDeclared": version.license.as_deref().unwrap_or("NOASSERTION"),
+        "copyrightText": "NOASSERTION",
+        "externalRefs": [package_manager_ref(&subject.purl())],
+    });
+    if let Some(description) = &krate.description {
+        root["description"] = json!(description);
+    }
+    if let Some(homepage) = &krate.homepage {
+        root["homepage"] = json!(homepage);
+    }
+    if let Some(checksum) = &version.checksum {
+        root["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": checksum }]);
+    }
+
+    let mut packages = vec![root];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": root_id,
+    })];
+
+    for (i, dep) in dependencies.iter().enumerate() {
+        let id = spdx_id(&format!("Dependency-{}-{}", i + 1, dep.crate_name));
+
+        packages.push(json!({
+            "name": dep.crate_name,
+            "SPDXID": id,
+            "versionInfo": dep.version().unwrap_or_else(|| dep.dependency.req.clone()),
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": "NOASSERTION",
+            "copyrightText": "NOASSERTION",
+            "externalRefs": [package_manager_ref(&dep.purl())],
+        }));
+
+        let relationship = match (dep.dependency.kind, dep.dependency.optional) {
+            (DependencyKind::Dev, _) => (id.as_str(), "DEV_DEPENDENCY_OF", root_id.as_str()),
+            (DependencyKind::Build, _) => (id.as_str(), "BUILD_DEPENDENCY_OF", root_id.as_str()),
+            (DependencyKind::Normal, true) => {
+                (id.as_str(), "OPTIONAL_DEPENDENCY_OF", root_id.as_str())
+            }
+            (DependencyKind::Normal, false) => (root_id.as_str(), "DEPENDS_ON", id.as_str()),
+        };
+        let (element, relationship_type, related) = relationship;
+        relationships.push(json!({
+            "spdxElementId": element,
+            "relationshipType": relationship_type,
+            "relatedSpdxElement": related,
+        }));
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{}-{}", krate.name, version.num),
+        "documentNamespace": format!(
+            "https://{}/api/v1/crates/{}/{}/sbom?format=spdx",
+            domain_name, krate.name, version.num
+        ),
+        "creationInfo": {
+            "created": timestamp(version.created_at),
+            "creators": [format!("Tool: {TOOL_NAME}")],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:cargo/{name}@{version}")
+}
+
+fn package_manager_ref(purl: &str) -> Value {
+    json!({
+        "referenceCategory": "PACKAGE-MANAGER",
+        "referenceType": "purl",
+        "referenceLocator": purl,
+    })
+}
+
+/// SPDX identifiers may only contain letters, numbers, `.` and `-`.
+fn spdx_id(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '-',
+        })
+        .collect::<String>();
+    format!("SPDXRef-{name}")
+}
+
+fn timestamp(dt: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(dt, Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn krate() -> Crate {
+        let created_at = NaiveDate::from_ymd(2022, 8, 8).and_hms(8, 30, 12);
+        Crate {
+            id: 1,
+            name: "foo_sbom".to_string(),
+            updated_at: created_at,
+            created_at,
+            downloads: 0,
+            description: Some("A crate".to_string()),
+            homepage: None,
+            documentation: None,
+            repository: Some("https://github.com/rust-lang/foo".to_string()),
+            max_upload_size: None,
+        }
+    }
+
+    fn version() -> Version {
+        let created_at = NaiveDate::from_ymd(2022, 8, 8).and_hms(8, 30, 12);
+        Version {
+            id: 1,
+            crate_id: 1,
+            num: "1.0.0".to_string(),
+            updated_at: created_at,
+            created_at,
+            downloads: 0,
+            features: json!({}),
+            yanked: false,
+            license: Some("MIT OR Apache-2.0".to_string()),
+            crate_size: None,
+            published_by: None,
+            unpacked_size: None,
+            file_count: None,
+            largest_file_path: None,
+            largest_file_size: None,
+            checksum: Some("abc123".to_string()),
+        }
+    }
+
+    fn dependency(
+        id: i32,
+        name: &str,
+        kind: DependencyKind,
+        optional: bool,
+        resolved: Option<&str>,
+    ) -> SbomDependency {
+        SbomDependency {
+            dependency: Dependency {
+                id,
+                version_id: 1,
+                crate_id: id + 1,
+                req: "^1.0".to_string(),
+                optional,
+                default_features: true,
+                features: vec![],
+                target: None,
+                kind,
+                explicit_name: None,
+            },
+            crate_name: name.to_string(),
+            resolved: resolved.map(|v| semver::Version::parse(v).unwrap()),
+        }
+    }
+
+    fn dependencies() -> Vec<SbomDependency> {
+        vec![
+            dependency(1, "serde", DependencyKind::Normal, false, Some("1.0.143")),
+            dependency(
+                2,
+                "serde_json",
+                DependencyKind::Normal,
+                true,
+                Some("1.0.83"),
+            ),
+            dependency(3, "serde", DependencyKind::Dev, false, Some("1.0.143")),
+            dependency(4, "cc", DependencyKind::Build, false, None),
+        ]
+    }
+
+    #[test]
+    fn cyclonedx_document() {
+        let (krate, version) = (krate(), version());
+        let subject = SbomSubject {
+            krate: &krate,
+            version: &version,
+            domain_name: "crates.io",
+        };
+        let bom = cyclonedx(&subject, &dependencies());
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["timestamp"], "2022-08-08T08:30:12Z");
+        let root = &bom["metadata"]["component"];
+        assert_eq!(root["purl"], "pkg:cargo/foo_sbom@1.0.0");
+        assert_eq!(root["licenses"][0]["expression"], "MIT OR Apache-2.0");
+        assert_eq!(root["hashes"][0]["content"], "abc123");
+        assert_eq!(root["externalReferences"][1]["type"], "vcs");
+
+        let components = bom["components"].as_array().unwrap();
+        let summary = components
+            .iter()
+            .map(|c| {
+                (
+                    c["bom-ref"].as_str().unwrap(),
+                    c["scope"].as_str().unwrap(),
+                    c["version"].as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("pkg:cargo/serde@1.0.143", "required", Some("1.0.143")),
+                ("pkg:cargo/serde_json@1.0.83", "optional", Some("1.0.83")),
+                ("pkg:cargo/serde@1.0.143#2", "excluded", Some("1.0.143")),
+                ("pkg:cargo/cc", "required", None),
+            ]
+        );
+        assert_eq!(
+            bom["dependencies"][0]["dependsOn"]
+                .as_array()
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn spdx_document() {
+        let (krate, version) = (krate(), version());
+        let subject = SbomSubject {
+            krate: &krate,
+            version: &version,
+            domain_name: "crates.io",
+        };
+        let doc = spdx(&subject, &dependencies());
+
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(
+            doc["documentNamespace"],
+            "https://crates.io/api/v1/crates/foo_sbom/1.0.0/sbom?format=spdx"
+        );
+        assert_eq!(
+            doc["packages"][0]["SPDXID"],
+            "SPDXRef-Package-foo-sbom-1.0.0"
+        );
+        assert_eq!(doc["packages"][0]["licenseDeclared"], "MIT OR Apache-2.0");
+        assert_eq!(doc["packages"][4]["versionInfo"], "^1.0");
+
+        let relationships = doc["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["spdxElementId"].as_str().unwrap(),
+                    r["relationshipType"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let root = "SPDXRef-Package-foo-sbom-1.0.0";
+        assert_eq!(
+            relationships,
+            vec![
+                ("SPDXRef-DOCUMENT", "DESCRIBES", root),
+                (root, "DEPENDS_ON", "SPDXRef-Dependency-1-serde"),
+                (
+                    "SPDXRef-Dependency-2-serde-json",
+                    "OPTIONAL_DEPENDENCY_OF",
+                    root
+                ),
+                ("SPDXRef-Dependency-3-serde", "DEV_DEPENDENCY_OF", root),
+                ("SPDXRef-Dependency-4-cc", "BUILD_DEPENDENCY_OF", root),
+            ]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Import security advisories from a local checkout of a RustSec-format
+//! advisory database.
+//!
+//! Each advisory is a markdown file at `crates/<crate name>/<id>.md` that
+//! starts with a fenced TOML block containing the advisory metadata. The
+//! first heading of the markdown body is used as the title and the remaining
+//! text as the description.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use chrono::NaiveDate;
+use diesel::dsl::all;
+use diesel::prelude::*;
+use semver::VersionReq;
+use swirl::PerformError;
+
+use crate::models::advisory::is_affected;
+use crate::models::{Crate, NewAdvisory};
+use crate::schema::{advisories, crates, version_advisories, versions};
+
+#[swirl::background_job]
+pub fn import_advisories(conn: &PgConnection, path: String) -> Result<(), PerformError> {
+    let advisories = load_advisories(Path::new(&path))?;
+    // An empty database is much more likely to be a wrong path or a broken
+    // checkout than the real thing, and importing it would remove every
+    // advisory.
+    if advisories.is_empty() {
+        return Err(format!("No advisories found in {path}, refusing to import").into());
+    }
+
+    println!("Importing {} advisories from {}", advisories.len(), path);
+    import(conn, &advisories)?;
+    println!("Finished importing advisories");
+    Ok(())
+}
+
+#[derive(Debug)]
+struct ParsedAdvisory {
+    id: String,
+    package: String,
+    title: String,
+    description: String,
+    url: Option<String>,
+    date: NaiveDate,
+    patched: Vec<String>,
+    unaffected: Vec<String>,
+    patched_reqs: Vec<VersionReq>,
+    unaffected_reqs: Vec<VersionReq>,
+}
+
+impl ParsedAdvisory {
+    fn affects(&self, version: &str) -> bool {
+        semver::Version::parse(version)
+            .map(|version| is_affected(&version, &self.patched_reqs, &self.unaffected_reqs))
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Deserialize)]
+struct FrontMatter {
+    advisory: AdvisorySection,
+    #[serde(default)]
+    versions: VersionsSection,
+}
+
+#[derive(Deserialize)]
+struct AdvisorySection {
+    id: String,
+    package: String,
+    date: String,
+    url: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    withdrawn: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct VersionsSection {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Reads all advisories from `<path>/crates/*/*.md`, skipping withdrawn ones.
+fn load_advisories(path: &Path) -> anyhow::Result<Vec<ParsedAdvisory>> {
+    let crates_dir = path.join("crates");
+    let mut files = Vec::new();
+    for crate_dir in read_dir_sorted(&crates_dir)? {
+        if !crate_dir.is_dir() {
+            continue;
+        }
+        for file in read_dir_sorted(&crate_dir)? {
+            if file.extension().and_then(|ext| ext.to_str()) == Some("md") {
+                files.push(file);
+            }
+        }
+    }
+
+    let mut advisories = Vec::with_capacity(files.len());
+    for file in files {
+        let contents = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let advisory = parse_advisory(&contents)
+            .with_context(|| format!("Failed to parse {}", file.display()))?;
+        if let Some(advisory) = advisory {
+            advisories.push(advisory);
+        }
+    }
+    Ok(advisories)
+}
+
+fn read_dir_sorted(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(path)
+        .with_context(|| format!("Failed to read directory {}", path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    Ok(paths)
+}
+
+/// Parses a single advisory file. Returns `None` if the advisory was withdrawn.
+fn parse_advisory(contents: &str) -> anyhow::Result<Option<ParsedAdvisory>> {
+    let rest = contents
+        .trim_start()
+        .strip_prefix("```toml")
+        .ok_or_else(|| anyhow!("Advisory does not start with a ```toml block"))?;
+    let (front_matter, body) = rest
+        .split_once("\n```")
+        .ok_or_else(|| anyhow!("Unterminated ```toml block"))?;
+
+    let FrontMatter { advisory, versions } = toml::from_str(front_matter)?;
+    if advisory.withdrawn.is_some() {
+        return Ok(None);
+    }
+
+    let body = body.trim();
+    let (body_title, body_description) = match body.strip_prefix("# ") {
+        Some(rest) => {
+            let (title, description) = rest.split_once('\n').unwrap_or((rest, ""));
+            (Some(title.trim()), description.trim())
+        }
+        None => (None, body),
+    };
+
+    let title = advisory
+        .title
+        .or_else(|| body_title.map(String::from))
+        .ok_or_else(|| anyhow!("Advisory {} has no title", advisory.id))?;
+    let description = advisory
+        .description
+        .unwrap_or_else(|| body_description.to_string());
+
+    let date = NaiveDate::parse_from_str(&advisory.date, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date `{}`", advisory.date))?;
+
+    let parse_reqs = |reqs: &[String]| {
+        reqs.iter()
+            .map(|req| {
+                VersionReq::parse(req).with_context(|| format!("Invalid version req `{req}`"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    };
+    let patched_reqs = parse_reqs(&versions.patched)?;
+    let unaffected_reqs = parse_reqs(&versions.unaffected)?;
+
+    Ok(Some(ParsedAdvisory {
+        id: advisory.id,
+        package: advisory.package,
+        title,
+        description,
+        url: advisory.url,
+        date,
+        patched: versions.patched,
+        unaffected: versions.unaffected,
+        patched_reqs,
+        unaffected_reqs,
+    }))
+}
+
+/// Stores the advisories and links them to the affected versions. Advisories
+/// that are no longer part of the advisory database are removed.
+///
+/// Everything happens in a single transaction, so that an error can't leave
+/// the advisories partially synced.
+fn import(conn: &PgConnection, parsed: &[ParsedAdvisory]) -> QueryResult<()> {
+    conn.transaction(|| {
+        for advisory in parsed {
+            let new_advisory = NewAdvisory {
+                id: &advisory.id,
+                crate_name: &advisory.package,
+                title: &advisory.title,
+                description: &advisory.description,
+                url: advisory.url.as_deref(),
+                date: advisory.date,
+                patched: &advisory.patched,
+                unaffected: &advisory.unaffected,
+            };
+            diesel::insert_into(advisories::table)
+                .values(&new_advisory)
+                .on_conflict(advisories::id)
+                .do_update()
+                .set((&new_advisory, advisories::imported_at.eq(diesel::dsl::now)))
+                .execute(conn)?;
+
+            diesel::delete(
+                version_advisories::table.filter(version_advisories::advisory_id.eq(&advisory.id)),
+            )
+            .execute(conn)?;
+
+            let versions: Vec<(i32, String)> = versions::table
+                .inner_join(crates::table)
+                .filter(Crate::with_name(&advisory.package))
+                .select((versions::id, versions::num))
+                .load(conn)?;
+
+            let affected = versions
+                .into_iter()
+                .filter(|(_, num)| advisory.affects(num))
+                .map(|(version_id, _)| {
+                    (
+                        version_advisories::version_id.eq(version_id),
+                        version_advisories::advisory_id.eq(&advisory.id),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            diesel::insert_into(version_advisories::table)
+                .values(&affected)
+                .execute(conn)?;
+        }
+
+        let ids = parsed.iter().map(|a| a.id.as_str()).collect::<Vec<_>>();
+        diesel::delete(advisories::table.filter(advisories::id.ne(all(ids)))).execute(conn)?;
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::Emails;
+    use crate::models::{Advisory, NewCrate, NewUser, NewVersion, Version};
+    use std::collections::HashMap;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2022-0001"
+package = "foo"
+date = "2022-01-15"
+url = "https://example.com/foo/issues/1"
+
+[versions]
+patched = [">= 1.2.0"]
+unaffected = ["< 1.0.0"]
+```
+
+# Memory corruption in `foo`
+
+Calling `foo::bar` with an empty slice
+reads out of bounds.
+"#;
+
+    #[test]
+    fn parse() {
+        let advisory = parse_advisory(ADVISORY).unwrap().unwrap();
+        assert_eq!(advisory.id, "RUSTSEC-2022-0001");
+        assert_eq!(advisory.package, "foo");
+        assert_eq!(advisory.title, "Memory corruption in `foo`");
+        assert_eq!(
+            advisory.description,
+            "Calling `foo::bar` with an empty slice\nreads out of bounds."
+        );
+        assert_eq!(advisory.date, NaiveDate::from_ymd(2022, 1, 15));
+        assert!(!advisory.affects("0.9.0"));
+        assert!(advisory.affects("1.1.9"));
+        assert!(!advisory.affects("1.2.0"));
+    }
+
+    #[test]
+    fn parse_withdrawn() {
+        let withdrawn = ADVISORY.replace("[versions]", "withdrawn = \"2022-02-01\"\n\n[versions]");
+        assert_none!(parse_advisory(&withdrawn).unwrap());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_err!(parse_advisory("# Not an advisory"));
+        assert_err!(parse_advisory(&ADVISORY.replace(">= 1.2.0", "not a req")));
+    }
+
+    #[test]
+    fn import_links_affected_versions() {
+        let conn = crate::db::test_conn();
+        let user = NewUser::new(2, "login", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), &conn)
+            .unwrap();
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create_or_update(&conn, user.id, None)
+        .unwrap();
+        let versions = ["0.1.0", "1.0.0", "1.2.0"]
+            .iter()
+            .map(|num| {
+                NewVersion::new(
+                    krate.id,
+                    &semver::Version::parse(num).unwrap(),
+                    &HashMap::new(),
+                    None,
+                    None,
+                    0,
+                    user.id,
+                )
+                .unwrap()
+                .save(&conn, "someone@example.com")
+                .unwrap()
+            })
+            .collect::<Vec<Version>>();
+
+        let advisory = parse_advisory(ADVISORY).unwrap().unwrap();
+        import(&conn, &[advisory]).unwrap();
+        // Importing twice must not fail or duplicate anything
+        let advisory = parse_advisory(ADVISORY).unwrap().unwrap();
+        import(&conn, &[advisory]).unwrap();
+
+        let ids = Advisory::ids_for_versions(&conn, &versions).unwrap();
+        assert_eq!(
+            ids,
+            vec![vec![], vec!["RUSTSEC-2022-0001".to_string()], vec![]]
+        );
+
+        // Advisories that disappeared from the database are removed
+        import(&conn, &[]).unwrap();
+        let ids = Advisory::ids_for_versions(&conn, &versions).unwrap();
+        assert_eq!(ids, vec![Vec::<String>::new(); 3]);
+    }
+}
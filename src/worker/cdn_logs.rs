@@ -0,0 +1,237 @@
+//! Import the downloads counted in CDN and mirror access logs.
+
+use std::collections::{HashMap, HashSet};
+
+use diesel::dsl::*;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::cdn_logs::{self, DownloadCounts, LogFormat};
+use crate::schema::{cdn_log_imports, crates, version_downloads, versions};
+
+/// The number of `version_downloads` rows upserted per query.
+const INSERT_BATCH_SIZE: usize = 1000;
+
+/// Adds the downloads counted in the log file at `path` in storage to
+/// `version_downloads`.
+///
+/// Every log file is only imported once. Files that were imported before are
+/// skipped, which makes it safe to enqueue the same file several times.
+#[swirl::background_job]
+pub fn import_cdn_log(
+    conn: &PgConnection,
+    env: &Environment,
+    path: String,
+    format: LogFormat,
+) -> Result<(), PerformError> {
+    let imported: bool = select(exists(cdn_log_imports::table.find(&path))).get_result(conn)?;
+    if imported {
+        println!("Skipping {path}, it was already imported");
+        return Ok(());
+    }
+
+    let content = env
+        .uploader
+        .download(env.http_client(), &path)?
+        .ok_or_else(|| format!("log file {path} does not exist"))?;
+    let content = cdn_logs::decompress(&content)?;
+    let log = cdn_logs::parse(format, &String::from_utf8_lossy(&content));
+
+    match record_import(conn, &path, format, &log.downloads)? {
+        Some(downloads) => println!(
+            "Imported {downloads} downloads from {path} ({} invalid lines)",
+            log.invalid_lines
+        ),
+        None => println!("Skipping {path}, it was imported concurrently"),
+    }
+    Ok(())
+}
+
+/// Marks the log file at `path` as imported and adds its downloads, in a
+/// single transaction, so that a failed import can be retried. Returns `None`
+/// if the file was already imported.
+fn record_import(
+    conn: &PgConnection,
+    path: &str,
+    format: LogFormat,
+    downloads: &DownloadCounts,
+) -> QueryResult<Option<i32>> {
+    conn.transaction(|| {
+        let inserted = insert_into(cdn_log_imports::table)
+            .values((
+                cdn_log_imports::path.eq(path),
+                cdn_log_imports::format.eq(format.as_str()),
+                cdn_log_imports::downloads.eq(0),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        let downloads = import(conn, downloads)?;
+        diesel::update(cdn_log_imports::table.find(path))
+            .set(cdn_log_imports::downloads.eq(downloads))
+            .execute(conn)?;
+        Ok(Some(downloads))
+    })
+}
+
+/// Adds the downloads to `version_downloads`, and returns the number of
+/// downloads that belonged to known versions.
+fn import(conn: &PgConnection, downloads: &DownloadCounts) -> QueryResult<i32> {
+    let names = downloads
+        .keys()
+        .map(|(name, _, _)| name.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let version_ids: HashMap<(String, String), i32> = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq_any(names))
+        .select((crates::name, versions::num, versions::id))
+        .load::<(String, String, i32)>(conn)?
+        .into_iter()
+        .map(|(name, num, id)| ((name, num), id))
+        .collect();
+
+    let mut total = 0;
+    let mut values = Vec::with_capacity(downloads.len());
+    for ((name, num, date), count) in downloads {
+        if let Some(version_id) = version_ids.get(&(name.clone(), num.clone())) {
+            total += count;
+            values.push((
+                version_downloads::version_id.eq(*version_id),
+                version_downloads::downloads.eq(*count),
+                version_downloads::date.eq(*date),
+            ));
+        }
+    }
+
+    for chunk in values.chunks(INSERT_BATCH_SIZE) {
+        // Rows of past days may already be processed by `update_downloads`,
+        // so they have to be marked as unprocessed again for the new
+        // downloads to be counted.
+        insert_into(version_downloads::table)
+            .values(chunk)
+            .on_conflict((version_downloads::version_id, version_downloads::date))
+            .do_update()
+            .set((
+                version_downloads::downloads
+                    .eq(version_downloads::downloads + excluded(version_downloads::downloads)),
+                version_downloads::processed.eq(false),
+            ))
+            .execute(conn)?;
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewUser, NewVersion, Version};
+    use chrono::NaiveDate;
+
+    fn version(conn: &PgConnection) -> Version {
+        let user = NewUser::new(2, "login", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap();
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create_or_update(conn, user.id, None)
+        .unwrap();
+        NewVersion::new(
+            krate.id,
+            &semver::Version::parse("1.0.0").unwrap(),
+            &HashMap::new(),
+            None,
+            None,
+            0,
+            user.id,
+        )
+        .unwrap()
+        .save(conn, "someone@example.com")
+        .unwrap()
+    }
+
+    #[test]
+    fn import_adds_to_existing_downloads() {
+        let conn = crate::db::test_conn();
+        let version = version(&conn);
+        let date = NaiveDate::from_ymd(2022, 8, 8);
+
+        insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version.id),
+                version_downloads::downloads.eq(5),
+                version_downloads::date.eq(date),
+                version_downloads::processed.eq(true),
+            ))
+            .execute(&conn)
+            .unwrap();
+
+        let mut downloads = DownloadCounts::new();
+        downloads.insert(("foo".into(), "1.0.0".into(), date), 3);
+        downloads.insert(("foo".into(), "9.9.9".into(), date), 7);
+        downloads.insert(("unknown".into(), "1.0.0".into(), date), 7);
+        assert_eq!(import(&conn, &downloads).unwrap(), 3);
+
+        let (downloads, processed): (i32, bool) = version_downloads::table
+            .find((version.id, date))
+            .select((version_downloads::downloads, version_downloads::processed))
+            .first(&conn)
+            .unwrap();
+        assert_eq!(downloads, 8);
+        assert!(!processed);
+    }
+
+    #[test]
+    fn failed_imports_can_be_retried() {
+        let conn = crate::db::test_conn();
+        let version = version(&conn);
+        let date = NaiveDate::from_ymd(2022, 8, 8);
+        let path = "cdn-logs/2022-08-08.log";
+
+        // Adding to this count overflows, so the import fails
+        insert_into(version_downloads::table)
+            .values((
+                version_downloads::version_id.eq(version.id),
+                version_downloads::downloads.eq(i32::MAX),
+                version_downloads::date.eq(date),
+            ))
+            .execute(&conn)
+            .unwrap();
+
+        let mut downloads = DownloadCounts::new();
+        downloads.insert(("foo".into(), "1.0.0".into(), date), 3);
+        assert_err!(record_import(&conn, path, LogFormat::Combined, &downloads));
+
+        let imports: i64 = cdn_log_imports::table.count().get_result(&conn).unwrap();
+        assert_eq!(imports, 0);
+
+        diesel::update(version_downloads::table.find((version.id, date)))
+            .set(version_downloads::downloads.eq(5))
+            .execute(&conn)
+            .unwrap();
+        assert_eq!(
+            record_import(&conn, path, LogFormat::Combined, &downloads).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            record_import(&conn, path, LogFormat::Combined, &downloads).unwrap(),
+            None
+        );
+
+        let downloads: i32 = version_downloads::table
+            .find((version.id, date))
+            .select(version_downloads::downloads)
+            .first(&conn)
+            .unwrap();
+        assert_eq!(downloads, 8);
+    }
+}
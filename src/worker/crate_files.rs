@@ -0,0 +1,46 @@
+//! Store the files inside uploaded `.crate` archives, so that they can be
+//! served without extracting the archive again.
+
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::crate_files::extract;
+
+#[swirl::background_job]
+pub fn cache_crate_files(
+    env: &Environment,
+    crate_name: String,
+    version: String,
+    max_unpack: u64,
+) -> Result<(), PerformError> {
+    let client = env.http_client();
+
+    let existing = env
+        .uploader
+        .download_crate_files_listing(client, &crate_name, &version)?;
+    if existing.is_some() {
+        return Ok(());
+    }
+
+    let tarball = env
+        .uploader
+        .download_crate(client, &crate_name, &version)?
+        .ok_or_else(|| format!("crate file of {crate_name} {version} is missing"))?;
+
+    let pkg_name = format!("{crate_name}-{version}");
+    let files = extract(&tarball, &pkg_name, max_unpack)?;
+
+    let listing = files.iter().map(|(file, _)| file).collect::<Vec<_>>();
+    let listing = serde_json::to_string(&listing)?;
+
+    for (file, content) in files {
+        env.uploader
+            .upload_crate_file(client, &crate_name, &version, &file.path, content)?;
+    }
+
+    // The listing is uploaded last, since its existence marks the files as cached
+    env.uploader
+        .upload_crate_files_listing(client, &crate_name, &version, listing)?;
+
+    Ok(())
+}
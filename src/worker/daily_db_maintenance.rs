@@ -4,16 +4,151 @@
 /// Because the `version_downloads` table includes years of historical data, we can accumulate
 /// a *lot* of garbage before an auto-vacuum is run.
 ///
-/// We only need to keep 90 days of entries in `version_downloads`. Once we have a mechanism to
-/// archive daily download counts and drop historical data, we can drop this task and rely on
-/// auto-vacuum again.
-use diesel::{sql_query, RunQueryDsl};
+/// Daily download counts older than 90 days are rolled up into `version_downloads_monthly` and
+/// pruned before vacuuming, so the table no longer grows indefinitely.
+///
+/// The number of dependents of each crate, which is used to rank search results, is also
+/// refreshed here as it doesn't need to be more accurate than that.
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use diesel::{select, sql_query};
 use swirl::PerformError;
 
+/// Number of days of daily download counts kept in the `version_downloads` table.
+const DAILY_RETENTION_DAYS: i32 = 90;
+
 #[swirl::background_job]
 pub fn daily_db_maintenance(conn: &PgConnection) -> Result<(), PerformError> {
+    println!("Archiving old version_downloads");
+    let months = archive(conn)?;
+    println!("Archived old version_downloads into {months} monthly rows");
+    let pruned = prune_agent_downloads(conn)?;
+    println!("Pruned {pruned} old version_downloads_by_agent rows");
+
+    no_arg_sql_function!(refresh_crate_dependent_counts, ());
+    select(refresh_crate_dependent_counts).execute(conn)?;
+    println!("Finished refreshing crate_dependent_counts");
+
     println!("Running VACUUM on version_downloads table");
     sql_query("VACUUM version_downloads;").execute(conn)?;
     println!("Finished running VACUUM on version_downloads table");
     Ok(())
 }
+
+/// Moves the processed daily download counts older than `DAILY_RETENTION_DAYS` into the
+/// `version_downloads_monthly` table, returning the number of monthly rows that were updated.
+///
+/// Deleting the daily rows and adding them to the monthly totals happens in a single statement,
+/// so the downloads are never counted twice or lost when the job is interrupted. Rows that are not
+/// processed yet are kept until `update_downloads` has counted them.
+fn archive(conn: &PgConnection) -> QueryResult<usize> {
+    sql_query(
+        "WITH archived AS ( \
+            DELETE FROM version_downloads \
+            WHERE date < CURRENT_DATE - $1 \
+            AND processed \
+            RETURNING version_id, date, downloads \
+        ) \
+        INSERT INTO version_downloads_monthly (version_id, month, downloads) \
+        SELECT version_id, date_trunc('month', date)::date, SUM(downloads) \
+        FROM archived \
+        GROUP BY 1, 2 \
+        ON CONFLICT (version_id, month) DO UPDATE \
+        SET downloads = version_downloads_monthly.downloads + EXCLUDED.downloads",
+    )
+    .bind::<Integer, _>(DAILY_RETENTION_DAYS)
+    .execute(conn)
+}
+
+/// Deletes the breakdown of downloads by `User-Agent` older than `DAILY_RETENTION_DAYS`, which
+/// is not archived.
+fn prune_agent_downloads(conn: &PgConnection) -> QueryResult<usize> {
+    sql_query("DELETE FROM version_downloads_by_agent WHERE date < CURRENT_DATE - $1")
+        .bind::<Integer, _>(DAILY_RETENTION_DAYS)
+        .execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewUser, NewVersion};
+    use crate::schema::{version_downloads, version_downloads_monthly};
+    use chrono::{Datelike, Duration, NaiveDate, Utc};
+    use std::collections::HashMap;
+
+    #[test]
+    fn archives_old_processed_downloads() {
+        let conn = crate::db::test_conn();
+        let user = NewUser::new(2, "login", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), &conn)
+            .unwrap();
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create_or_update(&conn, user.id, None)
+        .unwrap();
+        let version = NewVersion::new(
+            krate.id,
+            &semver::Version::parse("1.0.0").unwrap(),
+            &HashMap::new(),
+            None,
+            None,
+            0,
+            user.id,
+        )
+        .unwrap()
+        .save(&conn, "someone@example.com")
+        .unwrap();
+
+        let today = Utc::today().naive_utc();
+        let old = NaiveDate::from_ymd(today.year() - 1, today.month(), 1);
+        let insert = |date: NaiveDate, downloads: i32, processed: bool| {
+            diesel::insert_into(version_downloads::table)
+                .values((
+                    version_downloads::version_id.eq(version.id),
+                    version_downloads::date.eq(date),
+                    version_downloads::downloads.eq(downloads),
+                    version_downloads::processed.eq(processed),
+                ))
+                .execute(&conn)
+                .unwrap();
+        };
+        insert(old, 5, true);
+        insert(old + Duration::days(1), 7, true);
+        insert(old + Duration::days(2), 3, false);
+        insert(today, 11, false);
+
+        let monthly = || {
+            version_downloads_monthly::table
+                .select((
+                    version_downloads_monthly::month,
+                    version_downloads_monthly::downloads,
+                ))
+                .load::<(NaiveDate, i64)>(&conn)
+                .unwrap()
+        };
+        let daily = || {
+            version_downloads::table
+                .select(version_downloads::date)
+                .order(version_downloads::date)
+                .load::<NaiveDate>(&conn)
+                .unwrap()
+        };
+
+        assert_eq!(archive(&conn).unwrap(), 1);
+        assert_eq!(monthly(), vec![(old, 12)]);
+        assert_eq!(daily(), vec![old + Duration::days(2), today]);
+
+        // Archiving again adds to the existing monthly totals.
+        diesel::update(version_downloads::table)
+            .set(version_downloads::processed.eq(true))
+            .filter(version_downloads::date.lt(today))
+            .execute(&conn)
+            .unwrap();
+        assert_eq!(archive(&conn).unwrap(), 1);
+        assert_eq!(monthly(), vec![(old, 15)]);
+        assert_eq!(daily(), vec![today]);
+    }
+}
@@ -3,14 +3,24 @@
 //! the daily database maintenance, but also operations like rendering READMEs
 //! and uploading them to S3.
 
+mod advisories;
+mod cdn_logs;
+mod crate_files;
 mod daily_db_maintenance;
 pub mod dump_db;
 mod git;
 mod readmes;
+mod team_memberships;
 mod update_downloads;
+mod webhooks;
 
+pub use advisories::import_advisories;
+pub use cdn_logs::import_cdn_log;
+pub use crate_files::cache_crate_files;
 pub use daily_db_maintenance::daily_db_maintenance;
 pub use dump_db::dump_db;
 pub use git::{add_crate, squash_index, sync_yanked};
 pub use readmes::render_and_upload_readme;
+pub use team_memberships::{enqueue_refresh_team_memberships, refresh_team_memberships};
 pub use update_downloads::update_downloads;
+pub use webhooks::{deliver_webhook, notify_webhooks};
@@ -0,0 +1,91 @@
+//! Refresh the cached GitHub team memberships before they expire, so that
+//! publishing rarely has to wait for GitHub.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use swirl::{Job, PerformError};
+
+use crate::background_jobs::Environment;
+use crate::config::{self, TeamMembershipCacheConfig};
+use crate::github::RealGitHubClient;
+use crate::models::{team_with_gh_id_contains_user, CachedTeamMembership};
+use crate::schema::background_jobs;
+
+/// Maximum number of memberships refreshed by a single run of the job, to
+/// bound the number of requests made to GitHub. Memberships that are left
+/// over are refreshed by the next run.
+const MAX_REFRESHES_PER_RUN: i64 = 1000;
+
+#[swirl::background_job]
+pub fn refresh_team_memberships(
+    env: &Environment,
+    conn: &PgConnection,
+    gh_base_url: String,
+    cache_config: TeamMembershipCacheConfig,
+) -> Result<(), PerformError> {
+    let github = RealGitHubClient::new(Some(env.http_client().clone()), gh_base_url);
+    let now = Utc::now().naive_utc();
+
+    // Memberships that weren't used for longer than they could be used when
+    // GitHub is unreachable belong to users that stopped publishing, so they're
+    // dropped instead of being refreshed forever.
+    let unused_cutoff = now - chrono::Duration::from_std(cache_config.max_staleness)?;
+    let deleted = CachedTeamMembership::delete_unused_since(conn, unused_cutoff)?;
+    println!("Deleted {deleted} unused cached team memberships");
+
+    // Memberships are refreshed once half of their TTL has passed, so that a
+    // run of the job every few minutes keeps them fresh.
+    let cutoff = now - chrono::Duration::from_std(cache_config.ttl / 2)?;
+    let memberships = CachedTeamMembership::checked_before(conn, cutoff, MAX_REFRESHES_PER_RUN)?;
+
+    let mut refreshed = 0;
+    for (membership, team, user) in &memberships {
+        let (org_id, github_id) = match (team.org_id, team.github_id) {
+            (Some(org_id), Some(github_id)) => (org_id, github_id),
+            _ => continue,
+        };
+
+        match team_with_gh_id_contains_user(&github, org_id, github_id, user) {
+            Ok(is_member) => {
+                CachedTeamMembership::record(
+                    conn,
+                    membership.team_id,
+                    membership.user_id,
+                    is_member,
+                )?;
+                refreshed += 1;
+            }
+            Err(error) => {
+                println!(
+                    "Could not refresh the membership of {} in {}: {error}",
+                    user.gh_login, team.login
+                );
+            }
+        }
+    }
+
+    println!(
+        "Refreshed {refreshed} of {} cached team memberships",
+        memberships.len()
+    );
+    Ok(())
+}
+
+/// Enqueues a `refresh_team_memberships` job with the given config, unless one
+/// is already queued. Returns whether a job was enqueued.
+pub fn enqueue_refresh_team_memberships(
+    conn: &PgConnection,
+    config: &config::Server,
+) -> anyhow::Result<bool> {
+    let count: i64 = background_jobs::table
+        .filter(background_jobs::job_type.eq("refresh_team_memberships"))
+        .count()
+        .get_result(conn)?;
+    if count > 0 {
+        return Ok(false);
+    }
+
+    refresh_team_memberships(config.gh_base_url.clone(), config.team_membership_cache)
+        .enqueue(conn)?;
+    Ok(true)
+}
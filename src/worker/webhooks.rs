@@ -0,0 +1,285 @@
+//! Deliver crate events to the webhooks configured by the crate owners.
+//!
+//! Every request body is signed with the secret of the webhook, and the
+//! signature is sent in the `X-Crates-Signature-256` header as
+//! `sha256=<hex encoded HMAC-SHA256>`.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use diesel::prelude::*;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::header;
+use reqwest::redirect::Policy;
+use sha2::Sha256;
+use swirl::{Job, PerformError};
+use url::Url;
+
+use crate::models::{
+    check_webhook_url, resolve_webhook_url, CrateWebhook, WebhookDelivery, WebhookPayload,
+    WebhookResolveError,
+};
+use crate::schema::{crate_webhooks, webhook_deliveries};
+use crate::util::errors::AppResult;
+
+/// How often a delivery is attempted before it is recorded as failed.
+const MAX_ATTEMPTS: i32 = 3;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Records a delivery of `payload` for every webhook of the crate and enqueues
+/// the background jobs sending them.
+pub fn notify_webhooks(
+    conn: &PgConnection,
+    crate_id: i32,
+    payload: &WebhookPayload,
+) -> AppResult<()> {
+    for delivery_id in WebhookDelivery::create_for_crate(conn, crate_id, payload)? {
+        deliver_webhook(delivery_id).enqueue(conn)?;
+    }
+    Ok(())
+}
+
+/// Sends a single webhook delivery.
+///
+/// Every run of the job makes a single request and records its outcome. If the
+/// request failed, the job returns an error so that it is retried with the
+/// backoff of the job queue, until `MAX_ATTEMPTS` requests have been made.
+/// Webhooks whose host resolves to a non-public address are never retried.
+#[swirl::background_job]
+pub fn deliver_webhook(conn: &PgConnection, delivery_id: i64) -> Result<(), PerformError> {
+    deliver(conn, delivery_id, send)
+}
+
+/// Runs a delivery attempt, using `send` to make the request.
+fn deliver(
+    conn: &PgConnection,
+    delivery_id: i64,
+    send: impl FnOnce(
+        &WebhookDelivery,
+        &CrateWebhook,
+        Url,
+        &[SocketAddr],
+    ) -> Result<(Option<i32>, Option<String>), PerformError>,
+) -> Result<(), PerformError> {
+    let (delivery, webhook): (WebhookDelivery, CrateWebhook) = webhook_deliveries::table
+        .find(delivery_id)
+        .inner_join(crate_webhooks::table)
+        .first(conn)?;
+
+    if delivery.delivered_at.is_some() || delivery.attempts >= MAX_ATTEMPTS {
+        return Ok(());
+    }
+
+    let attempts = delivery.attempts + 1;
+    let url = match check_webhook_url(&webhook.url) {
+        Ok(url) => url,
+        Err(error) => {
+            record_attempt(conn, &delivery, attempts, None, Some(error))?;
+            return Ok(());
+        }
+    };
+    let (response_status, error) = match resolve_webhook_url(&url) {
+        Ok(addrs) => send(&delivery, &webhook, url, &addrs)?,
+        Err(WebhookResolveError::Unresolved(error)) => (None, Some(error)),
+        Err(WebhookResolveError::NonPublic(error)) => {
+            record_attempt(conn, &delivery, attempts, None, Some(error))?;
+            return Ok(());
+        }
+    };
+
+    let failed = error.is_some();
+    record_attempt(conn, &delivery, attempts, response_status, error)?;
+
+    if failed && attempts < MAX_ATTEMPTS {
+        return Err(format!("Delivery {delivery_id} failed, it will be retried").into());
+    }
+    Ok(())
+}
+
+/// Sends the payload of `delivery` to the webhook and returns the response
+/// status and the error, if any.
+///
+/// The request goes to the addresses that were checked by `resolve_webhook_url`,
+/// and redirects are not followed, so that the host can't point the worker to
+/// an internal address after the check.
+fn send(
+    delivery: &WebhookDelivery,
+    webhook: &CrateWebhook,
+    url: Url,
+    addrs: &[SocketAddr],
+) -> Result<(Option<i32>, Option<String>), PerformError> {
+    let body = serde_json::to_vec(&delivery.payload)?;
+    let signature = format!("sha256={}", sign(&webhook.secret, &body));
+
+    let mut builder = Client::builder()
+        .redirect(Policy::none())
+        .timeout(REQUEST_TIMEOUT);
+    if let Some(domain) = url.domain() {
+        for addr in addrs {
+            builder = builder.resolve(domain, *addr);
+        }
+    }
+
+    let result = builder
+        .build()?
+        .post(url)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header("X-Crates-Event", &delivery.event)
+        .header("X-Crates-Delivery", delivery.id.to_string())
+        .header("X-Crates-Signature-256", &signature)
+        .body(body)
+        .send();
+
+    Ok(match result {
+        Ok(response) if response.status().is_success() => {
+            (Some(response.status().as_u16() as i32), None)
+        }
+        Ok(response) => (
+            Some(response.status().as_u16() as i32),
+            Some(format!("Unexpected response status {}", response.status())),
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    })
+}
+
+fn record_attempt(
+    conn: &PgConnection,
+    delivery: &WebhookDelivery,
+    attempts: i32,
+    response_status: Option<i32>,
+    error: Option<String>,
+) -> QueryResult<()> {
+    let delivered_at = if error.is_none() {
+        Some(chrono::Utc::now().naive_utc())
+    } else {
+        None
+    };
+
+    diesel::update(delivery)
+        .set((
+            webhook_deliveries::attempts.eq(attempts),
+            webhook_deliveries::response_status.eq(response_status),
+            webhook_deliveries::error.eq(error),
+            webhook_deliveries::delivered_at.eq(delivered_at),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Returns the hex encoded HMAC-SHA256 of `body` using `secret` as the key.
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::Emails;
+    use crate::models::{NewCrate, NewCrateWebhook, NewUser, WebhookEvent};
+
+    fn delivery(conn: &PgConnection, url: &str) -> i64 {
+        let user = NewUser::new(2, "login", None, None, "access_token")
+            .create_or_update(None, &Emails::new_in_memory(), conn)
+            .unwrap();
+        let krate = NewCrate {
+            name: "foo",
+            ..Default::default()
+        }
+        .create_or_update(conn, user.id, None)
+        .unwrap();
+        NewCrateWebhook {
+            crate_id: krate.id,
+            url,
+            secret: "s3cr3t",
+            created_by: user.id,
+        }
+        .create(conn)
+        .unwrap();
+
+        let payload = WebhookPayload::new(WebhookEvent::Publish, "foo", "login");
+        WebhookDelivery::create_for_crate(conn, krate.id, &payload).unwrap()[0]
+    }
+
+    fn delivery_state(conn: &PgConnection, delivery_id: i64) -> (i32, Option<String>, bool) {
+        let delivery: WebhookDelivery = webhook_deliveries::table
+            .find(delivery_id)
+            .first(conn)
+            .unwrap();
+        (
+            delivery.attempts,
+            delivery.error,
+            delivery.delivered_at.is_some(),
+        )
+    }
+
+    #[test]
+    fn failed_deliveries_are_retried() {
+        let conn = crate::db::test_conn();
+        let delivery_id = delivery(&conn, "https://93.184.216.34/hook");
+        let failure = |_: &_, _: &_, _, _: &_| Ok((Some(500), Some("Unexpected".to_string())));
+
+        // Failed attempts return an error, so that the job queue retries them with a backoff
+        assert_err!(deliver(&conn, delivery_id, failure));
+        assert_eq!(
+            delivery_state(&conn, delivery_id),
+            (1, Some("Unexpected".into()), false)
+        );
+        assert_err!(deliver(&conn, delivery_id, failure));
+        assert_eq!(
+            delivery_state(&conn, delivery_id),
+            (2, Some("Unexpected".into()), false)
+        );
+
+        // The last attempt is recorded as failed without being retried
+        assert_ok!(deliver(&conn, delivery_id, failure));
+        assert_eq!(
+            delivery_state(&conn, delivery_id),
+            (3, Some("Unexpected".into()), false)
+        );
+        assert_ok!(deliver(&conn, delivery_id, |_, _, _, _| unreachable!()));
+        assert_eq!(delivery_state(&conn, delivery_id).0, 3);
+    }
+
+    #[test]
+    fn retried_deliveries_can_succeed() {
+        let conn = crate::db::test_conn();
+        let delivery_id = delivery(&conn, "https://93.184.216.34/hook");
+
+        assert_err!(deliver(&conn, delivery_id, |_, _, _, _| Ok((
+            None,
+            Some("connection refused".into())
+        ))));
+        assert_ok!(deliver(&conn, delivery_id, |_, _, url, addrs| {
+            assert_eq!(url.as_str(), "https://93.184.216.34/hook");
+            assert_eq!(addrs, ["93.184.216.34:443".parse::<SocketAddr>().unwrap()]);
+            Ok((Some(200), None))
+        }));
+        assert_eq!(delivery_state(&conn, delivery_id), (2, None, true));
+    }
+
+    #[test]
+    fn unresolved_hosts_are_retried() {
+        let conn = crate::db::test_conn();
+        let delivery_id = delivery(&conn, "https://does-not-exist.invalid/hook");
+
+        assert_err!(deliver(&conn, delivery_id, |_, _, _, _| unreachable!()));
+        let (attempts, error, _) = delivery_state(&conn, delivery_id);
+        assert_eq!(attempts, 1);
+        assert_eq!(
+            error.unwrap(),
+            "could not resolve the host `does-not-exist.invalid`"
+        );
+    }
+
+    #[test]
+    fn signature() {
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+}